@@ -114,9 +114,92 @@ pub fn get_activator<'p>(
         .env_vars
         .extend(get_environment_variables(environment));
 
+    // Add the environment variables from the manifest. These can reference variables of the
+    // activated environment.
+    let activation_env = environment.activation_env(Some(platform));
+    if !activation_env.is_empty() {
+        let mut env = activated_env_vars(&activator);
+        for (name, value) in activation_env {
+            let value = expand_env_vars(&value, &env);
+            env.insert(name.clone(), value.clone());
+            activator.env_vars.insert(name, value);
+        }
+    }
+
     Ok(activator)
 }
 
+/// Returns the environment variables as they will be after running the activator, without running
+/// the activation scripts.
+fn activated_env_vars(activator: &Activator<ShellEnum>) -> HashMap<String, String> {
+    let mut env: HashMap<String, String> = std::env::vars().collect();
+
+    // The paths of the environment are prepended to the current `PATH`.
+    let current_path = std::env::var_os("PATH").unwrap_or_default();
+    let path = activator
+        .paths
+        .iter()
+        .cloned()
+        .chain(std::env::split_paths(&current_path));
+    if let Ok(path) = std::env::join_paths(path) {
+        env.insert("PATH".to_string(), path.to_string_lossy().into_owned());
+    }
+
+    env.insert(
+        "CONDA_PREFIX".to_string(),
+        activator.target_prefix.to_string_lossy().into_owned(),
+    );
+    env.extend(activator.env_vars.clone());
+    env
+}
+
+/// Expands references to environment variables (`$VAR` or `${VAR}`) in `value` with the values
+/// from `env`. References to variables that are not set expand to an empty string, like they do in
+/// most shells.
+pub(crate) fn expand_env_vars(value: &str, env: &HashMap<String, String>) -> String {
+    let lookup = |name: &str| {
+        env.get(name)
+            .or_else(|| {
+                // Environment variables are case-insensitive on Windows.
+                cfg!(windows)
+                    .then(|| {
+                        env.iter()
+                            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                            .map(|(_, value)| value)
+                    })
+                    .flatten()
+            })
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(idx) = rest.find('$') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+        if let Some(braced) = rest.strip_prefix('{') {
+            if let Some(end) = braced.find('}') {
+                result.push_str(&lookup(&braced[..end]));
+                rest = &braced[end + 1..];
+                continue;
+            }
+        }
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            // Not a reference to a variable, keep the `$` as is.
+            result.push('$');
+        } else {
+            result.push_str(&lookup(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
 /// Runs and caches the activation script.
 pub async fn run_activation(
     environment: &Environment<'_>,
@@ -278,4 +361,41 @@ mod tests {
             &project.version().as_ref().unwrap().to_string()
         );
     }
+
+    #[test]
+    fn test_expand_env_vars() {
+        let env = HashMap::from_iter([
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("FOO_BAR".to_string(), "foo".to_string()),
+        ]);
+        assert_eq!(expand_env_vars("$PATH:/opt/bin", &env), "/usr/bin:/opt/bin");
+        assert_eq!(expand_env_vars("${FOO_BAR}baz", &env), "foobaz");
+        assert_eq!(expand_env_vars("$FOO_BARbaz", &env), "");
+        assert_eq!(expand_env_vars("$MISSING-x", &env), "-x");
+        assert_eq!(expand_env_vars("costs 5$", &env), "costs 5$");
+        assert_eq!(expand_env_vars("${FOO_BAR", &env), "${FOO_BAR");
+    }
+
+    #[test]
+    fn test_activation_env_in_activator() {
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "osx-arm64", "win-64"]
+
+        [activation.env]
+        MY_VAR = "hello"
+        MY_PATH = "$CONDA_PREFIX/share:${MY_VAR}"
+        "#;
+        let project = Project::from_str(Path::new(""), project).unwrap();
+        let environment = project.default_environment();
+        let activator = get_activator(&environment, ShellEnum::default()).unwrap();
+
+        assert_eq!(activator.env_vars.get("MY_VAR").unwrap(), "hello");
+        assert_eq!(
+            activator.env_vars.get("MY_PATH").unwrap(),
+            &format!("{}/share:hello", environment.dir().to_string_lossy())
+        );
+    }
 }
@@ -22,6 +22,7 @@ use std::{
     path::PathBuf,
     str::FromStr,
 };
+use url::Url;
 
 /// Adds a dependency to the project
 #[derive(Parser, Debug, Default)]
@@ -56,6 +57,8 @@ pub struct Args {
     /// The `--pypi` option will add the package as a pypi-dependency this can not be mixed with the conda dependencies
     /// - `pixi add --pypi boto3`
     /// - `pixi add --pypi "boto3==version"
+    /// - `pixi add --pypi torch --index https://download.pytorch.org/whl/cu121`
+    /// - `pixi add --pypi "flask @ git+https://github.com/pallets/flask.git@3.0.2"`
     ///
     #[arg(required = true)]
     pub specs: Vec<String>,
@@ -76,6 +79,10 @@ pub struct Args {
     #[arg(long, conflicts_with_all = ["host", "build"])]
     pub pypi: bool,
 
+    /// The index the pypi dependencies should be fetched from, only used together with `--pypi`
+    #[arg(long, requires = "pypi")]
+    pub index: Option<Url>,
+
     /// Don't update lockfile, implies the no-install as well.
     #[clap(long, conflicts_with = "no_install")]
     pub no_lockfile_update: bool,
@@ -169,7 +176,8 @@ pub async fn execute(args: Args) -> miette::Result<()> {
                 .into_iter()
                 .map(|req| {
                     let name = rip::types::PackageName::from_str(req.name.as_str())?;
-                    let requirement = PyPiRequirement::from(req);
+                    let mut requirement = PyPiRequirement::from(req);
+                    requirement.index = args.index.clone();
                     Ok((name, requirement))
                 })
                 .collect::<Result<Vec<_>, rip::types::ParsePackageNameError>>()
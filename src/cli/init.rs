@@ -66,6 +66,7 @@ const GITIGNORE_TEMPLATE: &str = r#"# pixi environments
 
 const GITATTRIBUTES_TEMPLATE: &str = r#"# GitHub syntax highlighting
 pixi.lock linguist-language=YAML
+pixi-pypi.lock linguist-language=YAML
 
 "#;
 
@@ -143,7 +144,9 @@ pub async fn execute(args: Args) -> miette::Result<()> {
                 }
             }
         }
-        for spec in pypi_deps {
+        for (name, requirement) in pypi_deps {
+            // Local paths in the environment file are absolute, keep them relative to the project.
+            let spec = (name, requirement.with_path_relative_to(&dir));
             match &args.platforms.is_empty() {
                 true => project
                     .manifest
@@ -317,11 +320,17 @@ fn parse_dependencies(deps: Vec<CondaEnvDep>) -> miette::Result<ParsedDependenci
             CondaEnvDep::Pip { pip } => pip_deps.extend(
                 pip.into_iter()
                     .map(|mut dep| {
-                        let re = Regex::new(r"/([^/]+)\.git").unwrap();
-                        if let Some(caps) = re.captures(dep.as_str()) {
-                            let name= caps.get(1).unwrap().as_str().to_string();
-                            tracing::warn!("The dependency '{}' is a git repository, as that is not available in pixi we'll try to install it as a package with the name: {}", dep, name);
-                            dep = name;
+                        // A git url without a name, determine the name from the `egg` fragment or
+                        // the name of the repository.
+                        if dep.starts_with("git+") {
+                            let egg_re = Regex::new(r"#egg=([^&]+)").unwrap();
+                            let repo_re =
+                                Regex::new(r"/([^/@#]+?)(\.git)?(@[^/#]*)?(#.*)?$").unwrap();
+                            if let Some(caps) =
+                                egg_re.captures(&dep).or_else(|| repo_re.captures(&dep))
+                            {
+                                dep = format!("{} @ {dep}", &caps[1]);
+                            }
                         }
                         let req = pep508_rs::Requirement::from_str(&dep).into_diagnostic()?;
                         let name = rip::types::PackageName::from_str(req.name.as_str())?;
@@ -367,6 +376,7 @@ mod tests {
     use std::io::Read;
     use std::path::{Path, PathBuf};
     use tempfile::tempdir;
+    use url::Url;
 
     #[test]
     fn test_parse_conda_env_file() {
@@ -405,7 +415,7 @@ mod tests {
         channels.extend(
             conda_env_file_data
                 .channels()
-                .into_iter()
+                .iter()
                 .map(|c| Arc::new(Channel::from_str(c, &ChannelConfig::default()).unwrap())),
         );
         let channels = channels.into_iter().unique().collect::<Vec<_>>();
@@ -435,26 +445,21 @@ mod tests {
             vec![
                 (
                     PackageName::from_str("requests").unwrap(),
-                    PyPiRequirement {
-                        version: None,
-                        extras: None,
-                        index: None,
-                    }
+                    PyPiRequirement::default()
                 ),
                 (
-                    PackageName::from_str("DeepOBS").unwrap(),
+                    PackageName::from_str("deepobs").unwrap(),
                     PyPiRequirement {
-                        version: None,
-                        extras: None,
-                        index: None,
+                        git: Url::parse("https://git@github.com/fsschneider/DeepOBS.git").ok(),
+                        rev: Some("develop".to_string()),
+                        ..Default::default()
                     },
                 ),
                 (
                     PackageName::from_str("torch").unwrap(),
                     PyPiRequirement {
                         version: pep440_rs::VersionSpecifiers::from_str("==1.8.1").ok(),
-                        extras: None,
-                        index: None,
+                        ..Default::default()
                     }
                 ),
             ]
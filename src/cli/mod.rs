@@ -23,6 +23,8 @@ pub mod self_update;
 pub mod shell;
 pub mod shell_hook;
 pub mod task;
+pub mod update;
+pub mod upgrade;
 pub mod upload;
 
 #[derive(Parser, Debug)]
@@ -75,6 +77,8 @@ pub enum Command {
     Remove(remove::Args),
     SelfUpdate(self_update::Args),
     List(list::Args),
+    Update(update::Args),
+    Upgrade(upgrade::Args),
 }
 
 #[derive(Parser, Debug, Default, Copy, Clone)]
@@ -217,6 +221,8 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::Remove(cmd) => remove::execute(cmd).await,
         Command::SelfUpdate(cmd) => self_update::execute(cmd).await,
         Command::List(cmd) => list::execute(cmd).await,
+        Command::Update(cmd) => update::execute(cmd).await,
+        Command::Upgrade(cmd) => upgrade::execute(cmd).await,
     }
 }
 
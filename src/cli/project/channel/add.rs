@@ -19,6 +19,11 @@ pub struct Args {
     /// The name of the feature to add the channel to.
     #[clap(long, short)]
     pub feature: Option<String>,
+
+    /// The priority of the channel, channels with a higher priority are preferred. If the channel
+    /// is already present, its priority is updated.
+    #[clap(long, allow_negative_numbers = true)]
+    pub priority: Option<i32>,
 }
 
 pub async fn execute(mut project: Project, args: Args) -> miette::Result<()> {
@@ -42,8 +47,10 @@ pub async fn execute(mut project: Project, args: Args) -> miette::Result<()> {
         channels
             .clone()
             .into_iter()
-            .map(|(_name, channel)| channel)
-            .map(PrioritizedChannel::from_channel),
+            .map(|(_name, channel)| PrioritizedChannel {
+                channel,
+                priority: args.priority,
+            }),
         &feature_name,
     )?;
 
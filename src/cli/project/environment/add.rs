@@ -0,0 +1,35 @@
+use crate::project::manifest::EnvironmentName;
+use crate::Project;
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The name of the environment to add.
+    pub name: EnvironmentName,
+
+    /// The features that make up the environment, in order of priority. The default feature is
+    /// always included.
+    #[clap(long, short)]
+    pub feature: Vec<String>,
+
+    /// The solve group of the environment. The dependencies of all environments in the same solve
+    /// group are solved together.
+    #[clap(long)]
+    pub solve_group: Option<String>,
+}
+
+pub async fn execute(mut project: Project, args: Args) -> miette::Result<()> {
+    project
+        .manifest
+        .add_environment(&args.name, args.feature, args.solve_group)?;
+    project.save()?;
+
+    // Report back to the user
+    eprintln!(
+        "{}Added environment {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        args.name.fancy_display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use crate::Project;
+use itertools::Itertools;
+
+pub async fn execute(project: Project) -> miette::Result<()> {
+    for environment in project.environments() {
+        println!(
+            "{} {}",
+            console::style("Environment:").bold().bright(),
+            environment.name().fancy_display()
+        );
+        println!(
+            "- features: {}",
+            environment
+                .features(true)
+                .map(|feature| feature.name.fancy_display())
+                .format(", ")
+        );
+        if let Some(solve_group) = environment.solve_group() {
+            println!("- solve-group: {}", solve_group.name());
+        }
+    }
+    Ok(())
+}
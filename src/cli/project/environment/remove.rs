@@ -0,0 +1,23 @@
+use crate::project::manifest::EnvironmentName;
+use crate::Project;
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The name of the environment to remove.
+    pub name: EnvironmentName,
+}
+
+pub async fn execute(mut project: Project, args: Args) -> miette::Result<()> {
+    project.manifest.remove_environment(&args.name)?;
+    project.save()?;
+
+    // Report back to the user
+    eprintln!(
+        "{}Removed environment {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        args.name.fancy_display()
+    );
+
+    Ok(())
+}
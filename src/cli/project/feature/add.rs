@@ -0,0 +1,23 @@
+use crate::{FeatureName, Project};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The name of the feature to add.
+    pub name: String,
+}
+
+pub async fn execute(mut project: Project, args: Args) -> miette::Result<()> {
+    let name = FeatureName::Named(args.name);
+    project.manifest.add_feature(&name)?;
+    project.save()?;
+
+    // Report back to the user
+    eprintln!(
+        "{}Added feature {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        name.fancy_display()
+    );
+
+    Ok(())
+}
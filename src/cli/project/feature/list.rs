@@ -0,0 +1,25 @@
+use crate::Project;
+use itertools::Itertools;
+
+pub async fn execute(project: Project) -> miette::Result<()> {
+    let environments = project.environments();
+    for feature in project.manifest.parsed.features.values() {
+        if feature.is_default() {
+            continue;
+        }
+        println!(
+            "{} {}",
+            console::style("Feature:").bold().bright(),
+            feature.name.fancy_display()
+        );
+        let used_by = environments
+            .iter()
+            .filter(|env| env.features(false).any(|f| f.name == feature.name))
+            .map(|env| env.name().fancy_display())
+            .collect_vec();
+        if !used_by.is_empty() {
+            println!("- environments: {}", used_by.iter().format(", "));
+        }
+    }
+    Ok(())
+}
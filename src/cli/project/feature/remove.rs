@@ -0,0 +1,77 @@
+use crate::{FeatureName, Project};
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use std::io::IsTerminal;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The name of the feature to remove.
+    pub name: String,
+
+    /// Also remove the feature from the environments that include it.
+    #[clap(long)]
+    pub force: bool,
+}
+
+pub async fn execute(mut project: Project, args: Args) -> miette::Result<()> {
+    let name = FeatureName::Named(args.name);
+
+    // Offer to remove the feature from the environments that still include it.
+    let environments = project
+        .environments()
+        .into_iter()
+        .filter(|env| env.features(false).any(|feature| feature.name == name))
+        .map(|env| env.name().clone())
+        .collect_vec();
+    let remove_from_environments = args.force
+        || (!environments.is_empty()
+            && std::io::stdin().is_terminal()
+            && std::io::stderr().is_terminal()
+            && dialoguer::Confirm::new()
+                .with_prompt(format!(
+                    "The feature {} is used by the environments {}. Remove it from these environments?",
+                    name.fancy_display(),
+                    environments
+                        .iter()
+                        .map(|env| env.fancy_display())
+                        .format(", ")
+                ))
+                .default(false)
+                .interact()
+                .into_diagnostic()?);
+
+    if !environments.is_empty() && !remove_from_environments {
+        miette::bail!(
+            help = "pass `--force` to remove the feature from these environments as well",
+            "feature {} is used by the environments {}",
+            name.fancy_display(),
+            environments
+                .iter()
+                .map(|env| env.fancy_display())
+                .format(", ")
+        );
+    }
+
+    project
+        .manifest
+        .remove_feature(&name, remove_from_environments)?;
+    project.save()?;
+
+    // Report back to the user
+    eprintln!(
+        "{}Removed feature {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        name.fancy_display()
+    );
+    for environment in environments {
+        eprintln!(
+            "{}Removed feature {} from environment {}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            name.fancy_display(),
+            environment.fancy_display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use crate::project::manifest::{Target, TargetSelector};
+use crate::project::SpecType;
+use crate::{consts, FeatureName, Project};
+use clap::Parser;
+use itertools::Itertools;
+
+static WIDTH: usize = 19;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The name of the feature to show.
+    pub name: String,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let name = match args.name.as_str() {
+        consts::DEFAULT_FEATURE_NAME => FeatureName::Default,
+        _ => FeatureName::Named(args.name),
+    };
+    let feature = project
+        .manifest
+        .feature(&name)
+        .ok_or_else(|| miette::miette!("feature {} does not exist", name.fancy_display()))?;
+
+    let bold = console::Style::new().bold();
+    let print_list = |label: &str, items: Vec<String>| {
+        if !items.is_empty() {
+            println!(
+                "{:>WIDTH$}: {}",
+                bold.apply_to(label),
+                items.iter().format(", ")
+            );
+        }
+    };
+
+    println!(
+        "{:>WIDTH$}: {}",
+        bold.apply_to("Feature"),
+        name.fancy_display().bold()
+    );
+    print_list(
+        "Environments",
+        project
+            .environments()
+            .iter()
+            .filter(|env| env.features(true).any(|f| f.name == name))
+            .map(|env| env.name().fancy_display().to_string())
+            .collect(),
+    );
+    print_list(
+        "Channels",
+        feature
+            .channels
+            .iter()
+            .flatten()
+            .map(|channel| {
+                let channel_name = channel
+                    .channel
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| channel.channel.base_url.to_string());
+                match channel.priority {
+                    Some(priority) => format!("{channel_name} (priority {priority})"),
+                    None => channel_name,
+                }
+            })
+            .collect(),
+    );
+    print_list(
+        "Target platforms",
+        feature
+            .platforms
+            .iter()
+            .flat_map(|platforms| platforms.value.iter())
+            .map(|platform| platform.to_string())
+            .collect(),
+    );
+
+    // The entries of target specific tables are followed by their target.
+    let per_target = |entries: &dyn Fn(&Target) -> Vec<String>| {
+        feature
+            .targets
+            .iter()
+            .flat_map(|(target, selector)| {
+                entries(target)
+                    .into_iter()
+                    .map(move |entry| with_selector(entry, selector))
+            })
+            .collect_vec()
+    };
+    for (label, spec_type) in [
+        ("Dependencies", SpecType::Run),
+        ("Host dependencies", SpecType::Host),
+        ("Build dependencies", SpecType::Build),
+    ] {
+        print_list(
+            label,
+            per_target(&|target| {
+                target
+                    .dependencies
+                    .get(&spec_type)
+                    .into_iter()
+                    .flatten()
+                    .map(|(name, spec)| format!("{} {}", name.as_source(), spec))
+                    .collect()
+            }),
+        );
+    }
+    print_list(
+        "PyPI dependencies",
+        per_target(&|target| {
+            target
+                .pypi_dependencies
+                .iter()
+                .flatten()
+                .map(|(name, requirement)| {
+                    format!(
+                        "{} {}",
+                        name.as_str(),
+                        requirement.to_string().trim_matches('"')
+                    )
+                })
+                .collect()
+        }),
+    );
+    print_list(
+        "Tasks",
+        per_target(&|target| {
+            target
+                .tasks
+                .keys()
+                .sorted()
+                .map(|name| name.fancy_display().to_string())
+                .collect()
+        }),
+    );
+
+    let requirements = &feature.system_requirements;
+    print_list(
+        "System requirements",
+        [
+            requirements
+                .macos
+                .as_ref()
+                .map(|version| format!("macos {version}")),
+            requirements
+                .linux
+                .as_ref()
+                .map(|version| format!("linux {version}")),
+            requirements
+                .cuda
+                .as_ref()
+                .map(|version| format!("cuda {version}")),
+            requirements.libc.as_ref().map(|libc| {
+                let (family, version) = libc.family_and_version();
+                format!("libc {family} {version}")
+            }),
+            requirements
+                .archspec
+                .as_ref()
+                .map(|archspec| format!("archspec {archspec}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    );
+
+    Ok(())
+}
+
+/// Appends the target `selector` to an `entry` of a target specific table.
+fn with_selector(entry: String, selector: Option<&TargetSelector>) -> String {
+    match selector {
+        Some(selector) => format!("{entry} ({})", selector.to_string()),
+        None => entry,
+    }
+}
@@ -3,6 +3,8 @@ use std::path::PathBuf;
 
 pub mod channel;
 pub mod description;
+pub mod environment;
+pub mod feature;
 pub mod platform;
 pub mod version;
 
@@ -10,6 +12,8 @@ pub mod version;
 pub enum Command {
     Channel(channel::Args),
     Description(description::Args),
+    Environment(environment::Args),
+    Feature(feature::Args),
     Platform(platform::Args),
     Version(version::Args),
 }
@@ -28,6 +32,8 @@ pub async fn execute(cmd: Args) -> miette::Result<()> {
     match cmd.command {
         Command::Channel(args) => channel::execute(args).await?,
         Command::Description(args) => description::execute(args).await?,
+        Command::Environment(args) => environment::execute(args).await?,
+        Command::Feature(args) => feature::execute(args).await?,
         Command::Platform(args) => platform::execute(args).await?,
         Command::Version(args) => version::execute(args).await?,
     };
@@ -1,23 +1,33 @@
-use std::collections::hash_map::Entry;
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::convert::identity;
+use std::future::Future;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{collections::HashMap, path::PathBuf, string::String};
 
 use crate::consts;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use deno_task_shell::{pipe, ShellPipeReader, ShellPipeWriter, ShellState};
 use dialoguer::theme::ColorfulTheme;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
 use itertools::Itertools;
-use miette::{miette, Context, Diagnostic};
+use miette::{miette, Context, Diagnostic, IntoDiagnostic};
 use rattler_conda_types::Platform;
 
 use crate::activation::get_environment_variables;
 use crate::environment::verify_prefix_location_unchanged;
 use crate::project::errors::UnsupportedPlatformError;
 use crate::task::{
-    AmbiguousTask, ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory,
-    SearchEnvironments, TaskAndEnvironment, TaskGraph, TaskName,
+    AmbiguousTask, ExecutableTask, FileWatcher, InvalidWorkingDirectory, RunOutput,
+    SearchEnvironments, TaskAndEnvironment, TaskCacheError, TaskGraph, TaskGraphFormat, TaskId,
+    TaskName, TaskProcesses,
 };
+use crate::utils::env_file::{read_env_files, EnvFileError};
 use crate::Project;
 
 use crate::lock_file::LockFileDerivedData;
@@ -27,13 +37,23 @@ use crate::project::manifest::EnvironmentName;
 use crate::project::virtual_packages::verify_current_platform_has_required_virtual_packages;
 use crate::project::Environment;
 use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tracing::Level;
 
+/// The time to wait before retrying a failed task for the first time. The time is doubled for every
+/// following retry, up to [`MAX_RETRY_BACKOFF`].
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The maximum time to wait before retrying a failed task.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
 /// Runs task in project.
 #[derive(Parser, Debug, Default)]
-#[clap(trailing_var_arg = true, arg_required_else_help = true)]
+#[clap(trailing_var_arg = true)]
 pub struct Args {
-    /// The task you want to run in the projects environment.
+    /// The task you want to run in the projects environment. When omitted in an interactive
+    /// terminal, the task can be selected from a list of the tasks of the project.
     pub task: Vec<String>,
 
     /// The path to 'pixi.toml'
@@ -43,13 +63,67 @@ pub struct Args {
     #[clap(flatten)]
     pub lock_file_usage: super::LockFileUsageArgs,
 
+    /// The environment to run the task in. When used multiple times the task is run in each of
+    /// the environments, and a summary of the results is printed at the end.
+    #[arg(long, short)]
+    pub environment: Vec<String>,
+
+    /// Run the task in every environment that defines it, and print a summary of the results at
+    /// the end.
+    #[arg(long, conflicts_with_all = ["environment", "watch"])]
+    pub all_environments: bool,
+
+    /// The maximum number of tasks to execute at the same time. Tasks that don't depend on each
+    /// other are executed concurrently and their output is prefixed with the name of the task.
+    /// Defaults to 1, which executes the tasks one by one.
     #[arg(long, short)]
-    pub environment: Option<String>,
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Watch the files of the project and execute the tasks again when they change. If the tasks
+    /// define `inputs`, only these files are watched. Otherwise all files in the project are
+    /// watched except for the ones that are ignored by git. Can only be used with a single
+    /// environment.
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Print the tasks that would be executed, including their environment, working directory and
+    /// command, without installing or executing anything.
+    #[arg(long, conflicts_with = "watch")]
+    pub dry_run: bool,
+
+    /// The format in which the tasks are printed with `--dry-run`.
+    #[arg(long, value_enum, default_value_t, requires = "dry_run")]
+    pub format: TaskGraphFormat,
 }
 
 /// CLI entry point for `pixi run`
 /// When running the sigints are ignored and child can react to them. As it pleases.
 pub async fn execute(args: Args) -> miette::Result<()> {
+    // If one of the tasks failed with a non-zero exit code, we exit this parent process with the
+    // same code.
+    let exit_code = run(args, None).await?;
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+    Ok(())
+}
+
+/// Runs the tasks like `pixi run` does, but captures the output of the tasks instead of writing it
+/// to the output of this process. The exit code is returned instead of exiting this process.
+pub async fn execute_captured(args: Args) -> miette::Result<RunOutput> {
+    let output = CapturedOutput::default();
+    let exit_code = run(args, Some(&output)).await?;
+    Ok(output.into_run_output(exit_code))
+}
+
+/// Runs the tasks of `pixi run` and returns the exit code of the run. If `capture` is given, the
+/// output of the tasks is written to it.
+async fn run(mut args: Args, capture: Option<&CapturedOutput>) -> miette::Result<i32> {
+    // Without a task there is nothing to run unless the task can be selected interactively.
+    if args.task.is_empty() && !is_interactive() {
+        return Err(miette::Report::msg(missing_task_error()));
+    }
+
     // Load the project
     let project = Project::load_or_else_discover(args.manifest_path.as_deref())?;
 
@@ -62,36 +136,19 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             .as_path(),
     )?;
 
-    // Extract the passed in environment name.
-    let explicit_environment = args
-        .environment
-        .map(|n| EnvironmentName::from_str(n.as_str()))
-        .transpose()?
-        .map(|n| {
-            project
-                .environment(&n)
-                .ok_or_else(|| miette::miette!("unknown environment '{n}'"))
-        })
-        .transpose()?;
-
-    // Verify that the current platform has the required virtual packages for the environment.
-    if let Some(ref explicit_environment) = explicit_environment {
-        verify_current_platform_has_required_virtual_packages(explicit_environment)?;
-    }
-
-    // Ensure that the lock-file is up-to-date.
-    let mut lock_file = project
-        .up_to_date_lock_file(UpdateLockFileOptions {
-            lock_file_usage: args.lock_file_usage.into(),
-            ..UpdateLockFileOptions::default()
-        })
-        .await?;
-
     // Split 'task' into arguments if it's a single string, supporting commands like:
     // `"test 1 == 0 || echo failed"` or `"echo foo && echo bar"` or `"echo 'Hello World'"`
     // This prevents shell interpretation of pixi run inputs.
     // Use as-is if 'task' already contains multiple elements.
-    let task_args = if args.task.len() == 1 {
+    let task_args = if args.task.is_empty() {
+        let (task_name, environment) = select_task_interactive(&project, &args)?;
+        if let Some(environment) = environment {
+            if args.environment.is_empty() && !args.all_environments {
+                args.environment.push(environment.name().to_string());
+            }
+        }
+        vec![task_name.as_str().to_string()]
+    } else if args.task.len() == 1 {
         shlex::split(args.task[0].as_str())
             .ok_or(miette!("Could not split task, assuming non valid task"))?
     } else {
@@ -99,6 +156,35 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     };
     tracing::debug!("Task parsed from run command: {:?}", task_args);
 
+    // Extract the passed in environment names.
+    let mut explicit_environments = explicit_environments(
+        &project,
+        &args.environment,
+        args.all_environments,
+        &task_args,
+    )?;
+
+    // Run the task in each of the environments if multiple environments were requested.
+    if args.all_environments || explicit_environments.len() > 1 {
+        if args.watch {
+            miette::bail!("--watch can only be used with a single environment");
+        }
+        return execute_matrix(
+            &project,
+            &explicit_environments,
+            task_args,
+            MatrixOptions {
+                lock_file_usage: args.lock_file_usage,
+                jobs: args.jobs.map_or(1, NonZeroUsize::get),
+                dry_run: args.dry_run,
+                format: args.format,
+            },
+            capture,
+        )
+        .await;
+    }
+    let explicit_environment = explicit_environments.pop();
+
     // Construct a task graph from the input arguments
     let search_environment = SearchEnvironments::from_opt_env(
         &project,
@@ -111,65 +197,273 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
     tracing::info!("Task graph: {}", task_graph);
 
-    // Traverse the task graph in topological order and execute each individual task.
-    let mut task_idx = 0;
+    // Only print the tasks when doing a dry-run.
+    if args.dry_run {
+        print!("{}", task_graph.format(args.format));
+        return Ok(0);
+    }
+
+    // Ensure that the lock-file is up-to-date.
+    let mut lock_file = project
+        .up_to_date_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.lock_file_usage.into(),
+            ..UpdateLockFileOptions::default()
+        })
+        .await?;
+
+    // Activate the environments of the tasks upfront, because the tasks may be executed
+    // concurrently or repeatedly.
     let mut task_envs = HashMap::new();
-    for task_id in task_graph.topological_order() {
-        let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id);
+    activate_task_graph_environments(&mut lock_file, &task_graph, &mut task_envs).await?;
 
-        // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
-        // instantiate a prefix for an alias.
-        if !executable_task.task().is_executable() {
-            continue;
-        }
+    let jobs = args.jobs.map_or(1, NonZeroUsize::get);
+    if args.watch {
+        watch_task_graph(&project, &task_graph, &task_envs, jobs).await?;
+        return Ok(0);
+    }
 
-        // Showing which command is being run if the level and type allows it.
-        if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
-            if task_idx > 0 {
-                // Add a newline between task outputs
-                eprintln!();
+    // Execute the tasks.
+    let mut summary = TaskSummary::default();
+    let result = execute_task_graph(
+        &task_graph,
+        &task_envs,
+        jobs,
+        CancellationToken::new(),
+        false,
+        capture,
+        &mut summary,
+    )
+    .await;
+    summary.print();
+    match result {
+        Ok(_) => Ok(0),
+        Err(TaskExecutionError::NonZeroExitCode(code)) => {
+            if code == 127 {
+                command_not_found(&project, explicit_environment);
             }
-            eprintln!(
-                "{}{}{}{}{}",
-                console::Emoji("✨ ", ""),
-                console::style("Pixi task (").bold(),
-                executable_task
-                    .run_environment
-                    .name()
-                    .fancy_display()
-                    .bold(),
-                console::style("): ").bold(),
-                executable_task.display_command(),
-            );
+            Ok(code)
         }
+        Err(err) => Err(err.into()),
+    }
+}
 
-        // If we don't have a command environment yet, we need to compute it. We lazily compute the
-        // task environment because we only need the environment if a task is actually executed.
-        let task_env: &_ = match task_envs.entry(executable_task.run_environment.clone()) {
-            Entry::Occupied(env) => env.into_mut(),
-            Entry::Vacant(entry) => {
-                let command_env =
-                    get_task_env(&mut lock_file, &executable_task.run_environment).await?;
-                entry.insert(command_env)
-            }
-        };
+/// Returns the environments that were selected on the command line to run the task in.
+///
+/// With `all_environments` these are all the environments that define the task (the first of the
+/// `task_args`) and that can run on the current platform. If no environment defines the task, the
+/// arguments are executed as a command in all of these environments.
+pub fn explicit_environments<'p>(
+    project: &'p Project,
+    environment_names: &[String],
+    all_environments: bool,
+    task_args: &[String],
+) -> miette::Result<Vec<Environment<'p>>> {
+    if all_environments {
+        let platform = Some(Platform::current());
+        let runnable_environments = project
+            .environments()
+            .into_iter()
+            .filter(|env| {
+                env.tasks(platform, true).is_ok()
+                    && verify_current_platform_has_required_virtual_packages(env).is_ok()
+            })
+            .collect_vec();
+        if runnable_environments.is_empty() {
+            miette::bail!("none of the environments of the project can run on this platform");
+        }
 
-        // Execute the task itself within the command environment. If one of the tasks failed with
-        // a non-zero exit code, we exit this parent process with the same code.
-        match execute_task(&executable_task, task_env).await {
-            Ok(_) => {
-                task_idx += 1;
-            }
-            Err(TaskExecutionError::NonZeroExitCode(code)) => {
-                if code == 127 {
-                    command_not_found(&project, explicit_environment);
-                }
-                std::process::exit(code);
-            }
-            Err(err) => return Err(err.into()),
+        let task_name = task_args.first().map(|name| TaskName::from(name.clone()));
+        let environments_with_task = runnable_environments
+            .iter()
+            .filter(|env| {
+                task_name
+                    .as_ref()
+                    .is_some_and(|name| env.task(name, platform).is_ok())
+            })
+            .cloned()
+            .collect_vec();
+        return Ok(if environments_with_task.is_empty() {
+            runnable_environments
+        } else {
+            environments_with_task
+        });
+    }
+
+    let mut environments: Vec<Environment<'p>> = Vec::new();
+    for name in environment_names {
+        let name = EnvironmentName::from_str(name)?;
+        let environment = project
+            .environment(&name)
+            .ok_or_else(|| miette::miette!("unknown environment '{name}'"))?;
+
+        // Verify that the current platform has the required virtual packages for the environment.
+        verify_current_platform_has_required_virtual_packages(&environment)?;
+        if !environments.contains(&environment) {
+            environments.push(environment);
+        }
+    }
+    Ok(environments)
+}
+
+/// The options of `pixi run` that apply to running a task in multiple environments.
+struct MatrixOptions {
+    lock_file_usage: super::LockFileUsageArgs,
+    jobs: usize,
+    dry_run: bool,
+    format: TaskGraphFormat,
+}
+
+/// Runs the task in each of the `environments`, and prints whether the task passed or failed in
+/// each environment at the end. With more than one job the task is run in multiple environments at
+/// the same time, in which case the output of every task is prefixed with its name and
+/// environment. Returns 1 if the task failed in any environment.
+async fn execute_matrix<'p>(
+    project: &'p Project,
+    environments: &[Environment<'p>],
+    task_args: Vec<String>,
+    options: MatrixOptions,
+    capture: Option<&CapturedOutput>,
+) -> miette::Result<i32> {
+    // Construct a task graph for every environment
+    let mut task_graphs = Vec::with_capacity(environments.len());
+    for environment in environments {
+        let search_environment = SearchEnvironments::from_opt_env(
+            project,
+            Some(environment.clone()),
+            Some(Platform::current()),
+        )
+        .with_disambiguate_fn(disambiguate_task_interactive);
+        task_graphs.push(TaskGraph::from_cmd_args(
+            project,
+            &search_environment,
+            task_args.clone(),
+        )?);
+    }
+
+    // Only print the tasks when doing a dry-run.
+    if options.dry_run {
+        for task_graph in &task_graphs {
+            print!("{}", task_graph.format(options.format));
+        }
+        return Ok(0);
+    }
+
+    // Ensure that the lock-file is up-to-date and activate all environments upfront.
+    let mut lock_file = project
+        .up_to_date_lock_file(UpdateLockFileOptions {
+            lock_file_usage: options.lock_file_usage.into(),
+            ..UpdateLockFileOptions::default()
+        })
+        .await?;
+    let mut task_envs = HashMap::new();
+    for task_graph in &task_graphs {
+        activate_task_graph_environments(&mut lock_file, task_graph, &mut task_envs).await?;
+    }
+
+    // Run the task graphs, at most `jobs` at the same time. Every environment then runs one task at
+    // a time with prefixed output, so the output of the environments can be told apart. A failure in
+    // one environment does not stop the runs in the other environments.
+    let task_envs = &task_envs;
+    let mut pending = task_graphs.iter().enumerate();
+    let mut running = FuturesUnordered::new();
+    let mut results = Vec::with_capacity(task_graphs.len());
+    loop {
+        while running.len() < options.jobs {
+            let Some((idx, task_graph)) = pending.next() else {
+                break;
+            };
+            let jobs = options.jobs;
+            running.push(async move {
+                let mut summary = TaskSummary::default();
+                let result = if jobs > 1 {
+                    execute_task_graph_concurrently(
+                        task_graph,
+                        task_envs,
+                        1,
+                        CancellationToken::new(),
+                        capture,
+                        &mut summary,
+                    )
+                    .await
+                } else {
+                    execute_task_graph_sequentially(
+                        task_graph,
+                        task_envs,
+                        CancellationToken::new(),
+                        false,
+                        capture,
+                        &mut summary,
+                    )
+                    .await
+                };
+                (idx, result, summary)
+            });
         }
+        let Some(result) = running.next().await else {
+            break;
+        };
+        results.push(result);
+    }
+    results.sort_by_key(|(idx, _, _)| *idx);
+
+    // Print the results of all environments.
+    let mut summary = TaskSummary::default();
+    for (_, _, environment_summary) in results.iter_mut() {
+        summary.merge(std::mem::take(environment_summary));
+    }
+    summary.print();
+    let failed = print_matrix(environments, &results);
+    Ok(if failed { 1 } else { 0 })
+}
+
+/// Prints whether the task passed or failed in every environment. Returns true if the task failed
+/// in any environment.
+fn print_matrix(
+    environments: &[Environment<'_>],
+    results: &[(usize, Result<(), TaskExecutionError>, TaskSummary)],
+) -> bool {
+    let width = environments
+        .iter()
+        .map(|env| env.name().as_str().len())
+        .max()
+        .unwrap_or_default();
+    let mut failed = false;
+    eprintln!("\n{}", console::style("Results:").bold());
+    for (idx, result, _) in results {
+        let name = environments[*idx].name();
+        let status = match result {
+            Ok(_) => console::style(format!("{}passed", console::Emoji("✔ ", ""))).green(),
+            Err(TaskExecutionError::NonZeroExitCode(code)) => console::style(format!(
+                "{}failed with exit code {code}",
+                console::Emoji("✘ ", "")
+            ))
+            .red(),
+            Err(err) => console::style(format!("{}failed: {err}", console::Emoji("✘ ", ""))).red(),
+        };
+        failed |= result.is_err();
+        eprintln!(
+            "  {}{} {status}",
+            name.fancy_display(),
+            " ".repeat(width - name.as_str().len())
+        );
     }
+    failed
+}
 
+/// Activates the environments of all executable tasks in the graph that are not yet in
+/// `task_envs`.
+async fn activate_task_graph_environments<'p>(
+    lock_file: &mut LockFileDerivedData<'p>,
+    task_graph: &TaskGraph<'p>,
+    task_envs: &mut HashMap<Environment<'p>, HashMap<String, String>>,
+) -> miette::Result<()> {
+    for task_id in task_graph.topological_order() {
+        let node = &task_graph[task_id];
+        if node.task.is_executable() && !task_envs.contains_key(&node.run_environment) {
+            let command_env = get_task_env(lock_file, &node.run_environment).await?;
+            task_envs.insert(node.run_environment.clone(), command_env);
+        }
+    }
     Ok(())
 }
 
@@ -230,10 +524,14 @@ pub async fn get_task_env<'p>(
     let environment_variables = get_environment_variables(environment);
 
     // Concatenate with the system environment variables
-    Ok(std::env::vars()
+    let mut env = std::env::vars()
         .chain(activation_env)
         .chain(environment_variables)
-        .collect())
+        .collect();
+
+    // The variables of the env files take precedence over the variables of the activation.
+    read_env_files(environment.env_files(), &mut env)?;
+    Ok(env)
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -241,42 +539,117 @@ enum TaskExecutionError {
     #[error("the script exited with a non-zero exit code {0}")]
     NonZeroExitCode(i32),
 
-    #[error(transparent)]
-    FailedToParseShellScript(#[from] FailedToParseShellScript),
-
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
 
     #[error(transparent)]
     UnsupportedPlatformError(#[from] UnsupportedPlatformError),
+
+    #[error("the task timed out after {}", humantime::format_duration(*.0))]
+    TimedOut(Duration),
+
+    #[error(transparent)]
+    TaskCacheError(#[from] TaskCacheError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    EnvFileError(#[from] EnvFileError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    ExecutableTaskError(#[from] crate::task::TaskExecutionError),
 }
 
-/// Called to execute a single command.
-///
-/// This function is called from [`execute`].
+/// Called to execute a single command. The `output` determines where the output of the command is
+/// written to. If `processes` is given, the processes that the command starts are recorded in it
+/// and killed together with the processes they started themselves when the `cancellation_token` is
+/// cancelled. Otherwise only the processes that the shell spawned directly are killed.
 async fn execute_task<'p>(
     task: &ExecutableTask<'p>,
     command_env: &HashMap<String, String>,
+    output: TaskOutput<'_>,
+    processes: Option<&TaskProcesses>,
+    cancellation_token: CancellationToken,
 ) -> Result<(), TaskExecutionError> {
     let Some(script) = task.as_deno_script()? else {
         return Ok(());
     };
     let cwd = task.working_directory()?;
 
+    let custom_commands = processes
+        .map(|processes| processes.custom_commands(&script, &cwd))
+        .unwrap_or_default();
+    let state = ShellState::new(
+        task.task_env(command_env)?.into_owned(),
+        &cwd,
+        custom_commands,
+    );
+    let shell_token = state.token().clone();
+
+    let (stdin, stdout, stderr, output_handles) = match output {
+        TaskOutput::Inherit => (
+            ShellPipeReader::stdin(),
+            ShellPipeWriter::stdout(),
+            ShellPipeWriter::stderr(),
+            None,
+        ),
+        TaskOutput::Prefixed => {
+            // Tasks that run concurrently cannot share the stdin of this process.
+            let (stdin, stdin_writer) = pipe();
+            drop(stdin_writer);
+
+            let prefix = task_prefix(task);
+            let (stdout, stdout_handle) = prefixed_output(prefix.clone(), std::io::stdout());
+            let (stderr, stderr_handle) = prefixed_output(prefix, std::io::stderr());
+            (stdin, stdout, stderr, Some((stdout_handle, stderr_handle)))
+        }
+        TaskOutput::Captured { output, prefixed } => {
+            let (stdin, stdin_writer) = pipe();
+            drop(stdin_writer);
+
+            let prefix = if prefixed {
+                task_prefix(task)
+            } else {
+                String::new()
+            };
+            let (stdout, stdout_handle) =
+                prefixed_output(prefix.clone(), CapturedWriter(output.stdout.clone()));
+            let (stderr, stderr_handle) =
+                prefixed_output(prefix, CapturedWriter(output.stderr.clone()));
+            (stdin, stdout, stderr, Some((stdout_handle, stderr_handle)))
+        }
+    };
+
     // Ignore CTRL+C
     // Specifically so that the child is responsible for its own signal handling
     // NOTE: one CTRL+C is registered it will always stay registered for the rest of the runtime of the program
     // which is fine when using run in isolation, however if we start to use run in conjunction with
     // some other command we might want to revaluate this.
-    let ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+    let mut ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
 
-    let execute_future =
-        deno_task_shell::execute(script, command_env.clone(), &cwd, Default::default());
+    let execute_future = deno_task_shell::execute_with_pipes(script, state, stdin, stdout, stderr);
+    tokio::pin!(execute_future);
     let status_code = tokio::select! {
-        code = execute_future => code,
+        code = &mut execute_future => code,
+        _ = cancellation_token.cancelled() => {
+            // The shell only kills the processes it spawned directly, which would leave their
+            // children running.
+            if let Some(processes) = processes {
+                processes.kill();
+            }
+            shell_token.cancel();
+            execute_future.await
+        }
         // This should never exit
-        _ = ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
+        _ = &mut ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
     };
+    ctrl_c.abort();
+
+    // Wait until all output has been written.
+    if let Some((stdout_handle, stderr_handle)) = output_handles {
+        let _ = stdout_handle.await;
+        let _ = stderr_handle.await;
+    }
 
     if status_code != 0 {
         return Err(TaskExecutionError::NonZeroExitCode(status_code));
@@ -285,6 +658,807 @@ async fn execute_task<'p>(
     Ok(())
 }
 
+/// Returns the line that is printed before a task is executed.
+fn task_header(task: &ExecutableTask) -> String {
+    format!(
+        "{}{}{}{}{}",
+        console::Emoji("✨ ", ""),
+        console::style("Pixi task (").bold(),
+        task.run_environment.name().fancy_display().bold(),
+        console::style("): ").bold(),
+        task.display_command(),
+    )
+}
+
+/// Executes all tasks in the graph, concurrently if more than one job is allowed and otherwise one
+/// by one. The `cancellable` flag tells whether the `cancellation_token` may be cancelled while the
+/// tasks run, see [`execute_task_graph_sequentially`]. If `capture` is given, the output of the
+/// tasks is written to it instead of to the output of this process.
+async fn execute_task_graph<'p>(
+    task_graph: &TaskGraph<'p>,
+    task_envs: &HashMap<Environment<'p>, HashMap<String, String>>,
+    jobs: usize,
+    cancellation_token: CancellationToken,
+    cancellable: bool,
+    capture: Option<&CapturedOutput>,
+    summary: &mut TaskSummary,
+) -> Result<(), TaskExecutionError> {
+    if jobs > 1 {
+        execute_task_graph_concurrently(
+            task_graph,
+            task_envs,
+            jobs,
+            cancellation_token,
+            capture,
+            summary,
+        )
+        .await
+    } else {
+        execute_task_graph_sequentially(
+            task_graph,
+            task_envs,
+            cancellation_token,
+            cancellable,
+            capture,
+            summary,
+        )
+        .await
+    }
+}
+
+/// Executes all tasks in the graph one by one. When the `cancellation_token` is cancelled, the task
+/// that is running is killed and no new tasks are started. If the token can be cancelled, as
+/// indicated by `cancellable`, the processes of the tasks are recorded so the processes they
+/// started themselves are killed as well.
+async fn execute_task_graph_sequentially<'p>(
+    task_graph: &TaskGraph<'p>,
+    task_envs: &HashMap<Environment<'p>, HashMap<String, String>>,
+    cancellation_token: CancellationToken,
+    cancellable: bool,
+    capture: Option<&CapturedOutput>,
+    summary: &mut TaskSummary,
+) -> Result<(), TaskExecutionError> {
+    let output = TaskOutput::new(false, capture);
+    let mut task_idx = 0;
+    for task_id in task_graph.topological_order() {
+        let executable_task = ExecutableTask::from_task_graph(task_graph, task_id);
+
+        // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
+        // instantiate a prefix for an alias.
+        if !executable_task.task().is_executable() {
+            continue;
+        }
+        if cancellation_token.is_cancelled() {
+            return Ok(());
+        }
+
+        // Showing which command is being run if the level and type allows it.
+        if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
+            if task_idx > 0 {
+                // Add a newline between task outputs
+                eprintln!();
+            }
+            eprintln!("{}", task_header(&executable_task));
+        }
+        task_idx += 1;
+
+        let task_env = &task_envs[&executable_task.run_environment];
+        let (outcome, result) = execute_task_with_cache(
+            &executable_task,
+            task_env,
+            output,
+            cancellation_token.clone(),
+            cancellable,
+        )
+        .await;
+        summary.record(&executable_task, &outcome);
+        result?;
+    }
+
+    Ok(())
+}
+
+/// Executes all tasks in the graph, running at most `jobs` tasks that don't depend on each other
+/// at the same time. The output of each task is prefixed with its name. When a task fails or the
+/// `cancellation_token` is cancelled, the tasks that are still running are killed and no new tasks
+/// are started.
+async fn execute_task_graph_concurrently<'p>(
+    task_graph: &TaskGraph<'p>,
+    task_envs: &HashMap<Environment<'p>, HashMap<String, String>>,
+    jobs: usize,
+    cancellation_token: CancellationToken,
+    capture: Option<&CapturedOutput>,
+    summary: &mut TaskSummary,
+) -> Result<(), TaskExecutionError> {
+    let output = TaskOutput::new(true, capture);
+
+    // A task can start when all the tasks it depends on that precede it in the topological order
+    // have finished. This ensures the same order constraints as when executing the tasks one by
+    // one.
+    let mut pending = task_graph.topological_order();
+    let order: HashMap<TaskId, usize> = pending
+        .iter()
+        .enumerate()
+        .map(|(idx, &task_id)| (task_id, idx))
+        .collect();
+    let is_ready = |task_id: TaskId, finished: &HashSet<TaskId>| {
+        task_graph[task_id]
+            .dependencies
+            .iter()
+            .all(|dep| order[dep] >= order[&task_id] || finished.contains(dep))
+    };
+
+    let mut finished = HashSet::new();
+    let mut running = FuturesUnordered::new();
+    let mut cancellation_tokens = HashMap::new();
+    let result = loop {
+        // Start as many tasks as allowed whose dependencies have finished.
+        while running.len() < jobs && !cancellation_token.is_cancelled() {
+            let Some(idx) = pending
+                .iter()
+                .position(|&task_id| is_ready(task_id, &finished))
+            else {
+                break;
+            };
+            let task_id = pending.remove(idx);
+            let executable_task = ExecutableTask::from_task_graph(task_graph, task_id);
+
+            // Tasks that are not executable (e.g. an alias) are done immediately.
+            if !executable_task.task().is_executable() {
+                finished.insert(task_id);
+                continue;
+            }
+
+            if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
+                eprintln!(
+                    "{}{}",
+                    task_prefix(&executable_task),
+                    task_header(&executable_task)
+                );
+            }
+
+            let task_env = &task_envs[&executable_task.run_environment];
+            let cancellation_token = cancellation_token.child_token();
+            cancellation_tokens.insert(task_id, cancellation_token.clone());
+            running.push(async move {
+                // The task is cancelled when one of the other tasks fails.
+                let (outcome, result) = execute_task_with_cache(
+                    &executable_task,
+                    task_env,
+                    output,
+                    cancellation_token,
+                    true,
+                )
+                .await;
+                (task_id, executable_task, outcome, result)
+            });
+        }
+
+        // Wait for the next task to finish.
+        let Some((task_id, executable_task, outcome, result)) = running.next().await else {
+            break Ok(());
+        };
+        cancellation_tokens.remove(&task_id);
+        summary.record(&executable_task, &outcome);
+        if let Err(err) = result {
+            break Err(err);
+        }
+        finished.insert(task_id);
+    };
+
+    // Cancel the tasks that are still running and wait for them to stop. A task that finished
+    // before it was killed is not reported as cancelled.
+    for token in cancellation_tokens.values() {
+        token.cancel();
+    }
+    while let Some((_, executable_task, mut outcome, result)) = running.next().await {
+        outcome.cancelled = result.is_err();
+        summary.record(&executable_task, &outcome);
+    }
+
+    result
+}
+
+/// Executes the tasks in the graph and executes them again every time one of the watched files
+/// changes. Tasks that are still running when a file changes are killed. Returns when CTRL+C is
+/// pressed.
+async fn watch_task_graph<'p>(
+    project: &'p Project,
+    task_graph: &TaskGraph<'p>,
+    task_envs: &HashMap<Environment<'p>, HashMap<String, String>>,
+    jobs: usize,
+) -> miette::Result<()> {
+    // Only watch the inputs of the tasks if all of them define them, otherwise a change that is
+    // relevant for a task without inputs could be missed.
+    let executable_tasks = task_graph
+        .topological_order()
+        .into_iter()
+        .map(|task_id| &task_graph[task_id].task)
+        .filter(|task| task.is_executable())
+        .collect_vec();
+    let inputs = if executable_tasks.iter().all(|task| task.inputs().is_some()) {
+        executable_tasks
+            .iter()
+            .flat_map(|task| task.inputs().unwrap_or_default())
+            .cloned()
+            .collect_vec()
+    } else {
+        Vec::new()
+    };
+    let outputs = executable_tasks
+        .iter()
+        .flat_map(|task| task.outputs().unwrap_or_default())
+        .cloned()
+        .collect_vec();
+    let mut watcher = FileWatcher::new(project.root(), &inputs, &outputs)?;
+
+    loop {
+        let cancellation_token = CancellationToken::new();
+        let execute_future = async {
+            let mut summary = TaskSummary::default();
+            let result = execute_task_graph(
+                task_graph,
+                task_envs,
+                jobs,
+                cancellation_token.clone(),
+                true,
+                None,
+                &mut summary,
+            )
+            .await;
+            (result, summary)
+        };
+        tokio::pin!(execute_future);
+
+        let changed = tokio::select! {
+            (result, summary) = &mut execute_future => {
+                summary.print();
+                match result {
+                    Ok(_) => {}
+                    Err(TaskExecutionError::NonZeroExitCode(code)) => eprintln!(
+                        "{}",
+                        console::style(format!("task failed with exit code {code}")).red()
+                    ),
+                    Err(err) => eprintln!("{:?}", miette::Report::new(err)),
+                }
+                eprintln!(
+                    "{}{}",
+                    console::Emoji("👀 ", ""),
+                    console::style("waiting for changes").dim()
+                );
+                tokio::select! {
+                    changed = watcher.next_change() => changed?,
+                    _ = tokio::signal::ctrl_c() => return Ok(()),
+                }
+            }
+            changed = watcher.next_change() => {
+                cancellation_token.cancel();
+                let _ = execute_future.await;
+                changed?
+            }
+            _ = tokio::signal::ctrl_c() => {
+                cancellation_token.cancel();
+                let _ = execute_future.await;
+                return Ok(());
+            }
+        };
+
+        let changed_file = changed
+            .first()
+            .map(|path| path.strip_prefix(project.root()).unwrap_or(path))
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        eprintln!(
+            "\n{}{}",
+            console::Emoji("🔁 ", ""),
+            console::style(format!("'{changed_file}' changed, restarting")).dim()
+        );
+    }
+}
+
+/// What happened while executing a task, reported by [`TaskSummary`].
+#[derive(Debug, Default)]
+struct TaskOutcome {
+    /// The number of times the task was retried.
+    retries: u32,
+
+    /// True if any of the attempts timed out.
+    timed_out: bool,
+
+    /// True if the task failed but is allowed to fail.
+    allowed_failure: bool,
+
+    /// True if the task was killed because another task failed.
+    cancelled: bool,
+}
+
+/// The tasks that were retried, timed out, failed but were allowed to or were cancelled. These are
+/// reported after all tasks finished so they don't get lost in the output of the tasks.
+#[derive(Debug, Default)]
+struct TaskSummary {
+    retried: Vec<(String, u32)>,
+    timed_out: Vec<String>,
+    allowed_failures: Vec<String>,
+    cancelled: Vec<String>,
+}
+
+impl TaskSummary {
+    /// Records the outcome of a task.
+    fn record(&mut self, task: &ExecutableTask, outcome: &TaskOutcome) {
+        if outcome.retries > 0 {
+            self.retried
+                .push((task_name(task).into_owned(), outcome.retries));
+        }
+        if outcome.timed_out {
+            self.timed_out.push(task_name(task).into_owned());
+        }
+        if outcome.allowed_failure {
+            self.allowed_failures.push(task_name(task).into_owned());
+        }
+        if outcome.cancelled {
+            self.cancelled.push(task_name(task).into_owned());
+        }
+    }
+
+    /// Adds the outcomes recorded in `other` to this summary.
+    fn merge(&mut self, other: TaskSummary) {
+        self.retried.extend(other.retried);
+        self.timed_out.extend(other.timed_out);
+        self.allowed_failures.extend(other.allowed_failures);
+        self.cancelled.extend(other.cancelled);
+    }
+
+    /// Prints the summary, if there is anything to report.
+    fn print(&self) {
+        if !tracing::enabled!(Level::WARN)
+            || (self.retried.is_empty()
+                && self.timed_out.is_empty()
+                && self.allowed_failures.is_empty()
+                && self.cancelled.is_empty())
+        {
+            return;
+        }
+
+        eprintln!("\n{}", console::style("Summary:").bold());
+        for (name, retries) in &self.retried {
+            eprintln!(
+                "  {} was retried {retries} {}",
+                consts::TASK_STYLE.apply_to(name),
+                if *retries == 1 { "time" } else { "times" }
+            );
+        }
+        for name in &self.timed_out {
+            eprintln!("  {} timed out", consts::TASK_STYLE.apply_to(name));
+        }
+        for name in &self.allowed_failures {
+            eprintln!(
+                "  {} failed, but is allowed to fail",
+                consts::TASK_STYLE.apply_to(name)
+            );
+        }
+        for name in &self.cancelled {
+            eprintln!("  {} was cancelled", consts::TASK_STYLE.apply_to(name));
+        }
+    }
+}
+
+/// Where the output of a task is written to.
+#[derive(Debug, Clone, Copy)]
+enum TaskOutput<'a> {
+    /// The task writes directly to the stdout and stderr of this process.
+    Inherit,
+
+    /// Every line of output is prefixed with the name of the task, see [`task_prefix`]. The task
+    /// does not read from the stdin of this process.
+    Prefixed,
+
+    /// The output is written to `output` instead of to the output of this process, prefixed like
+    /// [`TaskOutput::Prefixed`] if `prefixed` is set. The task does not read from the stdin of this
+    /// process.
+    Captured {
+        output: &'a CapturedOutput,
+        prefixed: bool,
+    },
+}
+
+impl<'a> TaskOutput<'a> {
+    /// Returns where the output of a task is written to, depending on whether the output should be
+    /// `prefixed` and whether it should be captured.
+    fn new(prefixed: bool, capture: Option<&'a CapturedOutput>) -> Self {
+        match capture {
+            Some(output) => Self::Captured { output, prefixed },
+            None if prefixed => Self::Prefixed,
+            None => Self::Inherit,
+        }
+    }
+
+    /// Returns true if every line of output of the task is prefixed with its name.
+    fn is_prefixed(&self) -> bool {
+        match self {
+            Self::Inherit => false,
+            Self::Prefixed => true,
+            Self::Captured { prefixed, .. } => *prefixed,
+        }
+    }
+}
+
+/// The output of tasks that is captured instead of written to the output of this process.
+#[derive(Debug, Default)]
+struct CapturedOutput {
+    stdout: Arc<Mutex<Vec<u8>>>,
+    stderr: Arc<Mutex<Vec<u8>>>,
+}
+
+impl CapturedOutput {
+    /// Returns the captured output of the tasks together with the `exit_code` of the run.
+    fn into_run_output(self, exit_code: i32) -> RunOutput {
+        let into_string = |buffer: Arc<Mutex<Vec<u8>>>| {
+            String::from_utf8_lossy(&buffer.lock().unwrap()).into_owned()
+        };
+        RunOutput {
+            exit_code,
+            stdout: into_string(self.stdout),
+            stderr: into_string(self.stderr),
+        }
+    }
+}
+
+/// Implements [`Write`] for a buffer of [`CapturedOutput`].
+struct CapturedWriter(Arc<Mutex<Vec<u8>>>);
+
+impl Write for CapturedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Executes a task unless it defines inputs and neither they, its outputs nor its environment
+/// changed since the last successful run. After a successful run the state of the task is stored,
+/// so it can be skipped next time.
+async fn execute_task_with_cache(
+    task: &ExecutableTask<'_>,
+    command_env: &HashMap<String, String>,
+    output: TaskOutput<'_>,
+    cancellation_token: CancellationToken,
+    cancellable: bool,
+) -> (TaskOutcome, Result<(), TaskExecutionError>) {
+    let result = execute_with_cache(
+        task,
+        command_env,
+        execute_task_with_retries(task, command_env, output, cancellation_token, cancellable),
+        |(outcome, result)| result.is_ok() && !outcome.allowed_failure,
+    )
+    .await;
+
+    match result {
+        Ok(Some(result)) => result,
+        Ok(None) => {
+            if tracing::enabled!(Level::WARN) {
+                let prefix = if output.is_prefixed() {
+                    task_prefix(task)
+                } else {
+                    console::Emoji("⚡ ", "").to_string()
+                };
+                eprintln!(
+                    "{prefix}{}",
+                    console::style("cache hit, skipping task").dim()
+                );
+            }
+            (TaskOutcome::default(), Ok(()))
+        }
+        Err(err) => (TaskOutcome::default(), Err(err.into())),
+    }
+}
+
+/// Awaits `execute` unless the `task` defines inputs and neither they, its outputs nor its
+/// environment changed since the last successful run, in which case `None` is returned. If the
+/// output of `execute` is considered `successful`, the state of the task is stored so it can be
+/// skipped next time.
+async fn execute_with_cache<T>(
+    task: &ExecutableTask<'_>,
+    command_env: &HashMap<String, String>,
+    execute: impl Future<Output = T>,
+    successful: impl FnOnce(&T) -> bool,
+) -> Result<Option<T>, TaskCacheError> {
+    if let Some(hash) = task.compute_hash(command_env)? {
+        if task.is_up_to_date(&hash) {
+            return Ok(None);
+        }
+    }
+
+    let output = execute.await;
+    if !successful(&output) {
+        return Ok(Some(output));
+    }
+
+    // The hash is computed again, because running the task changes its outputs.
+    if let Some(hash) = task.compute_hash(command_env)? {
+        task.save_to_cache(hash)?;
+    }
+    Ok(Some(output))
+}
+
+/// Executes a task, killing it when it runs longer than its timeout and retrying it with an
+/// exponential backoff when it fails. If the task is allowed to fail, a failure is recorded in the
+/// returned [`TaskOutcome`] instead of returned as an error.
+async fn execute_task_with_retries(
+    task: &ExecutableTask<'_>,
+    command_env: &HashMap<String, String>,
+    output: TaskOutput<'_>,
+    cancellation_token: CancellationToken,
+    cancellable: bool,
+) -> (TaskOutcome, Result<(), TaskExecutionError>) {
+    let prefix = if output.is_prefixed() {
+        task_prefix(task)
+    } else {
+        String::new()
+    };
+    let max_retries = task.task().retries();
+    let mut outcome = TaskOutcome::default();
+    let result = loop {
+        let result = execute_task_with_timeout(
+            task,
+            command_env,
+            output,
+            cancellation_token.clone(),
+            cancellable,
+        )
+        .await;
+        let err = match result {
+            Err(
+                err @ (TaskExecutionError::NonZeroExitCode(_) | TaskExecutionError::TimedOut(_)),
+            ) => err,
+            result => break result,
+        };
+        outcome.timed_out |= matches!(err, TaskExecutionError::TimedOut(_));
+        if outcome.retries >= max_retries || cancellation_token.is_cancelled() {
+            break Err(err);
+        }
+
+        let backoff = INITIAL_RETRY_BACKOFF
+            .checked_mul(2u32.saturating_pow(outcome.retries))
+            .map_or(MAX_RETRY_BACKOFF, |backoff| backoff.min(MAX_RETRY_BACKOFF));
+        outcome.retries += 1;
+        if tracing::enabled!(Level::WARN) {
+            eprintln!(
+                "{prefix}{}",
+                console::style(format!(
+                    "{err}, retrying in {} (attempt {} of {})",
+                    humantime::format_duration(backoff),
+                    outcome.retries + 1,
+                    max_retries + 1
+                ))
+                .yellow()
+            );
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = cancellation_token.cancelled() => break Err(err),
+        }
+    };
+
+    match result {
+        Err(err @ (TaskExecutionError::NonZeroExitCode(_) | TaskExecutionError::TimedOut(_)))
+            if task.task().allow_failure() && !cancellation_token.is_cancelled() =>
+        {
+            if tracing::enabled!(Level::WARN) {
+                eprintln!(
+                    "{prefix}{}",
+                    console::style(format!("{err}, continuing because the task may fail")).yellow()
+                );
+            }
+            outcome.allowed_failure = true;
+            (outcome, Ok(()))
+        }
+        result => (outcome, result),
+    }
+}
+
+/// Executes a task once. If the task defines a timeout and runs longer than that, or if the
+/// `cancellation_token` is cancelled, the task is killed together with all the processes it
+/// started.
+async fn execute_task_with_timeout(
+    task: &ExecutableTask<'_>,
+    command_env: &HashMap<String, String>,
+    output: TaskOutput<'_>,
+    cancellation_token: CancellationToken,
+    cancellable: bool,
+) -> Result<(), TaskExecutionError> {
+    let timeout = task.task().timeout();
+
+    // The processes of a task that may be killed before it finishes are recorded, so the
+    // processes they started themselves can be killed as well.
+    let processes = (cancellable || timeout.is_some()).then(TaskProcesses::default);
+    let attempt_token = cancellation_token.child_token();
+    let execute_future = execute_task(
+        task,
+        command_env,
+        output,
+        processes.as_ref(),
+        attempt_token.clone(),
+    );
+    tokio::pin!(execute_future);
+
+    let Some(timeout) = timeout else {
+        return execute_future.await;
+    };
+    tokio::select! {
+        result = &mut execute_future => result,
+        _ = tokio::time::sleep(timeout) => {
+            attempt_token.cancel();
+            let _ = execute_future.await;
+            Err(TaskExecutionError::TimedOut(timeout))
+        }
+    }
+}
+
+/// Returns the name of the task, or its command if the task has no name.
+fn task_name<'a>(task: &'a ExecutableTask) -> Cow<'a, str> {
+    match task.name() {
+        Some(name) => Cow::Borrowed(name),
+        None => Cow::Owned(task.full_command().unwrap_or_default()),
+    }
+}
+
+/// Returns the prefix that is put in front of every line of output of a task that is executed
+/// concurrently with other tasks.
+fn task_prefix(task: &ExecutableTask) -> String {
+    // The same task can run in multiple environments at the same time, e.g. with
+    // `--all-environments`, so the environment is part of the prefix unless it is the default.
+    let environment = task.run_environment.name();
+    let prefix = if environment.is_default() {
+        format!("[{}]", task_name(task))
+    } else {
+        format!("[{}:{}]", environment.as_str(), task_name(task))
+    };
+    format!("{} ", consts::TASK_STYLE.apply_to(prefix))
+}
+
+/// Creates a pipe of which every line that is written to it is written to `output`, prefixed with
+/// `prefix`. Without a prefix the output is written as is. The returned handle finishes when the
+/// writer side of the pipe is closed.
+fn prefixed_output(
+    prefix: String,
+    mut output: impl Write + Send + 'static,
+) -> (ShellPipeWriter, JoinHandle<()>) {
+    let (reader, writer) = pipe();
+    let handle = tokio::task::spawn_blocking(move || {
+        if prefix.is_empty() {
+            let _ = std::io::copy(&mut PipeReader(reader), &mut output);
+            return;
+        }
+        for line in BufReader::new(PipeReader(reader)).split(b'\n') {
+            let Ok(line) = line else {
+                break;
+            };
+            let mut prefixed_line = Vec::with_capacity(prefix.len() + line.len() + 1);
+            prefixed_line.extend_from_slice(prefix.as_bytes());
+            prefixed_line.extend_from_slice(&line);
+            prefixed_line.push(b'\n');
+            if output.write_all(&prefixed_line).is_err() {
+                break;
+            }
+        }
+    });
+    (writer, handle)
+}
+
+/// Implements [`Read`] for the reading side of a deno task shell pipe.
+struct PipeReader(ShellPipeReader);
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0
+            .read(buf)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))
+    }
+}
+
+/// Returns true if the user can be asked to select a task, which requires both stdin and stderr to
+/// be a terminal.
+fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Returns the error that is reported when no task is given and the task cannot be selected
+/// interactively. Like a required task argument, it shows the help of `pixi run`.
+fn missing_task_error() -> clap::Error {
+    Args::command()
+        .bin_name("pixi run")
+        .arg_required_else_help(true)
+        .try_get_matches_from(["pixi run"])
+        .expect_err("a task is required")
+}
+
+/// Asks the user to select one of the tasks of the environments that were selected on the command
+/// line, or of all environments of the project, that can run on the current platform. A task that
+/// is the same in several environments is listed once, with all of these environments. Returns the
+/// name of the selected task and, if it was listed for a single environment, that environment.
+fn select_task_interactive<'p>(
+    project: &'p Project,
+    args: &Args,
+) -> miette::Result<(&'p TaskName, Option<Environment<'p>>)> {
+    let mut environments =
+        explicit_environments(project, &args.environment, args.all_environments, &[])?;
+    if environments.is_empty() {
+        environments = project.environments();
+    }
+
+    // Group the tasks by their name and what they execute.
+    let platform = Some(Platform::current());
+    let mut tasks: Vec<(&TaskName, String, Vec<Environment<'p>>)> = Vec::new();
+    for env in &environments {
+        let Ok(env_tasks) = env.tasks(platform, true) else {
+            continue;
+        };
+        for (name, task) in env_tasks {
+            let command = match task.as_single_command() {
+                Some(command) => command.into_owned(),
+                None => format!("depends on {}", task.depends_on().iter().join(", ")),
+            };
+            match tasks
+                .iter_mut()
+                .find(|(other, other_command, _)| *other == name && *other_command == command)
+            {
+                Some((_, _, envs)) => envs.push(env.clone()),
+                None => tasks.push((name, command, vec![env.clone()])),
+            }
+        }
+    }
+    if tasks.is_empty() {
+        miette::bail!("the project does not define any tasks that can run on this platform");
+    }
+    tasks.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+
+    // Align the columns with the names of the tasks and the environments.
+    let env_names = tasks
+        .iter()
+        .map(|(_, _, envs)| envs.iter().map(|env| env.name().as_str()).join(", "))
+        .collect_vec();
+    let name_width = tasks.iter().map(|(name, ..)| name.as_str().len()).max();
+    let env_width = env_names.iter().map(String::len).max();
+    let items = tasks
+        .iter()
+        .zip(&env_names)
+        .map(|((name, command, _), env_names)| {
+            format!(
+                "{:name_width$}  {:env_width$}  {command}",
+                name.as_str(),
+                env_names,
+                name_width = name_width.unwrap_or_default(),
+                env_width = env_width.unwrap_or_default(),
+            )
+        })
+        .collect_vec();
+
+    let theme = ColorfulTheme {
+        active_item_style: console::Style::new().for_stderr().magenta(),
+        ..ColorfulTheme::default()
+    };
+    let selection = dialoguer::FuzzySelect::with_theme(&theme)
+        .with_prompt("Select a task to run")
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .into_diagnostic()?;
+    match selection {
+        Some(idx) => {
+            let (name, _, envs) = &tasks[idx];
+            let environment = match envs.as_slice() {
+                [env] => Some(env.clone()),
+                _ => None,
+            };
+            Ok((*name, environment))
+        }
+        None => miette::bail!("no task was selected"),
+    }
+}
+
 /// Called to disambiguate between environments to run a task in.
 fn disambiguate_task_interactive<'p>(
     problem: &AmbiguousTask<'p>,
@@ -316,3 +1490,163 @@ fn disambiguate_task_interactive<'p>(
         .map_or(None, identity)
         .map(|idx| problem.environments[idx].clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+
+    #[test]
+    fn test_watch_with_environment() {
+        let args = Args::try_parse_from(["run", "--watch", "-e", "test", "start"]).unwrap();
+        assert!(args.watch);
+        assert_eq!(args.environment, vec!["test".to_string()]);
+        assert_eq!(args.task, vec!["start".to_string()]);
+
+        assert!(Args::try_parse_from(["run", "--watch", "--all-environments", "start"]).is_err());
+    }
+
+    #[test]
+    fn test_missing_task_error() {
+        let err = missing_task_error();
+        assert_eq!(
+            err.kind(),
+            ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+        );
+        assert_eq!(err.exit_code(), 2);
+        assert!(err.to_string().contains("Usage: pixi run"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_tasks_are_recorded() {
+        let root = tempfile::tempdir().unwrap();
+        let project = Project::from_str(
+            root.path(),
+            &format!(
+                r#"
+                [project]
+                name = "foo"
+                channels = []
+                platforms = ["{}"]
+
+                [tasks]
+                slow = "sleep 60"
+                fail = "exit 1"
+                all = {{ depends_on = ["slow", "fail"] }}
+                "#,
+                Platform::current()
+            ),
+        )
+        .unwrap();
+        let search_environments =
+            SearchEnvironments::from_opt_env(&project, None, Some(Platform::current()));
+        let task_graph =
+            TaskGraph::from_cmd_args(&project, &search_environments, vec!["all".to_string()])
+                .unwrap();
+        let task_envs =
+            HashMap::from([(project.default_environment(), std::env::vars().collect())]);
+
+        // The slow task is killed when the other task fails.
+        let mut summary = TaskSummary::default();
+        let result = tokio::time::timeout(
+            Duration::from_secs(30),
+            execute_task_graph_concurrently(
+                &task_graph,
+                &task_envs,
+                2,
+                CancellationToken::new(),
+                None,
+                &mut summary,
+            ),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            result,
+            Err(TaskExecutionError::NonZeroExitCode(1))
+        ));
+        assert_eq!(summary.cancelled, vec!["slow".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_watch_kills_background_processes_on_restart() {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = dunce::canonicalize(root.path()).unwrap();
+        std::fs::write(root_path.join("input.txt"), "a").unwrap();
+        let project = Project::from_str(
+            &root_path,
+            &format!(
+                r#"
+                [project]
+                name = "foo"
+                channels = []
+                platforms = ["{}"]
+
+                [tasks]
+                start = {{ cmd = "sh -c 'sleep 60 & echo $! >> bg.pid; wait'", inputs = ["input.txt"] }}
+                "#,
+                Platform::current()
+            ),
+        )
+        .unwrap();
+        let search_environments =
+            SearchEnvironments::from_opt_env(&project, None, Some(Platform::current()));
+        let task_graph =
+            TaskGraph::from_cmd_args(&project, &search_environments, vec!["start".to_string()])
+                .unwrap();
+        let task_envs =
+            HashMap::from([(project.default_environment(), std::env::vars().collect())]);
+
+        let watch_future = watch_task_graph(&project, &task_graph, &task_envs, 1);
+        tokio::pin!(watch_future);
+        let background_pids = || {
+            std::fs::read_to_string(root_path.join("bg.pid"))
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|pid| pid.trim().parse::<u32>().ok())
+                .map(sysinfo::Pid::from_u32)
+                .collect_vec()
+        };
+        let is_running = |pid: sysinfo::Pid| {
+            let mut system = sysinfo::System::new();
+            system.refresh_processes_specifics(sysinfo::ProcessRefreshKind::new());
+            system
+                .process(pid)
+                .is_some_and(|process| process.status() != sysinfo::ProcessStatus::Zombie)
+        };
+
+        // Wait until the task started its process in the background, then restart the task by
+        // changing its input.
+        for _ in 0..100 {
+            tokio::select! {
+                _ = &mut watch_future => panic!("watching should not stop"),
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {},
+            }
+            if !background_pids().is_empty() {
+                break;
+            }
+        }
+        let first_pid = background_pids()[0];
+        std::fs::write(root_path.join("input.txt"), "b").unwrap();
+        for _ in 0..100 {
+            tokio::select! {
+                _ = &mut watch_future => panic!("watching should not stop"),
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {},
+            }
+            if background_pids().len() > 1 && !is_running(first_pid) {
+                break;
+            }
+        }
+
+        // The background process of the first run was killed when the task restarted.
+        let pids = background_pids();
+        for pid in &pids[1..] {
+            if let Some(process) = sysinfo::System::new_all().process(*pid) {
+                process.kill();
+            }
+        }
+        assert_eq!(pids.len(), 2);
+        assert!(!is_running(first_pid));
+    }
+}
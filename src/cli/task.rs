@@ -1,19 +1,29 @@
 use crate::project::manifest::{EnvironmentName, FeatureName};
-use crate::task::{quote, Alias, CmdArgs, Execute, Task, TaskName};
+use crate::project::Environment;
+use crate::task::{
+    quote, Alias, CmdArgs, Dependency, Execute, SearchEnvironments, Task, TaskArg, TaskGraph,
+    TaskGraphFormat, TaskName, TaskShell,
+};
 use crate::Project;
 use clap::Parser;
+use console::Color;
+use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::miette;
 use rattler_conda_types::Platform;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, stdout, Write};
 use std::path::PathBuf;
 use std::str::FromStr;
-use toml_edit::{Array, Item, Table, Value};
+use toml_edit::{Array, InlineTable, Item, Table, Value};
 
 #[derive(Parser, Debug)]
 pub enum Operation {
     /// Add a command to the project
     #[clap(alias = "a")]
-    Add(AddArgs),
+    Add(Box<AddArgs>),
 
     /// Remove a command from the project
     #[clap(alias = "r")]
@@ -26,6 +36,13 @@ pub enum Operation {
     /// List all tasks
     #[clap(alias = "l")]
     List(ListArgs),
+
+    /// Show the tasks that `pixi run` executes for a task, without executing them
+    Graph(GraphArgs),
+
+    /// Update a task in place, keeping its position and comments in the manifest
+    #[clap(alias = "edit")]
+    Update(Box<UpdateArgs>),
 }
 
 #[derive(Parser, Debug)]
@@ -69,6 +86,81 @@ pub struct AddArgs {
     /// The working directory relative to the root of the project
     #[arg(long)]
     pub cwd: Option<PathBuf>,
+
+    /// The environment variable to set, use --env key=value multiple times for more than one
+    /// variable
+    #[arg(long, value_parser = parse_key_val)]
+    pub env: Vec<(String, String)>,
+
+    /// A dotenv file with environment variables to set, relative to the root of the project, use
+    /// --env-file multiple times for more than one file
+    #[arg(long)]
+    pub env_file: Vec<PathBuf>,
+
+    /// Glob patterns of the files the task uses as input, relative to the root of the project
+    #[arg(long, num_args = 1..)]
+    pub inputs: Option<Vec<String>>,
+
+    /// Glob patterns of the files the task produces, relative to the root of the project
+    #[arg(long, num_args = 1..)]
+    pub outputs: Option<Vec<String>>,
+
+    /// A description of what the task does
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// An argument of the task as `NAME` or `NAME=DEFAULT`, use --arg multiple times for more
+    /// than one argument. The value of an argument is used in the command with `{{ NAME }}`
+    #[arg(long = "arg", value_parser = parse_task_arg)]
+    pub args: Vec<TaskArg>,
+
+    /// The maximum time the task may run, e.g. `10m` or `1h 30m`
+    #[arg(long)]
+    pub timeout: Option<humantime::Duration>,
+
+    /// The number of times the task is retried when it fails or times out
+    #[arg(long)]
+    pub retries: Option<u32>,
+
+    /// Execute the tasks that depend on this task even if it fails
+    #[arg(long)]
+    pub allow_failure: bool,
+
+    /// The environment the task runs in, unless another environment is selected with
+    /// `--environment` when running it
+    #[arg(long)]
+    pub environment: Option<EnvironmentName>,
+
+    /// Run the command as a script with this interpreter from the environment instead of the
+    /// built-in shell
+    #[arg(long, value_enum)]
+    pub shell: Option<TaskShell>,
+}
+
+/// Parses an environment variable in the form of `KEY=VALUE`.
+fn parse_key_val(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid KEY=VALUE: no `=` found in `{s}`"))?;
+    if key.is_empty() {
+        return Err(format!("invalid KEY=VALUE: the key in `{s}` is empty"));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Parses a task argument in the form of `NAME` or `NAME=DEFAULT`.
+fn parse_task_arg(s: &str) -> Result<TaskArg, String> {
+    let (name, default) = match s.split_once('=') {
+        Some((name, default)) => (name, Some(default.to_string())),
+        None => (s, None),
+    };
+    if name.is_empty() {
+        return Err(format!("invalid argument: the name in `{s}` is empty"));
+    }
+    Ok(TaskArg {
+        name: name.to_string(),
+        default,
+    })
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -84,6 +176,15 @@ pub struct AliasArgs {
     /// The platform for which the alias should be added
     #[arg(long, short)]
     pub platform: Option<Platform>,
+
+    /// A description of what the alias does
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// The environment the tasks of the alias run in, unless another environment is selected with
+    /// `--environment` when running it
+    #[arg(long)]
+    pub environment: Option<EnvironmentName>,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -92,14 +193,281 @@ pub struct ListArgs {
     pub summary: bool,
 
     /// The environment the list should be generated for
-    /// If not specified, the default environment is used.
+    /// If not specified, the tasks of all environments are listed, or only the tasks of the
+    /// default environment when using `--summary`.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+
+    /// Whether to output in json format
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// A field of a task that can be removed with `pixi task update --unset`. The values are named
+/// like the fields in the manifest.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[value(rename_all = "snake_case")]
+pub enum TaskField {
+    #[value(alias = "depends-on")]
+    DependsOn,
+    Cwd,
+    Env,
+    #[value(alias = "env-file")]
+    EnvFile,
+    Inputs,
+    Outputs,
+    Description,
+    Args,
+    Timeout,
+    Retries,
+    #[value(alias = "allow-failure")]
+    AllowFailure,
+    Environment,
+    Shell,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(arg_required_else_help = true)]
+pub struct UpdateArgs {
+    /// Task name
+    pub name: TaskName,
+
+    /// The platform of the task that should be updated
+    #[arg(long, short)]
+    pub platform: Option<Platform>,
+
+    /// The feature of the task that should be updated
+    #[arg(long, short)]
+    pub feature: Option<String>,
+
+    /// The new command of the task
+    #[arg(long)]
+    pub cmd: Option<String>,
+
+    /// Add these tasks to the tasks this task depends on
+    #[arg(long, num_args = 1..)]
+    pub add_depends_on: Vec<TaskName>,
+
+    /// Remove these tasks from the tasks this task depends on
+    #[arg(long, num_args = 1..)]
+    pub remove_depends_on: Vec<TaskName>,
+
+    /// The working directory relative to the root of the project
+    #[arg(long)]
+    pub cwd: Option<PathBuf>,
+
+    /// Set an environment variable, use --env key=value multiple times for more than one variable
+    #[arg(long, value_parser = parse_key_val)]
+    pub env: Vec<(String, String)>,
+
+    /// Remove an environment variable, can be used multiple times
+    #[arg(long)]
+    pub remove_env: Vec<String>,
+
+    /// Replace the dotenv files with environment variables to set
+    #[arg(long, num_args = 1..)]
+    pub env_file: Option<Vec<PathBuf>>,
+
+    /// Replace the glob patterns of the files the task uses as input
+    #[arg(long, num_args = 1..)]
+    pub inputs: Option<Vec<String>>,
+
+    /// Replace the glob patterns of the files the task produces
+    #[arg(long, num_args = 1..)]
+    pub outputs: Option<Vec<String>>,
+
+    /// A description of what the task does
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Add or replace an argument of the task as `NAME` or `NAME=DEFAULT`, can be used multiple
+    /// times
+    #[arg(long = "arg", value_parser = parse_task_arg)]
+    pub args: Vec<TaskArg>,
+
+    /// Remove an argument of the task, can be used multiple times
+    #[arg(long)]
+    pub remove_arg: Vec<String>,
+
+    /// The maximum time the task may run, e.g. `10m` or `1h 30m`
+    #[arg(long)]
+    pub timeout: Option<humantime::Duration>,
+
+    /// The number of times the task is retried when it fails or times out
+    #[arg(long)]
+    pub retries: Option<u32>,
+
+    /// Whether the tasks that depend on this task are executed even if it fails
+    #[arg(long)]
+    pub allow_failure: Option<bool>,
+
+    /// The environment the task runs in, unless another environment is selected with
+    /// `--environment` when running it
+    #[arg(long)]
+    pub environment: Option<EnvironmentName>,
+
+    /// Run the command as a script with this interpreter from the environment instead of the
+    /// built-in shell
+    #[arg(long, value_enum)]
+    pub shell: Option<TaskShell>,
+
+    /// Remove these fields from the task
+    #[arg(long, value_enum, num_args = 1..)]
+    pub unset: Vec<TaskField>,
+}
+
+impl UpdateArgs {
+    /// Applies the changes to `task` and returns the updated task.
+    fn apply(self, task: &Task) -> miette::Result<Task> {
+        let mut execute = match task {
+            Task::Plain(cmd) => execute_from_cmd(CmdArgs::Single(cmd.clone())),
+            Task::Execute(execute) => execute.as_ref().clone(),
+            Task::Alias(alias) => Execute {
+                depends_on: alias.depends_on.clone(),
+                description: alias.description.clone(),
+                environment: alias.environment.clone(),
+                ..execute_from_cmd(CmdArgs::Single(String::new()))
+            },
+            Task::Custom(_) => miette::bail!("custom tasks cannot be updated"),
+        };
+
+        for field in self.unset {
+            match field {
+                TaskField::DependsOn => execute.depends_on.clear(),
+                TaskField::Cwd => execute.cwd = None,
+                TaskField::Env => execute.env = None,
+                TaskField::EnvFile => execute.env_file = None,
+                TaskField::Inputs => execute.inputs = None,
+                TaskField::Outputs => execute.outputs = None,
+                TaskField::Description => execute.description = None,
+                TaskField::Args => execute.args = None,
+                TaskField::Timeout => execute.timeout = None,
+                TaskField::Retries => execute.retries = None,
+                TaskField::AllowFailure => execute.allow_failure = None,
+                TaskField::Environment => execute.environment = None,
+                TaskField::Shell => execute.shell = None,
+            }
+        }
+
+        if let Some(cmd) = self.cmd {
+            execute.cmd = CmdArgs::Single(cmd);
+        }
+
+        for name in self.remove_depends_on {
+            let len = execute.depends_on.len();
+            execute
+                .depends_on
+                .retain(|dependency| dependency.task_name != name);
+            if execute.depends_on.len() == len {
+                miette::bail!(
+                    "task {} does not depend on {}",
+                    self.name.fancy_display(),
+                    name.fancy_display()
+                );
+            }
+        }
+        for name in self.add_depends_on {
+            if !execute
+                .depends_on
+                .iter()
+                .any(|dependency| dependency.task_name == name)
+            {
+                execute.depends_on.push(Dependency::from(name));
+            }
+        }
+
+        if !self.env.is_empty() || !self.remove_env.is_empty() {
+            let env = execute.env.get_or_insert_with(IndexMap::new);
+            env.extend(self.env);
+            for key in self.remove_env {
+                env.shift_remove(&key);
+            }
+            if env.is_empty() {
+                execute.env = None;
+            }
+        }
+
+        if !self.args.is_empty() || !self.remove_arg.is_empty() {
+            let args = execute.args.get_or_insert_with(Vec::new);
+            for arg in self.args {
+                match args.iter_mut().find(|existing| existing.name == arg.name) {
+                    Some(existing) => *existing = arg,
+                    None => args.push(arg),
+                }
+            }
+            args.retain(|arg| !self.remove_arg.contains(&arg.name));
+            if args.is_empty() {
+                execute.args = None;
+            }
+        }
+
+        execute.cwd = self.cwd.or(execute.cwd);
+        execute.env_file = self.env_file.or(execute.env_file);
+        execute.inputs = self.inputs.or(execute.inputs);
+        execute.outputs = self.outputs.or(execute.outputs);
+        execute.description = self.description.or(execute.description);
+        execute.timeout = self.timeout.or(execute.timeout);
+        execute.retries = self.retries.or(execute.retries);
+        execute.environment = self.environment.or(execute.environment);
+        execute.shell = self.shell.or(execute.shell);
+        if let Some(allow_failure) = self.allow_failure {
+            execute.allow_failure = allow_failure.then_some(true);
+        }
+
+        // A task without a command can only depend on other tasks.
+        if has_empty_cmd(&execute) {
+            if execute.depends_on.is_empty() {
+                miette::bail!(
+                    "task {} has no command and does not depend on any task",
+                    self.name.fancy_display()
+                );
+            }
+            if execute.cwd.is_some()
+                || execute.env.is_some()
+                || execute.env_file.is_some()
+                || execute.inputs.is_some()
+                || execute.outputs.is_some()
+                || execute.args.is_some()
+                || execute.timeout.is_some()
+                || execute.retries.is_some()
+                || execute.allow_failure.is_some()
+                || execute.shell.is_some()
+            {
+                miette::bail!(
+                    "task {} does not have a command, set one with `--cmd`",
+                    self.name.fancy_display()
+                );
+            }
+        }
+
+        Ok(task_from_execute(execute))
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(trailing_var_arg = true, arg_required_else_help = true)]
+pub struct GraphArgs {
+    /// The task to show the graph of, followed by the arguments that are passed to it
+    #[clap(required = true, num_args = 1..)]
+    pub task: Vec<String>,
+
+    /// The environment to look up the tasks in
     #[arg(long, short)]
     pub environment: Option<String>,
+
+    /// The format in which the graph is printed
+    #[arg(long, value_enum, default_value_t)]
+    pub format: TaskGraphFormat,
 }
 
 impl From<AddArgs> for Task {
     fn from(value: AddArgs) -> Self {
-        let depends_on = value.depends_on.unwrap_or_default();
+        let depends_on = value
+            .depends_on
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dependency::from)
+            .collect_vec();
 
         // Convert the arguments into a single string representation
         let cmd_args = if value.commands.len() == 1 {
@@ -113,26 +481,93 @@ impl From<AddArgs> for Task {
                 .join(" ")
         };
 
-        // Depending on whether the task has a command, and depends_on or not we create a plain or
-        // complex, or alias command.
-        if cmd_args.trim().is_empty() && !depends_on.is_empty() {
-            Self::Alias(Alias { depends_on })
-        } else if depends_on.is_empty() && value.cwd.is_none() {
-            Self::Plain(cmd_args)
-        } else {
-            Self::Execute(Execute {
-                cmd: CmdArgs::Single(cmd_args),
-                depends_on,
-                cwd: value.cwd,
-            })
-        }
+        let env = (!value.env.is_empty()).then(|| value.env.into_iter().collect());
+        task_from_execute(Execute {
+            cmd: CmdArgs::Single(cmd_args),
+            depends_on,
+            cwd: value.cwd,
+            env,
+            env_file: (!value.env_file.is_empty()).then_some(value.env_file),
+            inputs: value.inputs,
+            outputs: value.outputs,
+            description: value.description,
+            args: (!value.args.is_empty()).then_some(value.args),
+            timeout: value.timeout,
+            retries: value.retries,
+            allow_failure: value.allow_failure.then_some(true),
+            environment: value.environment,
+            shell: value.shell,
+        })
+    }
+}
+
+/// Returns an [`Execute`] task with the given command and no other fields set.
+fn execute_from_cmd(cmd: CmdArgs) -> Execute {
+    Execute {
+        cmd,
+        depends_on: Vec::new(),
+        cwd: None,
+        env: None,
+        env_file: None,
+        inputs: None,
+        outputs: None,
+        description: None,
+        args: None,
+        timeout: None,
+        retries: None,
+        allow_failure: None,
+        environment: None,
+        shell: None,
+    }
+}
+
+/// Returns true if the command of the task is empty.
+fn has_empty_cmd(execute: &Execute) -> bool {
+    match &execute.cmd {
+        CmdArgs::Single(cmd) => cmd.trim().is_empty(),
+        CmdArgs::Multiple(cmds) => cmds.is_empty(),
+    }
+}
+
+/// Converts an [`Execute`] into the simplest kind of task that describes it. Depending on whether
+/// the task has a command, and depends_on or not we create a plain or complex, or alias command.
+fn task_from_execute(execute: Execute) -> Task {
+    if has_empty_cmd(&execute) && !execute.depends_on.is_empty() {
+        Task::Alias(Alias {
+            depends_on: execute.depends_on,
+            description: execute.description,
+            environment: execute.environment,
+        })
+    } else if execute.depends_on.is_empty()
+        && execute.args.is_none()
+        && execute.cwd.is_none()
+        && execute.description.is_none()
+        && execute.env.is_none()
+        && execute.env_file.is_none()
+        && execute.inputs.is_none()
+        && execute.outputs.is_none()
+        && execute.timeout.is_none()
+        && execute.retries.is_none()
+        && execute.allow_failure.is_none()
+        && execute.environment.is_none()
+        && execute.shell.is_none()
+        && matches!(execute.cmd, CmdArgs::Single(_))
+    {
+        let CmdArgs::Single(cmd) = execute.cmd else {
+            unreachable!()
+        };
+        Task::Plain(cmd)
+    } else {
+        Task::Execute(Box::new(execute))
     }
 }
 
 impl From<AliasArgs> for Task {
     fn from(value: AliasArgs) -> Self {
         Self::Alias(Alias {
-            depends_on: value.depends_on,
+            depends_on: value.depends_on.into_iter().map(Dependency::from).collect(),
+            description: value.description,
+            environment: value.environment,
         })
     }
 }
@@ -155,7 +590,7 @@ pub fn execute(args: Args) -> miette::Result<()> {
     match args.operation {
         Operation::Add(args) => {
             let name = &args.name;
-            let task: Task = args.clone().into();
+            let task: Task = (*args).clone().into();
             let feature = args
                 .feature
                 .map_or(FeatureName::Default, FeatureName::Named);
@@ -251,36 +686,209 @@ pub fn execute(args: Args) -> miette::Result<()> {
             );
         }
         Operation::List(args) => {
-            let env = EnvironmentName::from_str(args.environment.as_deref().unwrap_or("default"))?;
-            let tasks = project
-                .environment(&env)
-                .ok_or(miette!("Environment `{}` not found in project", env))?
-                .tasks(Some(Platform::current()), true)?
-                .into_keys()
-                .collect_vec();
-            if tasks.is_empty() {
+            let environment = args
+                .environment
+                .as_deref()
+                .map(|name| {
+                    let name = EnvironmentName::from_str(name)?;
+                    project
+                        .environment(&name)
+                        .ok_or_else(|| miette!("Environment `{}` not found in project", name))
+                })
+                .transpose()?;
+
+            if args.summary {
+                let environment = environment.unwrap_or_else(|| project.default_environment());
+                let tasks = environment.tasks(Some(Platform::current()), true)?;
+                if tasks.is_empty() {
+                    eprintln!("No tasks found",);
+                } else {
+                    println!(
+                        "{}",
+                        tasks.keys().map(|name| name.as_str()).sorted().format(" ")
+                    );
+                }
+                return Ok(());
+            }
+
+            let environments = match environment {
+                Some(environment) => vec![environment],
+                None => project.environments(),
+            };
+            let tasks = collect_task_info(&environments);
+            if args.json {
+                let json =
+                    serde_json::to_string_pretty(&tasks).expect("Cannot serialize tasks to JSON");
+                println!("{}", json);
+            } else if tasks.is_empty() {
                 eprintln!("No tasks found",);
             } else {
-                let formatted: String = tasks
-                    .iter()
-                    .sorted()
-                    .map(|name| {
-                        if args.summary {
-                            format!("{} ", name.as_str(),)
-                        } else {
-                            format!("* {}\n", name.fancy_display().bold(),)
-                        }
-                    })
-                    .collect();
-
-                println!("{}", formatted);
+                print_tasks_as_table(&tasks).expect("an io error occurred");
             }
         }
+        Operation::Update(args) => {
+            let name = args.name.clone();
+            let platform = args.platform;
+            let feature = args
+                .feature
+                .clone()
+                .map_or(FeatureName::Default, FeatureName::Named);
+            let task = project
+                .manifest
+                .update_task(&name, platform, &feature, |task| (*args).apply(task))?;
+            project.save()?;
+            eprintln!(
+                "{}Updated task `{}`: {}",
+                console::style(console::Emoji("✔ ", "+")).green(),
+                name.fancy_display().bold(),
+                task,
+            );
+        }
+        Operation::Graph(args) => {
+            let environment = args
+                .environment
+                .as_deref()
+                .map(|name| {
+                    let name = EnvironmentName::from_str(name)?;
+                    project
+                        .environment(&name)
+                        .ok_or_else(|| miette!("Environment `{}` not found in project", name))
+                })
+                .transpose()?;
+            let search_environments =
+                SearchEnvironments::from_opt_env(&project, environment, Some(Platform::current()));
+            let task_graph = TaskGraph::from_cmd_args(&project, &search_environments, args.task)?;
+            print!("{}", task_graph.format(args.format));
+        }
     };
 
     Ok(())
 }
 
+/// Information about a task that is shown by `pixi task list`.
+#[derive(Serialize)]
+struct TaskInfo {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cmd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    depends_on: Vec<String>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    env: IndexMap<String, String>,
+    features: Vec<String>,
+    environments: Vec<String>,
+}
+
+/// Collects the tasks that are available on the current platform in any of the `environments`,
+/// sorted by name. If a task is defined in multiple environments, the definition of the first
+/// environment is used.
+fn collect_task_info(environments: &[Environment]) -> Vec<TaskInfo> {
+    let platform = Platform::current();
+    let mut tasks: BTreeMap<&TaskName, TaskInfo> = BTreeMap::new();
+    for environment in environments {
+        // Skip environments that are not available on the current platform.
+        let Ok(environment_tasks) = environment.tasks(Some(platform), true) else {
+            continue;
+        };
+
+        for (name, task) in environment_tasks {
+            let info = tasks.entry(name).or_insert_with(|| TaskInfo {
+                name: name.as_str().to_string(),
+                cmd: task.as_single_command().map(Cow::into_owned),
+                description: task.description().map(ToOwned::to_owned),
+                depends_on: task
+                    .depends_on()
+                    .iter()
+                    .map(|dependency| dependency.to_string())
+                    .collect(),
+                env: task.env().cloned().unwrap_or_default(),
+                features: Vec::new(),
+                environments: Vec::new(),
+            });
+
+            info.environments
+                .push(environment.name().as_str().to_string());
+
+            // Find the features of this environment that define the task.
+            for feature in environment.features(true) {
+                let defines_task = feature
+                    .targets
+                    .resolve(Some(platform))
+                    .any(|target| target.tasks.contains_key(name));
+                let feature_name = feature.name.as_str();
+                if defines_task && !info.features.iter().any(|f| f == feature_name) {
+                    info.features.push(feature_name.to_string());
+                }
+            }
+        }
+    }
+    tasks.into_values().collect()
+}
+
+fn print_tasks_as_table(tasks: &[TaskInfo]) -> io::Result<()> {
+    let mut writer = tabwriter::TabWriter::new(stdout());
+
+    let header_style = console::Style::new().bold();
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}",
+        header_style.apply_to("Task"),
+        header_style.apply_to("Description"),
+        header_style.apply_to("Depends on"),
+        header_style.apply_to("Features"),
+        header_style.apply_to("Environments"),
+    )?;
+
+    for task in tasks {
+        write!(
+            writer,
+            "{}",
+            console::style(&task.name).fg(Color::Blue).bold()
+        )?;
+        if !task.env.is_empty() {
+            write!(
+                writer,
+                " {}",
+                console::style(format!(
+                    "(env: {})",
+                    task.env
+                        .iter()
+                        .format_with(", ", |(key, value), f| f(&format_args!("{key}={value}")))
+                ))
+                .dim()
+            )?;
+        }
+        writeln!(
+            writer,
+            "\t{}\t{}\t{}\t{}",
+            task.description.as_deref().unwrap_or(""),
+            task.depends_on.join(", "),
+            task.features.join(", "),
+            task.environments.join(", "),
+        )?;
+    }
+
+    writer.flush()
+}
+
+impl From<Dependency> for Value {
+    fn from(value: Dependency) -> Self {
+        if value.args.is_none() && value.environment.is_none() {
+            return Value::from(String::from(value.task_name));
+        }
+        let mut table = InlineTable::new();
+        table.insert("task", String::from(value.task_name).into());
+        if let Some(args) = value.args {
+            table.insert("args", Value::Array(Array::from_iter(args)));
+        }
+        if let Some(environment) = value.environment {
+            table.insert("environment", environment.as_str().into());
+        }
+        Value::InlineTable(table)
+    }
+}
+
 impl From<Task> for Item {
     fn from(value: Task) -> Self {
         match value {
@@ -299,17 +907,70 @@ impl From<Task> for Item {
                     table.insert(
                         "depends_on",
                         Value::Array(Array::from_iter(
-                            process
-                                .depends_on
-                                .into_iter()
-                                .map(String::from)
-                                .map(Value::from),
+                            process.depends_on.into_iter().map(Value::from),
                         )),
                     );
                 }
                 if let Some(cwd) = process.cwd {
                     table.insert("cwd", cwd.to_string_lossy().to_string().into());
                 }
+                if let Some(env) = process.env {
+                    table.insert(
+                        "env",
+                        Value::InlineTable(
+                            env.into_iter()
+                                .map(|(key, value)| (key, Value::from(value)))
+                                .collect(),
+                        ),
+                    );
+                }
+                if let Some(env_file) = process.env_file {
+                    table.insert(
+                        "env_file",
+                        Value::Array(Array::from_iter(
+                            env_file
+                                .iter()
+                                .map(|path| path.to_string_lossy().into_owned()),
+                        )),
+                    );
+                }
+                if let Some(inputs) = process.inputs {
+                    table.insert("inputs", Value::Array(Array::from_iter(inputs)));
+                }
+                if let Some(outputs) = process.outputs {
+                    table.insert("outputs", Value::Array(Array::from_iter(outputs)));
+                }
+                if let Some(description) = process.description {
+                    table.insert("description", description.into());
+                }
+                if let Some(args) = process.args {
+                    table.insert(
+                        "args",
+                        Value::Array(Array::from_iter(args.into_iter().map(|arg| {
+                            let mut table = InlineTable::new();
+                            table.insert("name", arg.name.into());
+                            if let Some(default) = arg.default {
+                                table.insert("default", default.into());
+                            }
+                            Value::InlineTable(table)
+                        }))),
+                    );
+                }
+                if let Some(timeout) = process.timeout {
+                    table.insert("timeout", timeout.to_string().into());
+                }
+                if let Some(retries) = process.retries {
+                    table.insert("retries", i64::from(retries).into());
+                }
+                if let Some(allow_failure) = process.allow_failure {
+                    table.insert("allow_failure", allow_failure.into());
+                }
+                if let Some(environment) = process.environment {
+                    table.insert("environment", environment.as_str().into());
+                }
+                if let Some(shell) = process.shell {
+                    table.insert("shell", shell.as_str().into());
+                }
                 Item::Value(Value::InlineTable(table))
             }
             Task::Alias(alias) => {
@@ -317,13 +978,15 @@ impl From<Task> for Item {
                 table.insert(
                     "depends_on",
                     Value::Array(Array::from_iter(
-                        alias
-                            .depends_on
-                            .into_iter()
-                            .map(String::from)
-                            .map(Value::from),
+                        alias.depends_on.into_iter().map(Value::from),
                     )),
                 );
+                if let Some(description) = alias.description {
+                    table.insert("description", description.into());
+                }
+                if let Some(environment) = alias.environment {
+                    table.insert("environment", environment.as_str().into());
+                }
                 Item::Value(Value::InlineTable(table))
             }
             _ => Item::None,
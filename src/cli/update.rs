@@ -0,0 +1,337 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Parser;
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use rattler_conda_types::Platform;
+use rattler_lock::{LockFile, Package};
+use rip::types::NormalizedPackageName;
+
+use crate::consts;
+use crate::lock_file::{
+    update_lock_file, write_lock_file, OutdatedEnvironments, UpdateLockFileOptions,
+};
+use crate::project::manifest::EnvironmentName;
+use crate::project::Environment;
+use crate::Project;
+
+/// Update dependencies as recorded in the local lock file
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// The packages to update. When omitted, all packages are updated.
+    pub packages: Vec<String>,
+
+    /// The path to 'pixi.toml'
+    #[arg(long)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// The environments to update. When omitted, all environments are updated.
+    #[arg(long, short)]
+    pub environment: Vec<String>,
+
+    /// The platforms to update. When omitted, all platforms are updated.
+    #[arg(long, short)]
+    pub platform: Vec<Platform>,
+
+    /// Don't install the environment for pypi solving, only update the lock-file if it can solve without installing.
+    #[arg(long)]
+    pub no_install: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.manifest_path.as_deref())?;
+
+    // Determine the environments and platforms to update.
+    let environments = if args.environment.is_empty() {
+        project.environments()
+    } else {
+        args.environment
+            .iter()
+            .map(|name| {
+                let name = EnvironmentName::from_str(name)?;
+                project
+                    .environment(&name)
+                    .ok_or_else(|| miette::miette!("unknown environment '{name}'"))
+            })
+            .collect::<miette::Result<Vec<_>>>()?
+    };
+    let targets = environments
+        .into_iter()
+        .map(|environment| {
+            let platforms = environment
+                .platforms()
+                .into_iter()
+                .filter(|platform| args.platform.is_empty() || args.platform.contains(platform))
+                .collect::<HashSet<_>>();
+            (environment, platforms)
+        })
+        .filter(|(_, platforms)| !platforms.is_empty())
+        .collect::<HashMap<_, _>>();
+    if targets.is_empty() {
+        miette::bail!(
+            "none of the selected environments support the platform(s) {}",
+            args.platform.iter().format(", ")
+        );
+    }
+
+    // Make sure the lock-file is up-to-date with the project before updating any of the packages.
+    let derived = project
+        .up_to_date_lock_file(UpdateLockFileOptions {
+            no_install: args.no_install,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?;
+    let old_lock_file = derived.lock_file;
+
+    // Verify that the packages that are requested to be updated are actually locked.
+    for name in &args.packages {
+        let is_locked = targets.iter().any(|(environment, platforms)| {
+            platforms.iter().any(|platform| {
+                locked_packages(&old_lock_file, environment, *platform)
+                    .any(|package| package_name_matches(&package, name))
+            })
+        });
+        if !is_locked {
+            miette::bail!(
+                "could not find a package named '{name}' in the lock-file of the selected environments"
+            );
+        }
+    }
+
+    // Remove the packages from the lock-file so the solver is free to pick a newer version of
+    // them, and re-solve the affected targets. The targets include all environments that share a
+    // solve-group with the selected environments, otherwise their locked records would still pin
+    // the packages.
+    let outdated = OutdatedEnvironments::from_targets(targets);
+    let relaxed_lock_file = relax_lock_file(&old_lock_file, &outdated.conda, &args.packages)?;
+    let updated = update_lock_file(
+        &project,
+        relaxed_lock_file,
+        derived.pypi_metadata,
+        outdated,
+        UpdateLockFileOptions {
+            no_install: args.no_install,
+            existing_repo_data: derived.repo_data,
+            ..UpdateLockFileOptions::default()
+        },
+    )
+    .await?;
+    let new_lock_file = updated.lock_file;
+    write_lock_file(&project, &new_lock_file, &updated.pypi_metadata)
+        .context("failed to write lock-file to disk")?;
+
+    // Print the packages that changed.
+    let mut changed = false;
+    for environment in project.environments() {
+        for platform in environment.platforms() {
+            let diff = diff_packages(
+                locked_packages(&old_lock_file, &environment, platform),
+                locked_packages(&new_lock_file, &environment, platform),
+            );
+            if diff.is_empty() {
+                continue;
+            }
+            changed = true;
+
+            println!(
+                "Updated {} on {}:",
+                environment.name().fancy_display(),
+                consts::PLATFORM_STYLE.apply_to(platform)
+            );
+            for (name, change) in diff {
+                println!("  {}", change.format(&name));
+            }
+        }
+    }
+
+    if !changed {
+        eprintln!(
+            "{}Lock-file was already up-to-date",
+            console::style(console::Emoji("✔ ", "")).green(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the packages that are locked for the given environment and platform.
+fn locked_packages<'l>(
+    lock_file: &'l LockFile,
+    environment: &Environment,
+    platform: Platform,
+) -> impl Iterator<Item = Package> + 'l {
+    lock_file
+        .environment(environment.name().as_str())
+        .and_then(|env| env.packages(platform).map(Vec::from_iter))
+        .into_iter()
+        .flatten()
+}
+
+/// Returns true if the name of the locked package matches `name`. Pypi package names are compared
+/// in their normalized form.
+fn package_name_matches(package: &Package, name: &str) -> bool {
+    match package {
+        Package::Conda(package) => {
+            package.package_record().name.as_normalized() == name.to_lowercase()
+        }
+        Package::Pypi(package) => {
+            match (
+                NormalizedPackageName::from_str(&package.data().package.name),
+                NormalizedPackageName::from_str(name),
+            ) {
+                (Ok(locked), Ok(name)) => locked == name,
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Constructs a copy of `lock_file` in which the given `packages` are removed from the `targets`.
+/// If no packages are given, all packages are removed from the targets.
+pub(crate) fn relax_lock_file(
+    lock_file: &LockFile,
+    targets: &HashMap<Environment, HashSet<Platform>>,
+    packages: &[String],
+) -> miette::Result<LockFile> {
+    let mut builder = LockFile::builder();
+    for (environment_name, environment) in lock_file.environments() {
+        builder.set_channels(environment_name, environment.channels().iter().cloned());
+
+        let relaxed_platforms = targets
+            .iter()
+            .find(|(env, _)| env.name().as_str() == environment_name)
+            .map(|(_, platforms)| platforms);
+
+        for (platform, locked_packages) in environment.packages_by_platform() {
+            let is_target = relaxed_platforms.map_or(false, |p| p.contains(&platform));
+            for package in locked_packages {
+                if is_target
+                    && (packages.is_empty()
+                        || packages
+                            .iter()
+                            .any(|name| package_name_matches(&package, name)))
+                {
+                    continue;
+                }
+
+                match package {
+                    Package::Conda(package) => {
+                        let record = rattler_conda_types::RepoDataRecord::try_from(package)
+                            .into_diagnostic()?;
+                        builder.add_conda_package(environment_name, platform, record.into());
+                    }
+                    Package::Pypi(package) => {
+                        let data = package.data();
+                        builder.add_pypi_package(
+                            environment_name,
+                            platform,
+                            data.package.clone(),
+                            data.environment.clone(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Ok(builder.finish())
+}
+
+/// The change of a single package between two lock-files.
+#[derive(Debug, PartialEq, Eq)]
+enum PackageChange {
+    Added(String),
+    Removed(String),
+    Changed(String, String),
+}
+
+impl PackageChange {
+    /// Formats the change of the package with the given name for display in the terminal.
+    fn format(&self, name: &str) -> String {
+        match self {
+            PackageChange::Added(version) => format!(
+                "{} {} {}",
+                console::style("+").green(),
+                console::style(name).bold(),
+                version
+            ),
+            PackageChange::Removed(version) => format!(
+                "{} {} {}",
+                console::style("-").red(),
+                console::style(name).bold(),
+                version
+            ),
+            PackageChange::Changed(old, new) => format!(
+                "{} {} {} -> {}",
+                console::style("~").yellow(),
+                console::style(name).bold(),
+                old,
+                new
+            ),
+        }
+    }
+}
+
+/// Returns the version of the package and, for conda packages, its build string.
+fn package_version(package: &Package) -> (String, Option<String>) {
+    match package {
+        Package::Conda(package) => {
+            let record = package.package_record();
+            (record.version.to_string(), Some(record.build.clone()))
+        }
+        Package::Pypi(package) => (package.data().package.version.to_string(), None),
+    }
+}
+
+/// Returns the name of the package as displayed in the diff. Pypi packages are suffixed to
+/// distinguish them from conda packages with the same name.
+fn package_display_name(package: &Package) -> String {
+    match package {
+        Package::Conda(package) => package.package_record().name.as_source().to_string(),
+        Package::Pypi(package) => format!("{} (pypi)", package.data().package.name),
+    }
+}
+
+/// Computes the packages that were added, removed or changed between `old` and `new`, sorted by
+/// name.
+fn diff_packages(
+    old: impl IntoIterator<Item = Package>,
+    new: impl IntoIterator<Item = Package>,
+) -> BTreeMap<String, PackageChange> {
+    let old = old
+        .into_iter()
+        .map(|p| (package_display_name(&p), package_version(&p)))
+        .collect::<HashMap<_, _>>();
+    let mut new = new
+        .into_iter()
+        .map(|p| (package_display_name(&p), package_version(&p)))
+        .collect::<HashMap<_, _>>();
+
+    let mut diff = BTreeMap::new();
+    for (name, old_version) in old {
+        match new.remove(&name) {
+            Some(new_version) if new_version == old_version => {}
+            // Only show the build strings if the version itself did not change.
+            Some((new_version, new_build)) if new_version == old_version.0 => {
+                let format_build = |build: Option<String>| match build {
+                    Some(build) => format!("{new_version} ({build})"),
+                    None => new_version.clone(),
+                };
+                let change =
+                    PackageChange::Changed(format_build(old_version.1), format_build(new_build));
+                diff.insert(name, change);
+            }
+            Some((new_version, _)) => {
+                diff.insert(name, PackageChange::Changed(old_version.0, new_version));
+            }
+            None => {
+                diff.insert(name, PackageChange::Removed(old_version.0));
+            }
+        }
+    }
+    for (name, (new_version, _)) in new {
+        diff.insert(name, PackageChange::Added(new_version));
+    }
+    diff
+}
@@ -0,0 +1,631 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Parser;
+use itertools::{Either, Itertools, MinMaxResult};
+use miette::WrapErr;
+use pep440_rs::{Operator, VersionSpecifier, VersionSpecifiers};
+use rattler_conda_types::{
+    version_spec::{EqualityOperator, LogicalOperator, RangeOperator, StrictRangeOperator},
+    PackageName, Platform, StrictVersion, Version, VersionBumpType, VersionSpec,
+};
+use rattler_lock::{LockFile, Package};
+
+use super::update::relax_lock_file;
+use crate::environment::{get_up_to_date_prefix, LockFileUsage};
+use crate::lock_file::{
+    load_lock_file_with_metadata, update_lock_file, write_lock_file, OutdatedEnvironments,
+    UpdateLockFileOptions,
+};
+use crate::project::manifest::TargetSelector;
+use crate::{FeatureName, Project, SpecType};
+
+/// Upgrade the version specs of the dependencies in `pixi.toml` to the newest compatible versions
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// The dependencies to upgrade. When omitted, all dependencies of the feature are upgraded.
+    pub packages: Vec<String>,
+
+    /// The path to 'pixi.toml'
+    #[arg(long)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// The feature whose dependencies should be upgraded, defaults to the default feature.
+    #[arg(long, short)]
+    pub feature: Option<String>,
+
+    /// Only show the upgraded version specs, don't modify `pixi.toml`, the lock-file or the
+    /// environment.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Don't install the environment, only update `pixi.toml` and the lock-file.
+    #[arg(long)]
+    pub no_install: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let mut project = Project::load_or_else_discover(args.manifest_path.as_deref())?;
+    let feature_name = args
+        .feature
+        .map_or(FeatureName::Default, FeatureName::Named);
+    let feature = project
+        .manifest
+        .feature(&feature_name)
+        .ok_or_else(|| miette::miette!("feature '{}' does not exist", feature_name.as_str()))?;
+
+    // Find the dependencies of the feature that should be upgraded.
+    let mut dependencies = Vec::new();
+    for (target, selector) in feature.targets.iter() {
+        let platform = match selector {
+            None => None,
+            Some(TargetSelector::Platform(platform)) => Some(*platform),
+            Some(_) => continue,
+        };
+        for spec_type in SpecType::all() {
+            for (name, spec) in target.dependencies.get(&spec_type).into_iter().flatten() {
+                dependencies.push(Dependency {
+                    kind: DependencyKind::Conda(name.clone(), spec_type),
+                    platform,
+                    current: DependencyVersion::Conda(spec.version.clone()),
+                });
+            }
+        }
+        for (name, requirement) in target.pypi_dependencies.iter().flatten() {
+            dependencies.push(Dependency {
+                kind: DependencyKind::Pypi(name.clone()),
+                platform,
+                current: DependencyVersion::Pypi(requirement.version.clone()),
+            });
+        }
+    }
+
+    // Only keep the dependencies that were requested and have a version spec we can upgrade.
+    for name in &args.packages {
+        if !dependencies.iter().any(|dep| dep.matches_name(name)) {
+            miette::bail!(
+                "'{name}' is not a dependency of the {} feature",
+                feature_name.as_str()
+            );
+        }
+    }
+    let dependencies = dependencies
+        .into_iter()
+        .filter(|dep| args.packages.is_empty() || args.packages.iter().any(|n| dep.matches_name(n)))
+        .filter(|dep| {
+            let supported = dep.pin().is_some();
+            if !supported && !args.packages.is_empty() {
+                tracing::warn!(
+                    "the version spec '{}' of '{}' can not be upgraded automatically",
+                    dep.current_display(),
+                    dep.name()
+                );
+            }
+            supported
+        })
+        .collect_vec();
+    if dependencies.is_empty() {
+        eprintln!(
+            "{}There are no dependencies to upgrade",
+            console::style(console::Emoji("✔ ", "")).green(),
+        );
+        return Ok(());
+    }
+
+    // Remove the version specs of the dependencies, so the solver is free to pick the newest
+    // versions that are compatible with the rest of the environments.
+    for dep in &dependencies {
+        dep.set_version(&mut project, &feature_name, None)?;
+    }
+
+    // Solve all environments that use the feature, without the locked records of the
+    // dependencies.
+    let targets = project
+        .environments()
+        .into_iter()
+        .filter(|env| {
+            env.features(true)
+                .any(|feature| feature.name == feature_name)
+        })
+        .map(|env| {
+            let platforms = env.platforms();
+            (env, platforms)
+        })
+        .collect::<HashMap<_, _>>();
+    let outdated = OutdatedEnvironments::from_targets(targets);
+    let (lock_file, pypi_metadata) = load_lock_file_with_metadata(&project).await?;
+    let relaxed_lock_file = relax_lock_file(
+        &lock_file,
+        &outdated.conda,
+        &dependencies.iter().map(Dependency::name).collect_vec(),
+    )?;
+    // A dry-run must not touch the environments on disk, so never install the prefix for pypi
+    // solving in that case.
+    let derived = update_lock_file(
+        &project,
+        relaxed_lock_file,
+        pypi_metadata,
+        outdated,
+        UpdateLockFileOptions {
+            no_install: args.no_install || args.dry_run,
+            ..UpdateLockFileOptions::default()
+        },
+    )
+    .await?;
+
+    // Determine the upgraded version specs from the solved versions.
+    let upgrades = dependencies
+        .into_iter()
+        .filter_map(|dep| {
+            let upgraded = dep.upgraded_version(&project, &feature_name, &derived.lock_file)?;
+            Some((dep, upgraded))
+        })
+        .collect_vec();
+    if upgrades.is_empty() {
+        eprintln!(
+            "{}All dependencies are already up-to-date",
+            console::style(console::Emoji("✔ ", "")).green(),
+        );
+        return Ok(());
+    }
+
+    print_upgrades_as_table(&upgrades).expect("an io error occurred");
+    if args.dry_run {
+        return Ok(());
+    }
+
+    // Write the upgraded version specs to a freshly loaded manifest, so the relaxed specs are not
+    // written to disk.
+    let mut project = Project::load_or_else_discover(Some(&project.manifest_path()))?;
+    for (dep, upgraded) in &upgrades {
+        dep.set_version(&mut project, &feature_name, Some(upgraded))?;
+    }
+    project.save()?;
+
+    // The solved lock-file satisfies the upgraded specs, store it and update the prefix.
+    write_lock_file(&project, &derived.lock_file, &derived.pypi_metadata)
+        .context("failed to write lock-file to disk")?;
+    get_up_to_date_prefix(
+        &project.default_environment(),
+        LockFileUsage::Update,
+        args.no_install,
+        derived.repo_data,
+    )
+    .await?;
+
+    eprintln!(
+        "{}Upgraded {} {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        upgrades.len(),
+        if upgrades.len() == 1 {
+            "dependency"
+        } else {
+            "dependencies"
+        }
+    );
+
+    Ok(())
+}
+
+/// A dependency of the feature that is being upgraded.
+struct Dependency {
+    kind: DependencyKind,
+
+    /// The platform of the target the dependency is defined in, or `None` for the default target.
+    platform: Option<Platform>,
+
+    /// The version spec of the dependency as it is currently written in the manifest.
+    current: DependencyVersion,
+}
+
+enum DependencyKind {
+    Conda(PackageName, SpecType),
+    Pypi(rip::types::PackageName),
+}
+
+/// The version spec of a dependency. Only the version of a `NamelessMatchSpec` or
+/// `PyPiRequirement` is upgraded, the other fields are kept as they are.
+enum DependencyVersion {
+    Conda(Option<VersionSpec>),
+    Pypi(Option<VersionSpecifiers>),
+}
+
+/// The upgraded version spec of a dependency.
+enum UpgradedVersion {
+    Conda(VersionSpec),
+    Pypi(VersionSpecifiers),
+}
+
+impl std::fmt::Display for UpgradedVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpgradedVersion::Conda(spec) => write!(f, "{spec}"),
+            UpgradedVersion::Pypi(specifiers) => write!(f, "{specifiers}"),
+        }
+    }
+}
+
+/// The style in which a version spec pins a package. Upgraded version specs are written in the
+/// same style as the original.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pin<V> {
+    /// `>=1.2`
+    LowerBound(V),
+    /// `>=1.2,<1.3`, with the number of segments of the upper bound.
+    Range(V, usize),
+    /// `==1.2`
+    Exact(V),
+    /// `1.2.*`
+    StartsWith(V),
+    /// `~=1.2`
+    Compatible(V),
+}
+
+impl<V: Ord> Pin<V> {
+    /// Returns the version the package is currently pinned to.
+    fn version(&self) -> &V {
+        match self {
+            Pin::LowerBound(version)
+            | Pin::Range(version, _)
+            | Pin::Exact(version)
+            | Pin::StartsWith(version)
+            | Pin::Compatible(version) => version,
+        }
+    }
+}
+
+impl Dependency {
+    fn name(&self) -> String {
+        match &self.kind {
+            DependencyKind::Conda(name, _) => name.as_normalized().to_string(),
+            DependencyKind::Pypi(name) => name.as_str().to_string(),
+        }
+    }
+
+    /// Returns true if `name` refers to this dependency.
+    fn matches_name(&self, name: &str) -> bool {
+        match &self.kind {
+            DependencyKind::Conda(dep_name, _) => dep_name.as_normalized() == name.to_lowercase(),
+            DependencyKind::Pypi(dep_name) => {
+                rip::types::PackageName::from_str(name).map_or(false, |name| &name == dep_name)
+            }
+        }
+    }
+
+    /// Returns the name of the dependency table the dependency is defined in.
+    fn table_display(&self) -> String {
+        let table = match &self.kind {
+            DependencyKind::Conda(_, spec_type) => spec_type.name(),
+            DependencyKind::Pypi(_) => crate::consts::PYPI_DEPENDENCIES,
+        };
+        match self.platform {
+            Some(platform) => format!("{table} ({platform})"),
+            None => table.to_string(),
+        }
+    }
+
+    fn current_display(&self) -> String {
+        match &self.current {
+            DependencyVersion::Conda(Some(spec)) => spec.to_string(),
+            DependencyVersion::Pypi(Some(specifiers)) => specifiers.to_string(),
+            DependencyVersion::Conda(None) | DependencyVersion::Pypi(None) => String::from("*"),
+        }
+    }
+
+    /// Returns the pinning style of the current version spec, or `None` if it can not be
+    /// upgraded.
+    fn pin(&self) -> Option<Either<Pin<Version>, Pin<pep440_rs::Version>>> {
+        match &self.current {
+            DependencyVersion::Conda(spec) => conda_pin(spec.as_ref()?).map(Either::Left),
+            DependencyVersion::Pypi(specifiers) => {
+                pypi_pin(specifiers.as_ref()?).map(Either::Right)
+            }
+        }
+    }
+
+    /// Writes the given version spec to the manifest, or removes the version spec if `version`
+    /// is `None`.
+    fn set_version(
+        &self,
+        project: &mut Project,
+        feature_name: &FeatureName,
+        version: Option<&UpgradedVersion>,
+    ) -> miette::Result<()> {
+        match (&self.kind, version) {
+            (DependencyKind::Conda(name, spec_type), None) => project
+                .manifest
+                .set_dependency_version(name, *spec_type, self.platform, feature_name, None),
+            (DependencyKind::Conda(name, spec_type), Some(UpgradedVersion::Conda(spec))) => {
+                project.manifest.set_dependency_version(
+                    name,
+                    *spec_type,
+                    self.platform,
+                    feature_name,
+                    Some(spec.clone()),
+                )
+            }
+            (DependencyKind::Pypi(name), None) => project.manifest.set_pypi_dependency_version(
+                name,
+                self.platform,
+                feature_name,
+                None,
+            ),
+            (DependencyKind::Pypi(name), Some(UpgradedVersion::Pypi(specifiers))) => {
+                project.manifest.set_pypi_dependency_version(
+                    name,
+                    self.platform,
+                    feature_name,
+                    Some(specifiers.clone()),
+                )
+            }
+            _ => unreachable!("the upgraded version should match the kind of the dependency"),
+        }
+    }
+
+    /// Determines the upgraded version spec from the versions of the dependency in the solved
+    /// `lock_file`. Returns `None` if the dependency can not be upgraded to a newer version.
+    fn upgraded_version(
+        &self,
+        project: &Project,
+        feature_name: &FeatureName,
+        lock_file: &LockFile,
+    ) -> Option<UpgradedVersion> {
+        // Collect the locked packages of all the environments and platforms that use the
+        // dependency.
+        let packages = project
+            .environments()
+            .into_iter()
+            .filter(|env| {
+                env.features(true)
+                    .any(|feature| &feature.name == feature_name)
+            })
+            .flat_map(|env| {
+                let locked_environment = lock_file.environment(env.name().as_str());
+                env.platforms()
+                    .into_iter()
+                    .filter(|platform| self.platform.map_or(true, |p| p == *platform))
+                    .filter_map(|platform| {
+                        Some(
+                            locked_environment
+                                .as_ref()?
+                                .packages(platform)?
+                                .collect_vec(),
+                        )
+                    })
+                    .flatten()
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        match (&self.kind, self.pin()?) {
+            (DependencyKind::Conda(name, _), Either::Left(pin)) => {
+                let versions = packages
+                    .iter()
+                    .filter_map(Package::as_conda)
+                    .filter(|package| &package.package_record().name == name)
+                    .map(|package| package.package_record().version.version().clone())
+                    .collect::<HashSet<_>>();
+                let (min, max) = min_max(versions)?;
+                upgrade_conda_pin(&pin, &min, &max).map(UpgradedVersion::Conda)
+            }
+            (DependencyKind::Pypi(name), Either::Right(pin)) => {
+                let versions = packages
+                    .iter()
+                    .filter_map(Package::as_pypi)
+                    .filter(|package| {
+                        rip::types::PackageName::from_str(&package.data().package.name)
+                            .map_or(false, |package_name| &package_name == name)
+                    })
+                    .map(|package| package.data().package.version.clone())
+                    .collect::<HashSet<_>>();
+                let (min, max) = min_max(versions)?;
+                upgrade_pypi_pin(&pin, &min, &max).map(UpgradedVersion::Pypi)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the smallest and largest version.
+fn min_max<V: Ord + Clone>(versions: impl IntoIterator<Item = V>) -> Option<(V, V)> {
+    match versions.into_iter().minmax() {
+        MinMaxResult::NoElements => None,
+        MinMaxResult::OneElement(version) => Some((version.clone(), version)),
+        MinMaxResult::MinMax(min, max) => Some((min, max)),
+    }
+}
+
+/// Determines the pinning style of a conda version spec.
+fn conda_pin(spec: &VersionSpec) -> Option<Pin<Version>> {
+    match spec {
+        VersionSpec::Range(RangeOperator::GreaterEquals, version) => {
+            Some(Pin::LowerBound(version.clone()))
+        }
+        VersionSpec::Exact(EqualityOperator::Equals, version) => Some(Pin::Exact(version.clone())),
+        VersionSpec::StrictRange(StrictRangeOperator::StartsWith, StrictVersion(version)) => {
+            Some(Pin::StartsWith(version.clone()))
+        }
+        VersionSpec::StrictRange(StrictRangeOperator::Compatible, StrictVersion(version)) => {
+            Some(Pin::Compatible(version.clone()))
+        }
+        VersionSpec::Group(LogicalOperator::And, specs) => match specs.as_slice() {
+            [VersionSpec::Range(RangeOperator::GreaterEquals, lower), VersionSpec::Range(RangeOperator::Less, upper)] => {
+                Some(Pin::Range(lower.clone(), upper.segment_count()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Constructs a conda version spec in the style of `pin` that includes all versions from `min` to
+/// `max`. Returns `None` if `min` is not newer than the currently pinned version.
+fn upgrade_conda_pin(pin: &Pin<Version>, min: &Version, max: &Version) -> Option<VersionSpec> {
+    let truncate = |version: &Version, segments: usize| {
+        version
+            .with_segments(..segments)
+            .unwrap_or_else(|| version.clone())
+    };
+    let spec = match pin {
+        Pin::LowerBound(_) => VersionSpec::Range(RangeOperator::GreaterEquals, min.clone()),
+        Pin::Range(_, upper_segments) => VersionSpec::Group(
+            LogicalOperator::And,
+            vec![
+                VersionSpec::Range(RangeOperator::GreaterEquals, min.clone()),
+                VersionSpec::Range(
+                    RangeOperator::Less,
+                    truncate(max, *upper_segments)
+                        .bump(VersionBumpType::Last)
+                        .ok()?,
+                ),
+            ],
+        ),
+        Pin::Exact(_) => VersionSpec::Exact(EqualityOperator::Equals, min.clone()),
+        Pin::StartsWith(current) => VersionSpec::StrictRange(
+            StrictRangeOperator::StartsWith,
+            StrictVersion(truncate(min, current.segment_count())),
+        ),
+        Pin::Compatible(current) => VersionSpec::StrictRange(
+            StrictRangeOperator::Compatible,
+            StrictVersion(truncate(min, current.segment_count())),
+        ),
+    };
+    (min > pin.version() && conda_pin(&spec).as_ref() != Some(pin)).then_some(spec)
+}
+
+/// Determines the pinning style of pypi version specifiers.
+fn pypi_pin(specifiers: &VersionSpecifiers) -> Option<Pin<pep440_rs::Version>> {
+    match &specifiers[..] {
+        [specifier] => {
+            let version = specifier.version().clone();
+            match specifier.operator() {
+                Operator::GreaterThanEqual => Some(Pin::LowerBound(version)),
+                Operator::Equal => Some(Pin::Exact(version)),
+                Operator::EqualStar => Some(Pin::StartsWith(version)),
+                Operator::TildeEqual => Some(Pin::Compatible(version)),
+                _ => None,
+            }
+        }
+        [lower, upper]
+            if lower.operator() == &Operator::GreaterThanEqual
+                && upper.operator() == &Operator::LessThan =>
+        {
+            Some(Pin::Range(
+                lower.version().clone(),
+                upper.version().release.len(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Constructs pypi version specifiers in the style of `pin` that include all versions from `min`
+/// to `max`. Returns `None` if `min` is not newer than the currently pinned version.
+fn upgrade_pypi_pin(
+    pin: &Pin<pep440_rs::Version>,
+    min: &pep440_rs::Version,
+    max: &pep440_rs::Version,
+) -> Option<VersionSpecifiers> {
+    let truncate = |version: &pep440_rs::Version, segments: usize| {
+        pep440_rs::Version::from_release(version.release.iter().take(segments).copied().collect())
+    };
+    let specifier = |operator, version| VersionSpecifier::new(operator, version, false).ok();
+    let specifiers = match pin {
+        Pin::LowerBound(_) => vec![specifier(Operator::GreaterThanEqual, min.clone())?],
+        Pin::Range(_, upper_segments) => {
+            let mut upper = truncate(max, *upper_segments);
+            *upper.release.last_mut()? += 1;
+            vec![
+                specifier(Operator::GreaterThanEqual, min.clone())?,
+                specifier(Operator::LessThan, upper)?,
+            ]
+        }
+        Pin::Exact(_) => vec![specifier(Operator::Equal, min.clone())?],
+        Pin::StartsWith(current) => vec![specifier(
+            Operator::EqualStar,
+            truncate(min, current.release.len()),
+        )?],
+        Pin::Compatible(current) => vec![specifier(
+            Operator::TildeEqual,
+            truncate(min, current.release.len()),
+        )?],
+    };
+    let specifiers = VersionSpecifiers::from_iter(specifiers);
+    (min > pin.version() && pypi_pin(&specifiers).as_ref() != Some(pin)).then_some(specifiers)
+}
+
+fn print_upgrades_as_table(upgrades: &[(Dependency, UpgradedVersion)]) -> std::io::Result<()> {
+    let mut writer = tabwriter::TabWriter::new(stdout());
+
+    let header_style = console::Style::new().bold();
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{}",
+        header_style.apply_to("Package"),
+        header_style.apply_to("Table"),
+        header_style.apply_to("Old"),
+        header_style.apply_to("New"),
+    )?;
+
+    for (dep, upgraded) in upgrades {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            console::style(dep.name()).bold(),
+            dep.table_display(),
+            dep.current_display(),
+            console::style(upgraded).green(),
+        )?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgrade_conda(spec: &str, min: &str, max: &str) -> Option<String> {
+        let pin = conda_pin(&VersionSpec::from_str(spec).unwrap()).unwrap();
+        upgrade_conda_pin(&pin, &min.parse().unwrap(), &max.parse().unwrap())
+            .map(|spec| spec.to_string())
+    }
+
+    fn upgrade_pypi(spec: &str, min: &str, max: &str) -> Option<String> {
+        let pin = pypi_pin(&VersionSpecifiers::from_str(spec).unwrap()).unwrap();
+        upgrade_pypi_pin(&pin, &min.parse().unwrap(), &max.parse().unwrap())
+            .map(|spec| spec.to_string())
+    }
+
+    #[test]
+    fn test_upgrade_conda_pin() {
+        insta::assert_snapshot!(upgrade_conda(">=1.2.0,<1.3", "1.4.1", "1.4.1").unwrap(), @">=1.4.1,<1.5");
+        insta::assert_snapshot!(upgrade_conda(">=1.2.0,<2", "2.3.0", "3.0.1").unwrap(), @">=2.3.0,<4");
+        insta::assert_snapshot!(upgrade_conda(">=1.2", "1.4", "1.4").unwrap(), @">=1.4");
+        insta::assert_snapshot!(upgrade_conda("==1.2.0", "1.4.0", "1.4.0").unwrap(), @"==1.4.0");
+        insta::assert_snapshot!(upgrade_conda("1.2.*", "1.4.3", "1.4.3").unwrap(), @"1.4.*");
+        insta::assert_snapshot!(upgrade_conda("~=1.2", "1.4.3", "1.4.3").unwrap(), @"~=1.4");
+
+        // Versions that are not newer, or that do not change the spec, are not upgraded.
+        assert_eq!(upgrade_conda(">=1.2", "1.2", "1.2"), None);
+        assert_eq!(upgrade_conda("1.2.*", "1.2.5", "1.2.5"), None);
+
+        // Unsupported pinning styles
+        assert!(conda_pin(&VersionSpec::from_str("<2").unwrap()).is_none());
+        assert!(conda_pin(&VersionSpec::from_str(">=1,<2|>3").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_upgrade_pypi_pin() {
+        insta::assert_snapshot!(upgrade_pypi(">=1.2.0,<1.3", "1.4.1", "1.4.1").unwrap(), @">=1.4.1, <1.5");
+        insta::assert_snapshot!(upgrade_pypi(">=1.2", "1.4", "1.4").unwrap(), @">=1.4");
+        insta::assert_snapshot!(upgrade_pypi("==1.2.0", "1.4.0", "1.4.0").unwrap(), @"==1.4.0");
+        insta::assert_snapshot!(upgrade_pypi("==1.2.*", "1.4.3", "1.4.3").unwrap(), @"==1.4.*");
+        insta::assert_snapshot!(upgrade_pypi("~=1.2", "1.4.3", "1.4.3").unwrap(), @"~=1.4");
+
+        assert_eq!(upgrade_pypi("==1.2.0", "1.2.0", "1.2.0"), None);
+        assert!(pypi_pin(&VersionSpecifiers::from_str("!=1.2").unwrap()).is_none());
+    }
+}
@@ -3,10 +3,13 @@ use lazy_static::lazy_static;
 
 pub const PROJECT_MANIFEST: &str = "pixi.toml";
 pub const PROJECT_LOCK_FILE: &str = "pixi.lock";
+pub const PYPI_LOCK_METADATA_FILE: &str = "pixi-pypi.lock";
 pub const PIXI_DIR: &str = ".pixi";
 pub const PREFIX_FILE_NAME: &str = "prefix";
 pub const ENVIRONMENTS_DIR: &str = "envs";
 pub const SOLVE_GROUP_ENVIRONMENTS_DIR: &str = "solve-group-envs";
+pub const TASK_CACHE_DIR: &str = "task-cache";
+pub const TASK_SCRIPTS_DIR: &str = "task-scripts";
 pub const PYPI_DEPENDENCIES: &str = "pypi-dependencies";
 
 pub const DEFAULT_ENVIRONMENT_NAME: &str = "default";
@@ -22,8 +22,15 @@ use rattler_conda_types::{Channel, Platform, PrefixRecord, RepoDataRecord};
 use rattler_lock::{PypiPackageData, PypiPackageEnvironmentData};
 use rattler_repodata_gateway::sparse::SparseRepoData;
 use reqwest_middleware::ClientWithMiddleware;
-use rip::{index::PackageDb, resolve::solve_options::SDistResolution};
-use std::{collections::HashMap, io::ErrorKind, path::Path, sync::Arc};
+use rip::{
+    index::PackageDb, resolve::solve_options::SDistResolution, types::NormalizedPackageName,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    path::Path,
+    sync::Arc,
+};
 
 /// Verify the location of the prefix folder is not changed so the applied prefix path is still valid.
 /// Errors when there is a file system error or the path does not align with the defined prefix.
@@ -182,6 +189,8 @@ pub async fn update_prefix_pypi(
     system_requirements: &SystemRequirements,
     sdist_resolution: SDistResolution,
     env_variables: HashMap<String, String>,
+    project_root: &Path,
+    editable_packages: &HashSet<NormalizedPackageName>,
 ) -> miette::Result<()> {
     // Remove python packages from a previous python distribution if the python version changed.
     install_pypi::remove_old_python_distributions(prefix, platform, status)?;
@@ -203,6 +212,8 @@ pub async fn update_prefix_pypi(
                 system_requirements,
                 sdist_resolution,
                 env_variables,
+                project_root,
+                editable_packages,
             )
         },
     )
@@ -10,7 +10,9 @@ use miette::{IntoDiagnostic, WrapErr};
 use rip::resolve::solve_options::{ResolveOptions, SDistResolution};
 
 use crate::consts::PROJECT_MANIFEST;
+use crate::lock_file::pypi::from_project_relative_url;
 use crate::project::manifest::SystemRequirements;
+use crate::pypi_git::{self, GitSource};
 use crate::pypi_marker_env::determine_marker_environment;
 use crate::pypi_tags::{is_python_record, project_platform_tags};
 use pep508_rs::MarkerEnvironment;
@@ -24,22 +26,34 @@ use rip::python_env::{
     WheelTags,
 };
 use rip::types::{
-    ArtifactHashes, ArtifactInfo, ArtifactName, Extra, HasArtifactName, NormalizedPackageName,
+    ArtifactHashes, ArtifactInfo, ArtifactName, DirectUrlJson, DirectUrlSource, Extra,
+    HasArtifactName, NormalizedPackageName, Record, RecordEntry,
 };
 use rip::wheel_builder::WheelBuilder;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::task::JoinError;
+use url::Url;
 
 /// The installer name for pypi packages installed by pixi.
 pub(crate) const PIXI_PYPI_INSTALLER: &str = env!("CARGO_PKG_NAME");
 
 type CombinedPypiPackageData = (PypiPackageData, PypiPackageEnvironmentData);
 
+/// A wheel to install together with its hash, the extras to install its entry points for, and the
+/// url and source directory of the package if it is installed in editable mode.
+type ArtifactToInstall = (
+    Option<String>,
+    HashSet<Extra>,
+    Wheel,
+    Option<(Url, PathBuf)>,
+);
+
 /// Installs and/or remove python distributions.
 // TODO: refactor arguments in struct
 #[allow(clippy::too_many_arguments)]
@@ -53,6 +67,8 @@ pub async fn update_python_distributions(
     system_requirements: &SystemRequirements,
     sdist_resolution: SDistResolution,
     env_variables: HashMap<String, String>,
+    project_root: &Path,
+    editable_packages: &HashSet<NormalizedPackageName>,
 ) -> miette::Result<()> {
     let Some(python_info) = status.current_info() else {
         // No python interpreter in the environment, so there is nothing to do here.
@@ -76,7 +92,32 @@ pub async fn update_python_distributions(
             "failed to locate python packages that have not been installed as conda packages",
         )?;
 
-    // Determine the python packages that are part of the lock-file
+    // Determine the python packages that are part of the lock-file. Local packages are stored
+    // relative to the project root in the lock-file. Git packages are installed from a checkout of
+    // their locked commit.
+    let mut locked_python_packages = Vec::with_capacity(python_packages.len());
+    for (pkg_data, pkg_env_data) in python_packages {
+        let url = match GitSource::from_locked_url(&pkg_data.url) {
+            Some((source, commit)) => pypi_git::checkout_commit(source.repository, commit)
+                .await
+                .wrap_err_with(|| {
+                    format!(
+                        "failed to checkout the git repository of '{}'",
+                        pkg_data.name
+                    )
+                })?
+                .source_url(),
+            None => from_project_relative_url(&pkg_data.url, project_root),
+        };
+        locked_python_packages.push((
+            PypiPackageData {
+                url,
+                ..pkg_data.clone()
+            },
+            pkg_env_data.clone(),
+        ));
+    }
+    let python_packages = locked_python_packages;
     let python_packages = python_packages.iter().collect_vec();
 
     // Determine the python packages to remove before we start installing anything new. If the
@@ -87,8 +128,16 @@ pub async fn update_python_distributions(
             prefix.root(),
             current_python_packages,
             python_packages,
+            editable_packages,
         );
 
+    // Editable packages are built into a wheel like any other local package, but their sources are
+    // linked instead of installed.
+    let python_distributions_to_install = python_distributions_to_install
+        .into_iter()
+        .map(|pkg| (pkg, editable_source_dir(pkg, editable_packages)))
+        .collect_vec();
+
     // Determine the python interpreter that is installed as part of the conda packages.
     let python_record = conda_package
         .iter()
@@ -121,7 +170,7 @@ pub async fn update_python_distributions(
         marker_environment,
         compatible_tags,
         resolve_options,
-        python_distributions_to_install.clone(),
+        python_distributions_to_install,
         env_variables,
     );
 
@@ -154,12 +203,13 @@ pub async fn update_python_distributions(
     Ok(())
 }
 
-/// Concurrently installs python wheels as they become available.
+/// Concurrently installs python wheels as they become available. The wheels of editable packages
+/// are linked to their source directory after they are unpacked.
 async fn install_python_distributions(
     prefix: &Prefix,
     install_paths: InstallPaths,
     python_executable_path: &Path,
-    package_stream: impl Stream<Item = miette::Result<(Option<String>, HashSet<Extra>, Wheel)>> + Sized,
+    package_stream: impl Stream<Item = miette::Result<ArtifactToInstall>> + Sized,
 ) -> miette::Result<Option<ProgressBar>> {
     // Determine the number of packages that we are going to install
     let len = {
@@ -182,7 +232,7 @@ async fn install_python_distributions(
     // Concurrently unpack the wheels as they become available in the stream.
     let install_pb = pb.clone();
     package_stream
-        .try_for_each_concurrent(Some(20), move |(hash, extras, wheel)| {
+        .try_for_each_concurrent(Some(20), move |(hash, extras, wheel, editable)| {
             let install_paths = install_paths.clone();
             let root = prefix.root().to_path_buf();
             let message_formatter = message_formatter.clone();
@@ -191,7 +241,13 @@ async fn install_python_distributions(
             async move {
                 let pb_task = message_formatter.start(wheel.name().to_string()).await;
                 let unpack_result = tokio::task::spawn_blocking(move || {
-                    wheel
+                    let direct_url_json = editable.as_ref().map(|(url, _)| DirectUrlJson {
+                        url: url.clone(),
+                        source: DirectUrlSource::Dir {
+                            editable: Some(true),
+                        },
+                    });
+                    let unpacked_wheel = wheel
                         .unpack(
                             &root,
                             &install_paths,
@@ -199,18 +255,29 @@ async fn install_python_distributions(
                             &UnpackWheelOptions {
                                 installer: Some(PIXI_PYPI_INSTALLER.into()),
                                 extras: Some(extras),
+                                direct_url_json,
                                 ..Default::default()
                             },
                         )
-                        .into_diagnostic()
-                        .and_then(|unpacked_wheel| {
-                            if let Some(hash) = hash {
-                                std::fs::write(unpacked_wheel.dist_info.join("HASH"), hash)
-                                    .into_diagnostic()
-                            } else {
-                                Ok(())
-                            }
-                        })
+                        .into_diagnostic()?;
+                    if let Some((_, source_dir)) = editable {
+                        link_editable_sources(
+                            &root.join(install_paths.site_packages()),
+                            &unpacked_wheel.dist_info,
+                            &source_dir,
+                        )
+                        .wrap_err_with(|| {
+                            format!(
+                                "failed to install '{}' in editable mode",
+                                unpacked_wheel.metadata.name.as_source_str()
+                            )
+                        })?;
+                    }
+                    if let Some(hash) = hash {
+                        std::fs::write(unpacked_wheel.dist_info.join("HASH"), hash)
+                            .into_diagnostic()?;
+                    }
+                    Ok(())
                 })
                 .map_err(JoinError::try_into_panic)
                 .await;
@@ -241,10 +308,10 @@ fn stream_python_artifacts(
     marker_environment: Arc<MarkerEnvironment>,
     compatible_tags: Arc<WheelTags>,
     resolve_options: Arc<ResolveOptions>,
-    packages_to_download: Vec<&CombinedPypiPackageData>,
+    packages_to_download: Vec<(&CombinedPypiPackageData, Option<PathBuf>)>,
     env_variables: HashMap<String, String>,
 ) -> (
-    impl Stream<Item = miette::Result<(Option<String>, HashSet<Extra>, Wheel)>> + '_,
+    impl Stream<Item = miette::Result<ArtifactToInstall>> + '_,
     Option<ProgressBar>,
 ) {
     if packages_to_download.is_empty() {
@@ -268,7 +335,7 @@ fn stream_python_artifacts(
     let total_packages = packages_to_download.len();
 
     let download_stream = stream::iter(packages_to_download)
-        .map(move |(pkg_data, pkg_env_data)| {
+        .map(move |((pkg_data, pkg_env_data), editable_source_dir)| {
             let pb = stream_pb.clone();
             let message_formatter = message_formatter.clone();
             let marker_environment = marker_environment.clone();
@@ -298,7 +365,9 @@ fn stream_python_artifacts(
                     stree.version = pkg_data.version.clone();
                     (ArtifactName::STree(stree), true)
                 } else {
-                    (artifact_name, false)
+                    // Local archives cannot be downloaded, they are read directly from disk.
+                    let is_local = pkg_data.url.scheme() == "file";
+                    (artifact_name, is_local)
                 };
 
                 // Log out intent to install this python package.
@@ -366,6 +435,7 @@ fn stream_python_artifacts(
                         .filter_map(|e| Extra::from_str(e).ok())
                         .collect(),
                     wheel,
+                    editable_source_dir.map(|source_dir| (pkg_data.url.clone(), source_dir)),
                 ))
             }
         })
@@ -375,6 +445,165 @@ fn stream_python_artifacts(
     (download_stream, Some(pb))
 }
 
+/// Returns the source directory of a locked package if it should be installed in editable mode.
+fn editable_source_dir(
+    (pkg_data, _): &CombinedPypiPackageData,
+    editable_packages: &HashSet<NormalizedPackageName>,
+) -> Option<PathBuf> {
+    let name = NormalizedPackageName::from_str(&pkg_data.name).ok()?;
+    if !editable_packages.contains(&name) {
+        return None;
+    }
+    pkg_data
+        .url
+        .to_file_path()
+        .ok()
+        .filter(|path| path.is_dir())
+}
+
+/// Turns a wheel of a local source directory that was just unpacked into an editable install. The
+/// top-level packages and modules that the build backend put in the wheel are looked up in the
+/// source directory. Their installed files are removed again and a `.pth` file adds the
+/// directories they were found in to the python path instead, so changes to the sources are picked
+/// up without reinstalling. The metadata and entry points of the wheel stay installed.
+///
+/// Top-level packages that contain files that are not found in the source directory, like
+/// generated files or compiled extensions, are kept as they were installed from the wheel.
+fn link_editable_sources(
+    site_packages: &Path,
+    dist_info: &Path,
+    source_dir: &Path,
+) -> miette::Result<()> {
+    let record_path = dist_info.join("RECORD");
+    let record = Record::from_path(&record_path).into_diagnostic()?;
+    let dist_info_name = dist_info
+        .file_name()
+        .and_then(OsStr::to_str)
+        .expect("the dist-info directory has a name");
+
+    // Group the installed files by the top-level package or module they belong to. Scripts are
+    // recorded relative to the site-packages directory as well but are never part of a package.
+    let mut top_levels: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for entry in record.iter() {
+        let top_level = entry.path.split('/').next().unwrap_or_default();
+        let is_package_file = top_level != ".."
+            && !top_level.ends_with(".dist-info")
+            && !top_level.ends_with(".data")
+            && !top_level.ends_with(".pth");
+        if is_package_file && !entry.path.ends_with(".pyc") {
+            top_levels.entry(top_level).or_default().push(&entry.path);
+        }
+    }
+
+    let source_dirs = python_source_dirs(source_dir).into_diagnostic()?;
+    let mut python_path = Vec::new();
+    let mut linked_files = HashSet::new();
+    for (top_level, files) in top_levels {
+        let Some(root) = source_dirs.iter().find(|dir| {
+            files
+                .iter()
+                .all(|file| is_same_file_contents(&site_packages.join(file), &dir.join(file)))
+        }) else {
+            tracing::warn!(
+                "'{top_level}' is installed from the wheel instead of linked to '{}', because not all of its files were found there",
+                source_dir.display()
+            );
+            continue;
+        };
+        linked_files.extend(files);
+        if !python_path.contains(root) {
+            python_path.push(root.clone());
+        }
+    }
+
+    // Remove the linked files and the directories they leave empty behind.
+    for file in &linked_files {
+        let path = site_packages.join(file);
+        std::fs::remove_file(&path).into_diagnostic()?;
+        for dir in path.ancestors().skip(1) {
+            if dir == site_packages || std::fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+
+    let pth_name = format!(
+        "__editable__.{}.pth",
+        dist_info_name.trim_end_matches(".dist-info")
+    );
+    let pth = python_path
+        .iter()
+        .map(|dir| format!("{}\n", dir.display()))
+        .join("");
+    std::fs::write(site_packages.join(&pth_name), pth).into_diagnostic()?;
+
+    // The RECORD file is used to uninstall the package again.
+    let record = record
+        .iter()
+        .filter(|entry| !linked_files.contains(entry.path.as_str()))
+        .cloned()
+        .chain([RecordEntry {
+            path: pth_name,
+            hash: None,
+            size: None,
+        }])
+        .collect::<Record>();
+    record.write_to_path(&record_path).into_diagnostic()
+}
+
+/// Returns the directories of a source tree that could contain python packages, ordered from the
+/// shallowest to the deepest. Hidden directories and the output directories of build backends are
+/// skipped.
+fn python_source_dirs(source_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut dirs = vec![source_dir.to_path_buf()];
+    let mut idx = 0;
+    while let Some(dir) = dirs.get(idx) {
+        let mut children = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if entry.file_type()?.is_dir()
+                && !name.starts_with('.')
+                && !["build", "dist", "__pycache__"].contains(&name.as_ref())
+            {
+                children.push(entry.path());
+            }
+        }
+        children.sort();
+        dirs.extend(children);
+        idx += 1;
+    }
+    Ok(dirs)
+}
+
+/// Returns true if both files exist and have the same contents.
+fn is_same_file_contents(a: &Path, b: &Path) -> bool {
+    match (std::fs::read(a), std::fs::read(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Returns true if the distribution was installed in editable mode.
+fn is_editable_install(prefix_root: &Path, distribution: &Distribution) -> bool {
+    std::fs::read_to_string(
+        prefix_root
+            .join(&distribution.dist_info)
+            .join("direct_url.json"),
+    )
+    .ok()
+    .and_then(|contents| serde_json::from_str::<DirectUrlJson>(&contents).ok())
+    .map_or(false, |direct_url| {
+        matches!(
+            direct_url.source,
+            DirectUrlSource::Dir {
+                editable: Some(true)
+            }
+        )
+    })
+}
+
 /// If there was a previous version of python installed, remove any distribution installed in that
 /// environment.
 pub fn remove_old_python_distributions(
@@ -459,6 +688,7 @@ fn determine_python_distributions_to_remove_and_install<'p>(
     prefix: &Path,
     mut current_python_packages: Vec<Distribution>,
     desired_python_packages: Vec<&'p CombinedPypiPackageData>,
+    editable_packages: &HashSet<NormalizedPackageName>,
 ) -> (Vec<Distribution>, Vec<&'p CombinedPypiPackageData>) {
     // Determine the artifact tags associated with the locked dependencies.
     let mut desired_python_packages = extract_locked_tags(desired_python_packages);
@@ -482,6 +712,7 @@ fn determine_python_distributions_to_remove_and_install<'p>(
                         prefix,
                         current_python_packages,
                         (pkg, artifact_name.as_ref()),
+                        editable_packages,
                     )
                 })
         {
@@ -550,6 +781,7 @@ fn does_installed_match_locked_package(
     prefix_root: &Path,
     installed_python_package: &Distribution,
     locked_python_package: (&CombinedPypiPackageData, Option<&IndexSet<WheelTag>>),
+    editable_packages: &HashSet<NormalizedPackageName>,
 ) -> bool {
     let ((pkg_data, _), artifact_tags) = locked_python_package;
 
@@ -560,6 +792,13 @@ fn does_installed_match_locked_package(
         return false;
     }
 
+    // Reinstall the package if it should be switched from or to an editable install.
+    if editable_packages.contains(&installed_python_package.name)
+        != is_editable_install(prefix_root, installed_python_package)
+    {
+        return false;
+    }
+
     // If this distribution is installed with pixi we can assume that there is a URL file that
     // contains the original URL.
     if installed_python_package.installer.as_deref() == Some(PIXI_PYPI_INSTALLER) {
@@ -592,3 +831,61 @@ fn does_installed_match_locked_package(
         (Some(locked_tags), Some(installed_tags)) => locked_tags == installed_tags,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_editable_sources() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let site_packages = tempfile::tempdir().unwrap();
+        let write = |root: &Path, path: &str, contents: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+
+        // A namespace package in a `src` layout, a module in a directory configured with
+        // `package-dir` and a package with a file generated by the build backend.
+        write(source_dir.path(), "src/ns/pkg/__init__.py", "pkg");
+        write(source_dir.path(), "lib/tool.py", "tool");
+        write(source_dir.path(), "build/lib/tool.py", "tool");
+        write(source_dir.path(), "gen/__init__.py", "gen");
+        for (path, contents) in [
+            ("ns/pkg/__init__.py", "pkg"),
+            ("tool.py", "tool"),
+            ("gen/__init__.py", "gen"),
+            ("gen/_version.py", "version"),
+            ("../../bin/tool", "script"),
+            ("my_pkg-1.0.dist-info/METADATA", "metadata"),
+        ] {
+            write(site_packages.path(), path, contents);
+        }
+        let dist_info = site_packages.path().join("my_pkg-1.0.dist-info");
+        std::fs::write(
+            dist_info.join("RECORD"),
+            "ns/pkg/__init__.py,,\ntool.py,,\ngen/__init__.py,,\ngen/_version.py,,\n../../bin/tool,,\nmy_pkg-1.0.dist-info/METADATA,,\nmy_pkg-1.0.dist-info/RECORD,,\n",
+        )
+        .unwrap();
+
+        link_editable_sources(site_packages.path(), &dist_info, source_dir.path()).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(site_packages.path().join("__editable__.my_pkg-1.0.pth"))
+                .unwrap(),
+            format!(
+                "{}\n{}\n",
+                source_dir.path().join("src").display(),
+                source_dir.path().join("lib").display()
+            )
+        );
+        assert!(!site_packages.path().join("ns").exists());
+        assert!(!site_packages.path().join("tool.py").exists());
+        assert!(site_packages.path().join("gen/_version.py").exists());
+        assert_eq!(
+            std::fs::read_to_string(dist_info.join("RECORD")).unwrap(),
+            "../../bin/tool,,\n__editable__.my_pkg-1.0.pth,,\ngen/__init__.py,,\ngen/_version.py,,\nmy_pkg-1.0.dist-info/METADATA,,\nmy_pkg-1.0.dist-info/RECORD,,\n"
+        );
+    }
+}
@@ -17,6 +17,8 @@ pub mod unix;
 pub mod util;
 pub mod utils;
 
+mod pypi_find_links;
+mod pypi_git;
 mod pypi_marker_env;
 mod pypi_tags;
 
@@ -3,6 +3,7 @@
 mod outdated;
 mod package_identifier;
 pub(crate) mod pypi;
+mod pypi_metadata;
 mod pypi_name_mapping;
 mod records_by_name;
 mod resolve;
@@ -16,12 +17,13 @@ use rattler_lock::{LockFile, PypiPackageData, PypiPackageEnvironmentData};
 
 pub use outdated::OutdatedEnvironments;
 pub use package_identifier::PypiPackageIdentifier;
+pub use pypi_metadata::{LockedPackageIndexes, LockedPypiIndexes, PypiLockMetadata};
 pub use records_by_name::{PypiRecordsByName, RepoDataRecordsByName};
 pub use resolve::{resolve_conda, resolve_pypi};
 pub use satisfiability::{
     verify_environment_satisfiability, verify_platform_satisfiability, PlatformUnsat,
 };
-pub use update::{LockFileDerivedData, UpdateLockFileOptions};
+pub use update::{update_lock_file, LockFileDerivedData, UpdateLockFileOptions};
 
 /// A list of conda packages that are locked for a specific platform.
 pub type LockedCondaPackages = Vec<RepoDataRecord>;
@@ -35,13 +37,33 @@ pub type PypiRecord = (PypiPackageData, PypiPackageEnvironmentData);
 
 /// Loads the lockfile for the specified project or returns a dummy one if none could be found.
 pub async fn load_lock_file(project: &Project) -> miette::Result<LockFile> {
+    load_lock_file_with_metadata(project)
+        .await
+        .map(|(lock_file, _)| lock_file)
+}
+
+/// Loads the lockfile for the specified project together with the pypi metadata that pixi stored
+/// in it. Returns a dummy lock-file if none could be found.
+pub(crate) async fn load_lock_file_with_metadata(
+    project: &Project,
+) -> miette::Result<(LockFile, PypiLockMetadata)> {
     let lock_file_path = project.lock_file_path();
     if lock_file_path.is_file() {
         // Spawn a background task because loading the file might be IO bound.
-        tokio::task::spawn_blocking(move || LockFile::from_path(&lock_file_path).into_diagnostic())
+        tokio::task::spawn_blocking(move || pypi_metadata::read_from_path(&lock_file_path))
             .await
             .unwrap_or_else(|e| Err(e).into_diagnostic())
     } else {
-        Ok(LockFile::default())
+        Ok(Default::default())
     }
 }
+
+/// Writes the lock-file of the `project` to disk, together with the pypi metadata that the
+/// lock-file format cannot store.
+pub(crate) fn write_lock_file(
+    project: &Project,
+    lock_file: &LockFile,
+    pypi_metadata: &PypiLockMetadata,
+) -> miette::Result<()> {
+    pypi_metadata::write_to_path(&project.lock_file_path(), lock_file, pypi_metadata)
+}
@@ -1,4 +1,7 @@
-use super::{verify_environment_satisfiability, verify_platform_satisfiability, PlatformUnsat};
+use super::{
+    verify_environment_satisfiability, verify_platform_satisfiability, PlatformUnsat,
+    PypiLockMetadata,
+};
 use crate::{consts, project::Environment, project::SolveGroup, Project};
 use itertools::Itertools;
 use rattler_conda_types::Platform;
@@ -19,13 +22,23 @@ pub struct OutdatedEnvironments<'p> {
 
 impl<'p> OutdatedEnvironments<'p> {
     /// Constructs a new instance of this struct by examining the project and lock-file and finding
-    /// any mismatches.
-    pub fn from_project_and_lock_file(project: &'p Project, lock_file: &LockFile) -> Self {
+    /// any mismatches. The `pypi_metadata` is the metadata that pixi stored in the lock-file.
+    pub fn from_project_and_lock_file(
+        project: &'p Project,
+        lock_file: &LockFile,
+        pypi_metadata: &PypiLockMetadata,
+    ) -> Self {
         let mut outdated_conda: HashMap<_, HashSet<_>> = HashMap::new();
         let mut outdated_pypi: HashMap<_, HashSet<_>> = HashMap::new();
 
         // Find all targets that are not satisfied by the lock-file
-        find_unsatisfiable_targets(project, lock_file, &mut outdated_conda, &mut outdated_pypi);
+        find_unsatisfiable_targets(
+            project,
+            lock_file,
+            pypi_metadata,
+            &mut outdated_conda,
+            &mut outdated_pypi,
+        );
 
         // Extend the outdated targets to include the solve groups
         let (mut conda_solve_groups_out_of_date, mut pypi_solve_groups_out_of_date) =
@@ -73,6 +86,31 @@ impl<'p> OutdatedEnvironments<'p> {
         }
     }
 
+    /// Constructs a new instance of this struct that marks the given `targets` as out of date.
+    ///
+    /// Both the conda and pypi packages of the targets are considered out of date. Because the
+    /// environments of a solve-group are solved together, the targets are extended to include all
+    /// other environments in the same solve-groups.
+    pub fn from_targets(targets: HashMap<Environment<'p>, HashSet<Platform>>) -> Self {
+        let (solve_groups_out_of_date, _) =
+            map_outdated_targets_to_solve_groups(&targets, &HashMap::new());
+
+        let mut outdated_conda = targets;
+        for (solve_group, platforms) in solve_groups_out_of_date {
+            for env in solve_group.environments() {
+                outdated_conda
+                    .entry(env.clone())
+                    .or_default()
+                    .extend(platforms.iter().copied());
+            }
+        }
+
+        Self {
+            pypi: outdated_conda.clone(),
+            conda: outdated_conda,
+        }
+    }
+
     /// Returns true if the lock-file is up-to-date with the project (e.g. there are no
     /// outdated targets).
     pub fn is_empty(&self) -> bool {
@@ -85,6 +123,7 @@ impl<'p> OutdatedEnvironments<'p> {
 fn find_unsatisfiable_targets<'p>(
     project: &'p Project,
     lock_file: &LockFile,
+    pypi_metadata: &PypiLockMetadata,
     outdated_conda: &mut HashMap<Environment<'p>, HashSet<Platform>>,
     outdated_pypi: &mut HashMap<Environment<'p>, HashSet<Platform>>,
 ) {
@@ -107,7 +146,12 @@ fn find_unsatisfiable_targets<'p>(
         };
 
         // The locked environment exists, but does it match our project environment?
-        if let Err(unsat) = verify_environment_satisfiability(&environment, &locked_environment) {
+        let locked_pypi_indexes = pypi_metadata.indexes(environment.name().as_str());
+        if let Err(unsat) = verify_environment_satisfiability(
+            &environment,
+            &locked_environment,
+            locked_pypi_indexes,
+        ) {
             tracing::info!(
                 "environment '{0}' is out of date because {unsat}",
                 environment.name().fancy_display()
@@ -123,7 +167,14 @@ fn find_unsatisfiable_targets<'p>(
 
         // Verify each individual platform
         for platform in platforms {
-            match verify_platform_satisfiability(&environment, &locked_environment, platform) {
+            let locked_package_indexes =
+                pypi_metadata.package_indexes(environment.name().as_str(), platform);
+            match verify_platform_satisfiability(
+                &environment,
+                &locked_environment,
+                locked_package_indexes,
+                platform,
+            ) {
                 Ok(_) => {}
                 Err(unsat @ PlatformUnsat::UnsatisfiableRequirement(_, _)) => {
                     tracing::info!(
@@ -1,12 +1,14 @@
 use crate::consts::PROJECT_MANIFEST;
 use crate::lock_file::{package_identifier, pypi_name_mapping};
 use crate::project::manifest::{PyPiRequirement, SystemRequirements};
+use crate::pypi_git::{self, GitCheckout, GitSource};
 use crate::pypi_marker_env::determine_marker_environment;
 use crate::pypi_tags::{is_python_record, project_platform_tags};
 use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::{Context, IntoDiagnostic};
 use rattler_conda_types::{Platform, RepoDataRecord};
+use rattler_digest::{compute_bytes_digest, Sha256, Sha256Hash};
 use rip::index::PackageDb;
 use rip::python_env::PythonLocation;
 use rip::resolve::solve_options::{ResolveOptions, SDistResolution};
@@ -15,6 +17,19 @@ use rip::types::PackageName;
 use std::path::Path;
 use std::sync::Arc;
 use std::{collections::HashMap, vec};
+use url::Url;
+
+/// The git repositories of the `git` dependencies of an environment, checked out at the commit
+/// their reference resolved to.
+pub type GitCheckouts = HashMap<PackageName, (GitSource, GitCheckout)>;
+
+/// The scheme of the urls that are used to store paths relative to the project root in the
+/// lock-file, e.g. `path:libs/mylib`.
+const PROJECT_RELATIVE_SCHEME: &str = "path";
+
+/// The files that determine how a source tree is built. If any of these change the metadata of the
+/// package might have changed as well.
+const SOURCE_TREE_BUILD_FILES: [&str; 3] = ["pyproject.toml", "setup.py", "setup.cfg"];
 
 /// Resolve python packages for the specified project.
 // TODO(nichita): extract in strunct passed args
@@ -28,6 +43,8 @@ pub async fn resolve_dependencies<'db>(
     python_location: Option<&Path>,
     sdist_resolution: SDistResolution,
     env_variables: HashMap<String, String>,
+    project_root: &Path,
+    git_checkouts: &GitCheckouts,
 ) -> miette::Result<Vec<PinnedPackage>> {
     if dependencies.is_empty() {
         return Ok(vec![]);
@@ -74,7 +91,15 @@ pub async fn resolve_dependencies<'db>(
     let requirements = dependencies
         .iter()
         .flat_map(|(name, req)| req.iter().map(move |req| (name, req)))
-        .map(|(name, req)| req.as_pep508(name))
+        .map(|(name, req)| {
+            let mut requirement = req.as_pep508(name, project_root);
+            // Git dependencies are resolved from the source tree of their checkout.
+            if let Some((_, checkout)) = git_checkouts.get(name) {
+                requirement.version_or_url =
+                    Some(pep508_rs::VersionOrUrl::Url(checkout.source_url()));
+            }
+            requirement
+        })
         .collect::<Vec<pep508_rs::Requirement>>();
 
     // If we only have a system python
@@ -113,6 +138,30 @@ pub async fn resolve_dependencies<'db>(
     Ok(result)
 }
 
+/// Checks out the git repositories of all `git` dependencies at the commit their reference
+/// currently points to.
+pub async fn checkout_git_dependencies(
+    dependencies: &IndexMap<PackageName, Vec<PyPiRequirement>>,
+) -> miette::Result<GitCheckouts> {
+    let git_sources = dependencies
+        .iter()
+        .flat_map(|(name, reqs)| reqs.iter().map(move |req| (name, req)))
+        .filter_map(|(name, req)| Some((name.clone(), req.git_source()?)))
+        .collect_vec();
+
+    let mut git_checkouts = GitCheckouts::new();
+    for (name, source) in git_sources {
+        let checkout = pypi_git::checkout(source.clone()).await.wrap_err_with(|| {
+            format!(
+                "failed to checkout the git repository of '{}'",
+                name.as_str()
+            )
+        })?;
+        git_checkouts.insert(name, (source, checkout));
+    }
+    Ok(git_checkouts)
+}
+
 /// Amend the records with pypi purls if they are not present yet.
 pub async fn amend_pypi_purls(conda_packages: &mut [RepoDataRecord]) -> miette::Result<()> {
     let conda_forge_mapping = pypi_name_mapping::conda_pypi_name_mapping().await?;
@@ -121,3 +170,109 @@ pub async fn amend_pypi_purls(conda_packages: &mut [RepoDataRecord]) -> miette::
     }
     Ok(())
 }
+
+/// Converts a `file://` url that points inside the project to a url that stores the path relative
+/// to the project root. This keeps the lock-file independent of the location of the project. Other
+/// urls are returned unchanged.
+pub(crate) fn to_project_relative_url(url: &Url, project_root: &Path) -> Url {
+    let relative_path = url
+        .to_file_path()
+        .ok()
+        .and_then(|path| path.strip_prefix(project_root).ok().map(Path::to_path_buf));
+    match relative_path {
+        Some(relative_path) => {
+            let relative_path = relative_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .join("/");
+            let relative_path = if relative_path.is_empty() {
+                "."
+            } else {
+                relative_path.as_str()
+            };
+            Url::parse(&format!("{PROJECT_RELATIVE_SCHEME}:{relative_path}"))
+                .expect("a relative path should always form a valid url")
+        }
+        None => url.clone(),
+    }
+}
+
+/// The inverse of [`to_project_relative_url`], converts a url with a path relative to the project
+/// root back into an absolute `file://` url. Other urls are returned unchanged.
+pub(crate) fn from_project_relative_url(url: &Url, project_root: &Path) -> Url {
+    if url.scheme() != PROJECT_RELATIVE_SCHEME {
+        return url.clone();
+    }
+    let path = project_root.join(url.path());
+    let path = dunce::canonicalize(&path).unwrap_or(path);
+    Url::from_file_path(path).unwrap_or_else(|_| url.clone())
+}
+
+/// Computes a hash of the files that determine how the source tree at `path` is built. This is
+/// used to detect when the metadata of a local package might have changed. Returns `None` if `path`
+/// is not a directory.
+pub(crate) fn source_tree_hash(path: &Path) -> Option<Sha256Hash> {
+    if !path.is_dir() {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    for file_name in SOURCE_TREE_BUILD_FILES {
+        if let Ok(contents) = std::fs::read(path.join(file_name)) {
+            bytes.extend_from_slice(file_name.as_bytes());
+            bytes.extend(contents);
+        }
+    }
+    Some(compute_bytes_digest::<Sha256>(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_relative_url() {
+        let project_root = std::env::temp_dir().join("pixi-project");
+        let url = Url::from_file_path(project_root.join("libs").join("mylib")).unwrap();
+
+        let relative_url = to_project_relative_url(&url, &project_root);
+        assert_eq!(relative_url.as_str(), "path:libs/mylib");
+        assert_eq!(from_project_relative_url(&relative_url, &project_root), url);
+
+        let root_url = Url::from_file_path(&project_root).unwrap();
+        assert_eq!(
+            to_project_relative_url(&root_url, &project_root).as_str(),
+            "path:."
+        );
+
+        let other_url = Url::parse("https://example.com/foo-1.0.tar.gz").unwrap();
+        assert_eq!(
+            to_project_relative_url(&other_url, &project_root),
+            other_url
+        );
+        assert_eq!(
+            from_project_relative_url(&other_url, &project_root),
+            other_url
+        );
+    }
+
+    #[test]
+    fn test_source_tree_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"foo\"",
+        )
+        .unwrap();
+        let hash = source_tree_hash(dir.path()).unwrap();
+        assert_eq!(source_tree_hash(dir.path()), Some(hash));
+
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"bar\"",
+        )
+        .unwrap();
+        assert_ne!(source_tree_hash(dir.path()), Some(hash));
+
+        assert_eq!(source_tree_hash(&dir.path().join("pyproject.toml")), None);
+    }
+}
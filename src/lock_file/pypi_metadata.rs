@@ -0,0 +1,317 @@
+//! The lock-file format has no fields for some of the information that pixi needs to verify the
+//! pypi packages in the lock-file. This module stores that information in a separate file next to
+//! the lock-file, see [`consts::PYPI_LOCK_METADATA_FILE`]:
+//!
+//! - `pypi_indexes` of an environment: the pypi indexes the environment was resolved with.
+//! - `package_indexes` of an environment: the indexes that the packages pinned to an index were
+//!   resolved from, by platform. These packages are only looked up on their index.
+//!
+//! The lock-file itself is read and written by [`rattler_lock`] only, so it can still be read by
+//! other tools and older versions of pixi. Those don't update the metadata file when they write
+//! the lock-file. An environment whose metadata doesn't match the manifest is resolved again.
+
+use crate::{
+    consts,
+    project::{
+        manifest::{FindLinksUrlOrPath, PypiOptions},
+        pypi_index_overrides, Environment,
+    },
+};
+use miette::{Context, IntoDiagnostic};
+use rattler_conda_types::Platform;
+use rattler_lock::LockFile;
+use rip::types::NormalizedPackageName;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use url::Url;
+
+/// The version of the format of the metadata file.
+const METADATA_VERSION: u64 = 1;
+
+/// The pypi indexes an environment was resolved with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPypiIndexes {
+    /// The primary index.
+    pub index_url: Url,
+
+    /// The extra indexes in the order they are searched.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_index_urls: Vec<Url>,
+
+    /// The flat indexes in the order they are searched, after the other indexes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub find_links: Vec<FindLinksUrlOrPath>,
+}
+
+impl LockedPypiIndexes {
+    /// Returns the indexes as they are stored in the metadata for the given `options`, or `None`
+    /// if no options are specified and the default index is used.
+    pub fn from_options(options: &PypiOptions) -> Option<Self> {
+        if options.is_empty() {
+            return None;
+        }
+        Some(Self {
+            index_url: options.index_url(),
+            extra_index_urls: options.extra_index_urls().collect(),
+            find_links: options.find_links().cloned().collect(),
+        })
+    }
+}
+
+/// The metadata of a single environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct EnvironmentMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pypi_indexes: Option<LockedPypiIndexes>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    package_indexes: BTreeMap<Platform, LockedPackageIndexes>,
+}
+
+impl EnvironmentMetadata {
+    fn is_empty(&self) -> bool {
+        self.pypi_indexes.is_none() && self.package_indexes.is_empty()
+    }
+}
+
+/// The indexes that the pypi packages pinned to an index were resolved from, by the name of the
+/// package.
+pub type LockedPackageIndexes = BTreeMap<NormalizedPackageName, Url>;
+
+/// The information about pypi packages that pixi stores next to the lock-file because the
+/// lock-file format has no fields for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PypiLockMetadata {
+    version: u64,
+
+    /// The metadata of the environments, by the name of the environment.
+    #[serde(default)]
+    environments: BTreeMap<String, EnvironmentMetadata>,
+}
+
+impl Default for PypiLockMetadata {
+    fn default() -> Self {
+        Self {
+            version: METADATA_VERSION,
+            environments: BTreeMap::new(),
+        }
+    }
+}
+
+impl PypiLockMetadata {
+    /// Records the pypi indexes that the pypi packages of the `environment` were just resolved
+    /// with for the given `platforms`. The indexes of environments and platforms that were not
+    /// resolved again are kept as they are, because their locked packages still come from the
+    /// indexes they were resolved with.
+    pub fn set_resolved(
+        &mut self,
+        environment: &Environment<'_>,
+        platforms: impl IntoIterator<Item = Platform>,
+    ) {
+        let name = environment.name().as_str().to_string();
+        let metadata = self.environments.entry(name.clone()).or_default();
+        metadata.pypi_indexes = LockedPypiIndexes::from_options(&environment.pypi_options());
+        for platform in platforms {
+            // Conflicting indexes fail the resolution, so there is nothing to record for them.
+            let package_indexes =
+                pypi_index_overrides(&environment.pypi_dependencies(Some(platform)))
+                    .unwrap_or_default();
+            if package_indexes.is_empty() {
+                metadata.package_indexes.remove(&platform);
+            } else {
+                metadata.package_indexes.insert(platform, package_indexes);
+            }
+        }
+        if metadata.is_empty() {
+            self.environments.remove(&name);
+        }
+    }
+
+    /// Returns the pypi indexes the environment with the given name was resolved with, or `None`
+    /// if the default index was used.
+    pub fn indexes(&self, environment: &str) -> Option<&LockedPypiIndexes> {
+        self.environments.get(environment)?.pypi_indexes.as_ref()
+    }
+
+    /// Returns the indexes that the pinned pypi packages of the environment with the given name
+    /// were resolved from for the `platform`.
+    pub fn package_indexes(
+        &self,
+        environment: &str,
+        platform: Platform,
+    ) -> Option<&LockedPackageIndexes> {
+        self.environments
+            .get(environment)?
+            .package_indexes
+            .get(&platform)
+    }
+
+    /// Returns the metadata with only the environments of the `lock_file`.
+    fn retain_environments(&self, lock_file: &LockFile) -> Self {
+        Self {
+            version: METADATA_VERSION,
+            environments: self
+                .environments
+                .iter()
+                .filter(|(name, metadata)| {
+                    lock_file.environment(name).is_some() && !metadata.is_empty()
+                })
+                .map(|(name, metadata)| (name.clone(), metadata.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Reads the lock-file at `path` together with the metadata that pixi stored next to it. Without a
+/// metadata file the metadata is empty.
+pub(crate) fn read_from_path(path: &Path) -> miette::Result<(LockFile, PypiLockMetadata)> {
+    let lock_file = LockFile::from_path(path).into_diagnostic()?;
+    let metadata_path = metadata_path(path);
+    let metadata = match std::fs::read_to_string(&metadata_path) {
+        Ok(contents) => {
+            let metadata: PypiLockMetadata = serde_yaml::from_str(&contents)
+                .into_diagnostic()
+                .with_context(|| format!("failed to parse '{}'", metadata_path.display()))?;
+            // Metadata of a newer format cannot be interpreted, the environments are resolved
+            // again instead.
+            if metadata.version == METADATA_VERSION {
+                metadata
+            } else {
+                PypiLockMetadata::default()
+            }
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => PypiLockMetadata::default(),
+        Err(err) => return Err(err).into_diagnostic(),
+    };
+    Ok((lock_file, metadata))
+}
+
+/// Writes the `lock_file` to `path` and the `metadata` next to it. If there is no metadata for any
+/// of the environments in the lock-file, the metadata file is removed.
+pub(crate) fn write_to_path(
+    path: &Path,
+    lock_file: &LockFile,
+    metadata: &PypiLockMetadata,
+) -> miette::Result<()> {
+    lock_file.to_path(path).into_diagnostic()?;
+
+    let metadata_path = metadata_path(path);
+    let metadata = metadata.retain_environments(lock_file);
+    if metadata.environments.is_empty() {
+        return match std::fs::remove_file(&metadata_path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err).into_diagnostic(),
+            _ => Ok(()),
+        };
+    }
+    let contents = serde_yaml::to_string(&metadata).into_diagnostic()?;
+    std::fs::write(metadata_path, contents).into_diagnostic()
+}
+
+/// Returns the path of the metadata file of the lock-file at `lock_file_path`.
+fn metadata_path(lock_file_path: &Path) -> std::path::PathBuf {
+    lock_file_path.with_file_name(consts::PYPI_LOCK_METADATA_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_roundtrip_pypi_indexes() {
+        let lock_file = LockFile::builder()
+            .with_channels("default", ["https://conda.anaconda.org/conda-forge/"])
+            .finish();
+        let indexes = LockedPypiIndexes {
+            index_url: Url::parse("https://example.com/simple/").unwrap(),
+            extra_index_urls: vec![Url::parse("https://extra.com/simple/").unwrap()],
+            find_links: vec![
+                FindLinksUrlOrPath::Path(PathBuf::from("./links")),
+                FindLinksUrlOrPath::Url(Url::parse("https://example.com/links.html").unwrap()),
+            ],
+        };
+        let package_indexes = LockedPackageIndexes::from([(
+            "torch".parse().unwrap(),
+            Url::parse("https://download.pytorch.org/whl/cu121/").unwrap(),
+        )]);
+        let metadata = PypiLockMetadata {
+            version: METADATA_VERSION,
+            environments: BTreeMap::from([
+                (
+                    "default".to_string(),
+                    EnvironmentMetadata {
+                        pypi_indexes: Some(indexes.clone()),
+                        package_indexes: BTreeMap::from([(
+                            Platform::Linux64,
+                            package_indexes.clone(),
+                        )]),
+                    },
+                ),
+                // Environments that are not in the lock-file are not written.
+                (
+                    "removed".to_string(),
+                    EnvironmentMetadata {
+                        pypi_indexes: Some(indexes.clone()),
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(consts::PROJECT_LOCK_FILE);
+        let expected_path = dir.path().join("expected.lock");
+        write_to_path(&path, &lock_file, &metadata).unwrap();
+        lock_file.to_path(&expected_path).unwrap();
+
+        // The lock-file itself is written by rattler_lock.
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::fs::read_to_string(&expected_path).unwrap()
+        );
+        insta::assert_snapshot!(
+            std::fs::read_to_string(dir.path().join(consts::PYPI_LOCK_METADATA_FILE)).unwrap(),
+            @r###"
+        version: 1
+        environments:
+          default:
+            pypi_indexes:
+              index_url: https://example.com/simple/
+              extra_index_urls:
+              - https://extra.com/simple/
+              find_links:
+              - path: ./links
+              - url: https://example.com/links.html
+            package_indexes:
+              linux-64:
+                torch: https://download.pytorch.org/whl/cu121/
+        "###
+        );
+
+        let (_, read_metadata) = read_from_path(&path).unwrap();
+        assert_eq!(read_metadata.indexes("default"), Some(&indexes));
+        assert_eq!(
+            read_metadata.package_indexes("default", Platform::Linux64),
+            Some(&package_indexes)
+        );
+        assert_eq!(read_metadata.indexes("removed"), None);
+    }
+
+    #[test]
+    fn test_write_without_pypi_indexes() {
+        let lock_file = LockFile::builder()
+            .with_channels("default", ["https://conda.anaconda.org/conda-forge/"])
+            .finish();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(consts::PROJECT_LOCK_FILE);
+        let metadata_path = dir.path().join(consts::PYPI_LOCK_METADATA_FILE);
+        std::fs::write(&metadata_path, "version: 1\n").unwrap();
+        write_to_path(&path, &lock_file, &PypiLockMetadata::default()).unwrap();
+
+        // Without any metadata the metadata file is removed.
+        assert!(!metadata_path.exists());
+        let (_, read_metadata) = read_from_path(&path).unwrap();
+        assert_eq!(read_metadata, PypiLockMetadata::default());
+    }
+}
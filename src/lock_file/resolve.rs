@@ -5,11 +5,13 @@
 use crate::{
     lock_file::{pypi, LockedCondaPackages, LockedPypiPackages, PypiRecord},
     project::manifest::{PyPiRequirement, SystemRequirements},
+    pypi_find_links,
 };
 use indexmap::IndexMap;
 use indicatif::ProgressBar;
 use miette::IntoDiagnostic;
 use rattler_conda_types::{GenericVirtualPackage, MatchSpec, Platform, RepoDataRecord};
+use rattler_digest::{compute_bytes_digest, Sha256};
 use rattler_lock::{PackageHashes, PypiPackageData, PypiPackageEnvironmentData};
 use rattler_solve::{resolvo, SolverImpl};
 use rip::{index::PackageDb, resolve::solve_options::SDistResolution};
@@ -29,7 +31,12 @@ pub async fn resolve_pypi(
     python_location: Option<&Path>,
     sdist_resolution: SDistResolution,
     env_variables: HashMap<String, String>,
+    project_root: &Path,
 ) -> miette::Result<LockedPypiPackages> {
+    // Checkout the git dependencies, their source trees are resolved like local packages.
+    pb.set_message("fetching git dependencies");
+    let git_checkouts = pypi::checkout_git_dependencies(&dependencies).await?;
+
     // Solve python packages
     pb.set_message("resolving pypi dependencies");
     let python_artifacts = pypi::resolve_dependencies(
@@ -41,6 +48,8 @@ pub async fn resolve_pypi(
         python_location,
         sdist_resolution,
         env_variables,
+        project_root,
+        &git_checkouts,
     )
     .await?;
 
@@ -58,16 +67,36 @@ pub async fn resolve_pypi(
             .expect("failed to get metadata for a package for which we have already fetched metadata during solving.")
             .expect("no metadata for a package for which we have already fetched metadata during solving.");
 
+        // The distributions of local flat indexes are locked by their path, like other local
+        // distributions.
+        let artifact_url =
+            pypi_find_links::local_file_url(&artifact.url).unwrap_or_else(|| artifact.url.clone());
+        let artifact_path = artifact_url.to_file_path().ok();
+        let git_checkout = git_checkouts
+            .values()
+            .find(|(_, checkout)| Some(&checkout.path) == artifact_path.as_ref());
+        let (url, sha256) = if let Some((source, checkout)) = git_checkout {
+            // Git packages are locked to the commit that was checked out. Like rip does, the hash
+            // of the url is used as the hash of the package.
+            let url = source.locked_url(&checkout.commit);
+            let sha256 = compute_bytes_digest::<Sha256>(url.as_str());
+            (url, Some(sha256))
+        } else {
+            // Local source trees are locked by the hash of their build files, this way we can
+            // detect when they need to be rebuilt.
+            let source_tree_hash = artifact_path.and_then(|path| pypi::source_tree_hash(&path));
+            (
+                pypi::to_project_relative_url(&artifact_url, project_root),
+                source_tree_hash.or_else(|| artifact.hashes.as_ref().and_then(|hash| hash.sha256)),
+            )
+        };
         let pkg_data = PypiPackageData {
             name: python_artifact.name.to_string(),
             version: python_artifact.version,
             requires_dist: metadata.requires_dist,
             requires_python: metadata.requires_python,
-            url: artifact.url.clone(),
-            hash: artifact
-                .hashes
-                .as_ref()
-                .and_then(|hash| PackageHashes::from_hashes(None, hash.sha256)),
+            url,
+            hash: sha256.and_then(|sha256| PackageHashes::from_hashes(None, Some(sha256))),
         };
 
         let pkg_env = PypiPackageEnvironmentData {
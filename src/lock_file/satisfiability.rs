@@ -1,25 +1,38 @@
-use super::package_identifier;
+use super::{
+    package_identifier,
+    pypi::{source_tree_hash, to_project_relative_url},
+    LockedPackageIndexes, LockedPypiIndexes,
+};
+use crate::project::manifest::PyPiRequirement;
+use crate::pypi_git::GitSource;
 use crate::{
-    project::Environment, pypi_marker_env::determine_marker_environment,
+    project::{pypi_index_overrides, Environment},
+    pypi_marker_env::determine_marker_environment,
     pypi_tags::is_python_record,
 };
+use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::Diagnostic;
 use pep440_rs::VersionSpecifiers;
 use pep508_rs::Requirement;
 use rattler_conda_types::{MatchSpec, ParseMatchSpecError, Platform};
 use rattler_lock::{CondaPackage, Package, PypiPackage};
-use rip::types::NormalizedPackageName;
+use rip::types::{NormalizedPackageName, PackageName};
 use std::{
     collections::{HashMap, HashSet},
+    path::Path,
     str::FromStr,
 };
 use thiserror::Error;
+use url::Url;
 
 #[derive(Debug, Error, Diagnostic)]
 pub enum EnvironmentUnsat {
     #[error("the channels in the lock-file do not match the environments channels")]
     ChannelsMismatch,
+
+    #[error("the pypi indexes in the lock-file do not match the environments pypi options")]
+    PypiOptionsMismatch,
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -52,6 +65,18 @@ pub enum PlatformUnsat {
 
     #[error("{0} requires python version {1} but the python interpreter in the lock-file has version {2}")]
     PythonVersionMismatch(String, VersionSpecifiers, Box<pep440_rs::Version>),
+
+    #[error("the locked source of '{0}' does not match the path in the manifest")]
+    SourcePathMismatch(String),
+
+    #[error("the build files of the local package '{0}' have changed")]
+    SourceTreeChanged(String),
+
+    #[error("the locked source of '{0}' does not match the git repository or url in the manifest")]
+    SourceUrlMismatch(String),
+
+    #[error("the locked source of '{0}' was not resolved from the index in the manifest")]
+    IndexMismatch(String),
 }
 
 /// Verifies that all the requirements of the specified `environment` can be satisfied with the
@@ -63,6 +88,7 @@ pub enum PlatformUnsat {
 pub fn verify_environment_satisfiability(
     environment: &Environment<'_>,
     locked_environment: &rattler_lock::Environment,
+    locked_pypi_indexes: Option<&LockedPypiIndexes>,
 ) -> Result<(), EnvironmentUnsat> {
     // Check if the channels in the lock file match our current configuration. Note that the order
     // matters here. If channels are added in a different order, the solver might return a different
@@ -76,6 +102,12 @@ pub fn verify_environment_satisfiability(
         return Err(EnvironmentUnsat::ChannelsMismatch);
     }
 
+    // Check if the pypi indexes in the lock file match our current configuration.
+    let pypi_indexes = LockedPypiIndexes::from_options(&environment.pypi_options());
+    if locked_pypi_indexes != pypi_indexes.as_ref() {
+        return Err(EnvironmentUnsat::PypiOptionsMismatch);
+    }
+
     Ok(())
 }
 
@@ -92,6 +124,7 @@ pub fn verify_environment_satisfiability(
 pub fn verify_platform_satisfiability(
     environment: &Environment<'_>,
     locked_environment: &rattler_lock::Environment,
+    locked_package_indexes: Option<&LockedPackageIndexes>,
     platform: Platform,
 ) -> Result<(), PlatformUnsat> {
     // Get all the conda packages from the locked environment
@@ -114,7 +147,13 @@ pub fn verify_platform_satisfiability(
         .collect_vec();
 
     // Check the satisfiability of the pypi packages.
-    verify_pypi_platform_satisfiability(environment, &conda_packages, &pypi_packages, platform)?;
+    verify_pypi_platform_satisfiability(
+        environment,
+        &conda_packages,
+        &pypi_packages,
+        locked_package_indexes,
+        platform,
+    )?;
 
     Ok(())
 }
@@ -242,14 +281,26 @@ pub fn verify_pypi_platform_satisfiability(
     environment: &Environment<'_>,
     locked_conda_packages: &[CondaPackage],
     locked_pypi_environment: &[PypiPackage],
+    locked_package_indexes: Option<&LockedPackageIndexes>,
     platform: Platform,
 ) -> Result<(), PlatformUnsat> {
-    let mut requirements = environment
-        .pypi_dependencies(Some(platform))
+    let project_root = environment.project().root();
+    let dependencies = environment.pypi_dependencies(Some(platform));
+
+    // Packages that are pinned to an index are only resolved from that index, verify that they
+    // are pinned to the same indexes as when they were resolved.
+    verify_pypi_package_indexes(&dependencies, locked_package_indexes)?;
+
+    // Local packages are locked by their path and the hash of their build files, verify that they
+    // still match the manifest and the files on disk. Also verify that git and url packages still
+    // refer to the same source.
+    verify_pypi_source_dependencies(&dependencies, locked_pypi_environment, project_root)?;
+
+    let mut requirements = dependencies
         .iter()
         .flat_map(|(name, reqs)| {
             reqs.iter()
-                .map(move |req| (req.as_pep508(name), "<environment>"))
+                .map(move |req| (req.as_pep508(name, project_root), "<environment>"))
         })
         .collect_vec();
 
@@ -376,6 +427,74 @@ pub fn verify_pypi_platform_satisfiability(
     Ok(())
 }
 
+/// Verifies that the locked packages of path, git and url dependencies refer to the same source as
+/// the manifest and that the build files of local packages did not change since they were locked.
+fn verify_pypi_source_dependencies(
+    dependencies: &IndexMap<PackageName, Vec<PyPiRequirement>>,
+    locked_pypi_environment: &[PypiPackage],
+    project_root: &Path,
+) -> Result<(), PlatformUnsat> {
+    for (name, requirements) in dependencies {
+        let normalized_name = NormalizedPackageName::from(name.clone());
+        let Some(locked_package) = locked_pypi_environment.iter().find(|pkg| {
+            NormalizedPackageName::from_str(&pkg.data().package.name).ok()
+                == Some(normalized_name.clone())
+        }) else {
+            // Missing packages are reported when the requirements are verified.
+            continue;
+        };
+        let pkg_data = locked_package.data().package;
+
+        for requirement in requirements {
+            if let Some(path) = requirement.absolute_path(project_root) {
+                let Ok(url) = Url::from_file_path(&path) else {
+                    return Err(PlatformUnsat::SourcePathMismatch(pkg_data.name.clone()));
+                };
+                if pkg_data.url != to_project_relative_url(&url, project_root) {
+                    return Err(PlatformUnsat::SourcePathMismatch(pkg_data.name.clone()));
+                }
+
+                if let Some(hash) = source_tree_hash(&path) {
+                    if pkg_data.hash.as_ref().and_then(|h| h.sha256()) != Some(&hash) {
+                        return Err(PlatformUnsat::SourceTreeChanged(pkg_data.name.clone()));
+                    }
+                }
+            } else if let Some(source) = requirement.git_source() {
+                // The locked commit can only be verified by accessing the repository, so we only
+                // check that the repository and the reference did not change.
+                let locked_source = GitSource::from_locked_url(&pkg_data.url);
+                if locked_source.map(|(locked_source, _)| locked_source) != Some(source) {
+                    return Err(PlatformUnsat::SourceUrlMismatch(pkg_data.name.clone()));
+                }
+            } else if let Some(url) = &requirement.url {
+                if pkg_data.url != to_project_relative_url(url, project_root) {
+                    return Err(PlatformUnsat::SourceUrlMismatch(pkg_data.name.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies that the packages that are pinned to an index in the manifest were resolved from that
+/// index, and that the packages that were resolved from a pinned index are still pinned to it.
+fn verify_pypi_package_indexes(
+    dependencies: &IndexMap<PackageName, Vec<PyPiRequirement>>,
+    locked_package_indexes: Option<&LockedPackageIndexes>,
+) -> Result<(), PlatformUnsat> {
+    let package_indexes = pypi_index_overrides(dependencies).unwrap_or_default();
+    let locked_package_indexes = locked_package_indexes.cloned().unwrap_or_default();
+    let names = package_indexes.keys().chain(locked_package_indexes.keys());
+    for name in names {
+        if package_indexes.get(name) != locked_package_indexes.get(name) {
+            return Err(PlatformUnsat::IndexMismatch(name.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
 /// Returns the [`PypiPackageIdentifier`] that are present in the given set of locked packages. The
 /// resulting identifiers are also associated with the package data that they came from. This is
 /// only the case for Pypi packages.
@@ -412,3 +531,42 @@ fn package_identifiers_from_locked_packages(
     // Combine the two sets of identifiers.
     itertools::chain(conda_package_identifiers, pypi_package_identifiers).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_pypi_package_indexes() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let torch = PackageName::from_str("torch").unwrap();
+        let pinned = |index: &str| {
+            IndexMap::from([(
+                torch.clone(),
+                vec![PyPiRequirement {
+                    index: Some(url(index)),
+                    ..Default::default()
+                }],
+            )])
+        };
+        let locked = |index: &str| {
+            LockedPackageIndexes::from([(NormalizedPackageName::from(torch.clone()), url(index))])
+        };
+
+        // The artifacts of an index may be hosted elsewhere, only the index itself is compared.
+        let cu121 = "https://download.pytorch.org/whl/cu121/";
+        assert!(verify_pypi_package_indexes(&pinned(cu121), Some(&locked(cu121))).is_ok());
+        assert!(verify_pypi_package_indexes(
+            &pinned("https://download.pytorch.org/whl/cu121"),
+            Some(&locked(cu121))
+        )
+        .is_ok());
+        assert!(verify_pypi_package_indexes(&IndexMap::new(), None).is_ok());
+
+        // A different index, a new pin and a removed pin all require a new resolution.
+        let cu118 = "https://download.pytorch.org/whl/cu118/";
+        assert!(verify_pypi_package_indexes(&pinned(cu118), Some(&locked(cu121))).is_err());
+        assert!(verify_pypi_package_indexes(&pinned(cu121), None).is_err());
+        assert!(verify_pypi_package_indexes(&IndexMap::new(), Some(&locked(cu121))).is_err());
+    }
+}
@@ -4,14 +4,13 @@ use crate::{
     environment::{
         self, LockFileUsage, PerEnvironmentAndPlatform, PerGroup, PerGroupAndPlatform, PythonStatus,
     },
-    load_lock_file,
     lock_file::{
-        self, update, OutdatedEnvironments, PypiPackageIdentifier, PypiRecordsByName,
-        RepoDataRecordsByName,
+        self, load_lock_file_with_metadata, update, write_lock_file, OutdatedEnvironments,
+        PypiLockMetadata, PypiPackageIdentifier, PypiRecordsByName, RepoDataRecordsByName,
     },
     prefix::Prefix,
     progress::global_multi_progress,
-    project::{grouped_environment::GroupedEnvironment, Environment},
+    project::{grouped_environment::GroupedEnvironment, manifest::PyPiRequirement, Environment},
     repodata::fetch_sparse_repodata_targets,
     utils::BarrierCell,
     EnvironmentName, Project,
@@ -73,6 +72,9 @@ pub struct LockFileDerivedData<'p> {
     /// The lock-file
     pub lock_file: LockFile,
 
+    /// The pypi metadata that is stored in the lock-file
+    pub pypi_metadata: PypiLockMetadata,
+
     /// The package cache
     pub package_cache: Arc<PackageCache>,
 
@@ -95,7 +97,15 @@ impl<'p> LockFileDerivedData<'p> {
 
         // Get the prefix with the conda packages installed.
         let platform = Platform::current();
-        let package_db = environment.project().pypi_package_db()?;
+        let pypi_dependencies = environment.pypi_dependencies(Some(platform));
+        let package_db = environment
+            .project()
+            .pypi_package_db(&environment.pypi_options(), &pypi_dependencies)?;
+        let editable_packages = pypi_dependencies
+            .into_iter()
+            .filter(|(_, requirements)| requirements.iter().any(PyPiRequirement::is_editable))
+            .map(|(name, _)| name.into())
+            .collect();
         let (prefix, python_status) = self.conda_prefix(environment).await?;
         let repodata_records = self
             .repodata_records(environment, platform)
@@ -116,6 +126,8 @@ impl<'p> LockFileDerivedData<'p> {
             &environment.system_requirements(),
             SDistResolution::default(),
             env_variables.clone(),
+            environment.project().root(),
+            &editable_packages,
         )
         .await?;
 
@@ -394,13 +406,8 @@ pub async fn ensure_up_to_date_lock_file(
     project: &Project,
     options: UpdateLockFileOptions,
 ) -> miette::Result<LockFileDerivedData<'_>> {
-    let lock_file = load_lock_file(project).await?;
-    let current_platform = Platform::current();
+    let (lock_file, pypi_metadata) = load_lock_file_with_metadata(project).await?;
     let package_cache = Arc::new(PackageCache::new(config::get_cache_dir()?.join("pkgs")));
-    let max_concurrent_solves = options
-        .max_concurrent_solves
-        .unwrap_or_else(default_max_concurrent_solves);
-    let solve_semaphore = Arc::new(Semaphore::new(max_concurrent_solves));
 
     // should we check the lock-file in the first place?
     if !options.lock_file_usage.should_check_if_out_of_date() {
@@ -408,6 +415,7 @@ pub async fn ensure_up_to_date_lock_file(
 
         return Ok(LockFileDerivedData {
             lock_file,
+            pypi_metadata,
             package_cache,
             repo_data: options.existing_repo_data,
             updated_conda_prefixes: Default::default(),
@@ -416,13 +424,15 @@ pub async fn ensure_up_to_date_lock_file(
     }
 
     // Check which environments are out of date.
-    let outdated = OutdatedEnvironments::from_project_and_lock_file(project, &lock_file);
+    let outdated =
+        OutdatedEnvironments::from_project_and_lock_file(project, &lock_file, &pypi_metadata);
     if outdated.is_empty() {
         tracing::info!("the lock-file is up-to-date");
 
         // If no-environment is outdated we can return early.
         return Ok(LockFileDerivedData {
             lock_file,
+            pypi_metadata,
             package_cache,
             repo_data: options.existing_repo_data,
             updated_conda_prefixes: Default::default(),
@@ -435,6 +445,40 @@ pub async fn ensure_up_to_date_lock_file(
         miette::bail!("lock-file not up-to-date with the project");
     }
 
+    let derived = update_lock_file(project, lock_file, pypi_metadata, outdated, options).await?;
+
+    // Store the lock file
+    write_lock_file(project, &derived.lock_file, &derived.pypi_metadata)
+        .context("failed to write lock-file to disk")?;
+
+    Ok(derived)
+}
+
+/// Re-solves the given `outdated` targets and returns the resulting lock-file. The lock-file is
+/// not written to disk.
+///
+/// The records in `lock_file` are used as a starting point for the solves, packages that are not
+/// present in the `lock_file` are free to be updated to any version. Targets that are not marked
+/// as outdated are copied from `lock_file` as-is, and so is their `pypi_metadata`.
+pub async fn update_lock_file<'p>(
+    project: &'p Project,
+    lock_file: LockFile,
+    mut pypi_metadata: PypiLockMetadata,
+    outdated: OutdatedEnvironments<'p>,
+    options: UpdateLockFileOptions,
+) -> miette::Result<LockFileDerivedData<'p>> {
+    let current_platform = Platform::current();
+    let package_cache = Arc::new(PackageCache::new(config::get_cache_dir()?.join("pkgs")));
+    let max_concurrent_solves = options
+        .max_concurrent_solves
+        .unwrap_or_else(default_max_concurrent_solves);
+    let solve_semaphore = Arc::new(Semaphore::new(max_concurrent_solves));
+
+    // The pypi packages of the outdated environments are resolved with the current pypi options.
+    for (environment, platforms) in outdated.pypi.iter() {
+        pypi_metadata.set_resolved(environment, platforms.iter().copied());
+    }
+
     // Determine the repodata that we're going to need to solve the environments. For all outdated
     // conda targets we take the union of all the channels that are used by the environment.
     //
@@ -998,17 +1042,13 @@ pub async fn ensure_up_to_date_lock_file(
         }
     }
 
-    // Store the lock file
     let lock_file = builder.finish();
-    lock_file
-        .to_path(&project.lock_file_path())
-        .into_diagnostic()
-        .context("failed to write lock-file to disk")?;
 
     top_level_progress.finish_and_clear();
 
     Ok(LockFileDerivedData {
         lock_file,
+        pypi_metadata,
         package_cache,
         updated_conda_prefixes: context.take_instantiated_conda_prefixes(),
         updated_pypi_prefixes: HashMap::default(),
@@ -1253,7 +1293,9 @@ async fn spawn_solve_pypi_task(
     let system_requirements = environment.system_requirements();
 
     // Get the package database
-    let package_db = environment.project().pypi_package_db()?;
+    let package_db = environment
+        .project()
+        .pypi_package_db(&environment.pypi_options(), &dependencies)?;
 
     // Wait until the conda records and prefix are available.
     let (repodata_records, (prefix, python_status)) = tokio::join!(repodata_records, prefix);
@@ -1261,6 +1303,7 @@ async fn spawn_solve_pypi_task(
     let environment_name = environment.name().clone();
 
     let envs = env_variables.clone();
+    let project_root = environment.project().root().to_path_buf();
 
     let (pypi_packages, duration) = tokio::spawn(
         async move {
@@ -1287,6 +1330,7 @@ async fn spawn_solve_pypi_task(
                     .as_deref(),
                 sdist_resolution,
                 envs,
+                &project_root,
             )
             .await?;
 
@@ -1,6 +1,10 @@
 #[tokio::main]
 pub async fn main() {
     if let Err(err) = pixi::cli::execute().await {
+        // Errors of the command line are printed by clap, which exits with its own exit code.
+        if let Some(err) = err.downcast_ref::<clap::Error>() {
+            err.exit();
+        }
         eprintln!("{err:?}");
         std::process::exit(1);
     }
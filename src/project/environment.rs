@@ -1,7 +1,7 @@
 use super::{
     dependencies::Dependencies,
     errors::{UnknownTask, UnsupportedPlatformError},
-    manifest::{self, EnvironmentName, Feature, FeatureName, SystemRequirements},
+    manifest::{self, EnvironmentName, Feature, FeatureName, PypiOptions, SystemRequirements},
     PyPiRequirement, SolveGroup, SpecType,
 };
 use crate::task::TaskName;
@@ -14,6 +14,7 @@ use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
     fmt::Debug,
+    path::PathBuf,
 };
 
 /// Describes a single environment from a project manifest. This is used to describe environments
@@ -266,6 +267,31 @@ impl<'p> Environment<'p> {
             })
     }
 
+    /// Returns the pypi options for this environment.
+    ///
+    /// The pypi options of all features are combined. Extra indexes are concatenated in the order
+    /// the features are defined. Only a single primary index can be specified across all features.
+    ///
+    /// If an environment defines a solve group the pypi options of all environments in the solve
+    /// group are combined, because all the environments in the group are solved together.
+    pub fn pypi_options(&self) -> PypiOptions {
+        if let Some(solve_group) = self.solve_group() {
+            solve_group.pypi_options()
+        } else {
+            self.local_pypi_options()
+        }
+    }
+
+    /// Returns the pypi options for this environment without taking the solve-group into account.
+    pub fn local_pypi_options(&self) -> PypiOptions {
+        self.features(true)
+            .filter_map(|feature| feature.pypi_options.as_ref())
+            .fold(PypiOptions::default(), |acc, opts| {
+                acc.union(opts)
+                    .expect("pypi options should have been validated upfront")
+            })
+    }
+
     /// Returns the dependencies to install for this environment.
     ///
     /// The dependencies of all features are combined. This means that if two features define a
@@ -322,6 +348,37 @@ impl<'p> Environment<'p> {
             .collect()
     }
 
+    /// Returns the environment variables that are set when activating this environment.
+    ///
+    /// The variables of all features are combined. If multiple features define the same variable
+    /// the feature that is listed first for the environment takes precedence, the default feature
+    /// has the lowest precedence.
+    pub fn activation_env(&self, platform: Option<Platform>) -> IndexMap<String, String> {
+        self.features(true)
+            .rev()
+            .flat_map(|f| f.activation_env(platform))
+            .collect()
+    }
+
+    /// Returns the paths of the dotenv files with environment variables that are set when
+    /// activating this environment.
+    ///
+    /// The files of the project come first, followed by the files of the features. Variables of
+    /// later files override those of earlier files, so the feature that is listed first for the
+    /// environment takes precedence.
+    pub fn env_files(&self) -> Vec<PathBuf> {
+        let root = self.project.root();
+        self.project
+            .manifest
+            .parsed
+            .project
+            .env_file
+            .iter()
+            .chain(self.features(false).rev().flat_map(|f| f.env_file.iter()))
+            .map(|path| root.join(path))
+            .collect()
+    }
+
     /// Validates that the given platform is supported by this environment.
     fn validate_platform_support(
         &self,
@@ -534,6 +591,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_activation_env() {
+        let manifest = Project::from_str(
+            Path::new(""),
+            r#"
+        [project]
+        name = "foobar"
+        channels = []
+        platforms = ["linux-64", "osx-64"]
+
+        [activation.env]
+        DEFAULT = "default"
+        SHARED = "default"
+
+        [target.linux-64.activation.env]
+        SHARED = "linux"
+
+        [feature.foo.activation.env]
+        FOO = "foo"
+        SHARED = "foo"
+
+        [feature.bar.activation.env]
+        FOO = "bar"
+        BAR = "bar"
+
+        [environments]
+        foo = ["foo"]
+        foobar = ["foo", "bar"]
+                "#,
+        )
+        .unwrap();
+
+        let default_env = manifest.default_environment();
+        assert_eq!(
+            default_env
+                .activation_env(Some(Platform::Linux64))
+                .into_iter()
+                .collect_vec(),
+            vec![
+                ("DEFAULT".to_string(), "default".to_string()),
+                ("SHARED".to_string(), "linux".to_string()),
+            ]
+        );
+
+        let foobar_env = manifest.environment("foobar").unwrap();
+        let env = foobar_env.activation_env(Some(Platform::Osx64));
+        assert_eq!(env.get("DEFAULT").map(String::as_str), Some("default"));
+        assert_eq!(env.get("SHARED").map(String::as_str), Some("foo"));
+        assert_eq!(env.get("FOO").map(String::as_str), Some("foo"));
+        assert_eq!(env.get("BAR").map(String::as_str), Some("bar"));
+    }
+
     #[test]
     fn test_channel_priorities() {
         let manifest = Project::from_str(
@@ -584,4 +693,67 @@ mod tests {
             vec!["barry", "conda-forge", "bar"]
         );
     }
+
+    #[test]
+    fn test_pypi_options() {
+        let project = Project::from_str(
+            Path::new(""),
+            r#"
+        [project]
+        name = "foobar"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+
+        [pypi-options]
+        extra-index-urls = ["https://default.com/simple"]
+
+        [feature.foo.pypi-options]
+        index-url = "https://foo.com/simple"
+        extra-index-urls = ["https://extra.com/simple", "https://default.com/simple"]
+
+        [environments]
+        foo = ["foo"]
+        "#,
+        )
+        .unwrap();
+
+        let default_options = project.default_environment().pypi_options();
+        assert_eq!(
+            default_options.index_url().as_str(),
+            "https://pypi.org/simple/"
+        );
+
+        let foo_options = project.environment("foo").unwrap().pypi_options();
+        assert_eq!(foo_options.index_url().as_str(), "https://foo.com/simple/");
+        assert_eq!(
+            foo_options
+                .extra_index_urls()
+                .map(|url| url.to_string())
+                .collect_vec(),
+            vec!["https://extra.com/simple/", "https://default.com/simple/"]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_pypi_options() {
+        let result = Project::from_str(
+            Path::new(""),
+            r#"
+        [project]
+        name = "foobar"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+
+        [pypi-options]
+        index-url = "https://default.com/simple"
+
+        [feature.foo.pypi-options]
+        index-url = "https://foo.com/simple"
+
+        [environments]
+        foo = ["foo"]
+        "#,
+        );
+        assert!(result.is_err());
+    }
 }
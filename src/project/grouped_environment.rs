@@ -3,7 +3,7 @@ use crate::{
     consts,
     prefix::Prefix,
     project::{
-        manifest::{PyPiRequirement, SystemRequirements},
+        manifest::{PyPiRequirement, PypiOptions, SystemRequirements},
         virtual_packages::get_minimal_virtual_packages,
         Dependencies, Environment, SolveGroup,
     },
@@ -124,6 +124,14 @@ impl<'p> GroupedEnvironment<'p> {
         }
     }
 
+    /// Returns the pypi options of the group.
+    pub fn pypi_options(&self) -> PypiOptions {
+        match self {
+            GroupedEnvironment::Group(group) => group.pypi_options(),
+            GroupedEnvironment::Environment(env) => env.pypi_options(),
+        }
+    }
+
     /// Returns the virtual packages from the group based on the system requirements.
     pub fn virtual_packages(&self, platform: Platform) -> Vec<GenericVirtualPackage> {
         get_minimal_virtual_packages(platform, &self.system_requirements())
@@ -1,4 +1,4 @@
-use super::{Activation, PyPiRequirement, SystemRequirements, Target, TargetSelector};
+use super::{Activation, PyPiRequirement, PypiOptions, SystemRequirements, Target, TargetSelector};
 use crate::consts;
 use crate::project::manifest::channel::{PrioritizedChannel, TomlPrioritizedChannelStrOrMap};
 use crate::project::manifest::target::Targets;
@@ -6,6 +6,7 @@ use crate::project::manifest::{deserialize_opt_package_map, deserialize_package_
 use crate::project::SpecType;
 use crate::task::{Task, TaskName};
 use crate::utils::spanned::PixiSpanned;
+use crate::EnvironmentName;
 use indexmap::IndexMap;
 use itertools::Either;
 use rattler_conda_types::{NamelessMatchSpec, PackageName, Platform};
@@ -15,6 +16,7 @@ use serde_with::{serde_as, SerializeDisplay};
 use std::borrow::{Borrow, Cow};
 use std::collections::HashMap;
 use std::fmt;
+use std::path::PathBuf;
 
 /// The name of a feature. This is either a string or default for the default feature.
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, SerializeDisplay, Default)]
@@ -121,6 +123,19 @@ pub struct Feature {
     /// Additional system requirements
     pub system_requirements: SystemRequirements,
 
+    /// Options that determine where pypi packages are found.
+    ///
+    /// This value is `None` if this feature does not specify any pypi options.
+    pub pypi_options: Option<PypiOptions>,
+
+    /// The environment the tasks of this feature run in, unless another environment is selected
+    /// explicitly.
+    pub task_environment: Option<EnvironmentName>,
+
+    /// Dotenv files, relative to the root of the project, with environment variables that are set
+    /// when activating an environment that includes this feature.
+    pub env_file: Vec<PathBuf>,
+
     /// Target specific configuration.
     pub targets: Targets,
 }
@@ -208,6 +223,22 @@ impl Feature {
             .next()
     }
 
+    /// Returns the activation environment variables of the feature for the given `platform`.
+    ///
+    /// The variables of all targets that match the platform are combined, the variables of more
+    /// specific targets overwrite those of less specific targets.
+    pub fn activation_env(&self, platform: Option<Platform>) -> IndexMap<String, String> {
+        self.targets
+            .resolve(platform)
+            // Get the targets in reverse order, from least specific to most specific.
+            .rev()
+            .filter_map(|t| t.activation.as_ref())
+            .filter_map(|a| a.env.as_ref())
+            .flatten()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+
     /// Returns true if the feature contains any reference to a pypi dependencies.
     pub fn has_pypi_dependencies(&self) -> bool {
         self.targets
@@ -232,6 +263,12 @@ impl<'de> Deserialize<'de> for Feature {
             #[serde(default)]
             system_requirements: SystemRequirements,
             #[serde(default)]
+            pypi_options: Option<PypiOptions>,
+            #[serde(default)]
+            task_environment: Option<EnvironmentName>,
+            #[serde(default)]
+            env_file: Vec<PathBuf>,
+            #[serde(default)]
             target: IndexMap<PixiSpanned<TargetSelector>, Target>,
 
             #[serde(default, deserialize_with = "deserialize_package_map")]
@@ -281,6 +318,9 @@ impl<'de> Deserialize<'de> for Feature {
                     .collect()
             }),
             system_requirements: inner.system_requirements,
+            pypi_options: inner.pypi_options,
+            task_environment: inner.task_environment,
+            env_file: inner.env_file,
             targets: Targets::from_default_and_user_defined(default_target, inner.target),
         })
     }
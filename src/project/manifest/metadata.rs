@@ -50,4 +50,9 @@ pub struct ProjectMetadata {
 
     /// URL of the project documentation
     pub documentation: Option<Url>,
+
+    /// Dotenv files (relative to the project root) with environment variables that are set when
+    /// activating any environment of the project
+    #[serde(default)]
+    pub env_file: Vec<PathBuf>,
 }
@@ -4,6 +4,7 @@ mod environment;
 mod error;
 mod feature;
 mod metadata;
+mod pypi_options;
 mod python;
 mod system_requirements;
 mod target;
@@ -21,8 +22,12 @@ use indexmap::{Equivalent, IndexMap, IndexSet};
 use itertools::Itertools;
 pub use metadata::ProjectMetadata;
 use miette::{miette, Diagnostic, IntoDiagnostic, LabeledSpan, NamedSource};
+pub use pypi_options::{FindLinksUrlOrPath, PypiOptions};
 pub use python::PyPiRequirement;
-use rattler_conda_types::{MatchSpec, NamelessMatchSpec, PackageName, Platform, Version};
+use rattler_conda_types::{
+    Channel, ChannelConfig, MatchSpec, NamelessMatchSpec, PackageName, Platform, Version,
+    VersionSpec,
+};
 use serde::de::{DeserializeSeed, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use serde_with::serde_as;
@@ -186,6 +191,52 @@ impl Manifest {
         Ok(())
     }
 
+    /// Updates an existing task of the project. The `update` function is called with the current
+    /// definition of the task and returns the new definition, which is returned by this function.
+    ///
+    /// The task is updated in place: fields that did not change keep their position and
+    /// formatting, and the comments around the task are kept.
+    pub fn update_task(
+        &mut self,
+        name: &TaskName,
+        platform: Option<Platform>,
+        feature_name: &FeatureName,
+        update: impl FnOnce(&Task) -> miette::Result<Task>,
+    ) -> miette::Result<Task> {
+        let selector = platform.map(TargetSelector::from);
+        let task = self
+            .feature(feature_name)
+            .and_then(|feature| feature.targets.for_opt_target(selector.as_ref()))
+            .and_then(|target| target.tasks.get(name))
+            .ok_or_else(|| miette::miette!("task {} does not exist", name.fancy_display()))?;
+        let task = update(task)?;
+
+        // Update the task in the document
+        let tasks_table =
+            get_or_insert_toml_table(&mut self.document, platform, feature_name, "tasks")?;
+        match tasks_table.get_mut(name.as_str()) {
+            Some(item) => {
+                let new_item = with_existing_key_spelling(item, task.clone().into());
+                update_toml_item(item, new_item)
+            }
+            None => {
+                tasks_table.insert(name.as_str(), task.clone().into());
+            }
+        }
+
+        // Update the task in the internal manifest
+        if let Some(target) = self
+            .feature_mut(feature_name)
+            .expect("feature should exist")
+            .targets
+            .for_opt_target_mut(selector.as_ref())
+        {
+            target.tasks.insert(name.clone(), task.clone());
+        }
+
+        Ok(task)
+    }
+
     /// Remove a task from the project, and the tasks that depend on it
     pub fn remove_task(
         &mut self,
@@ -263,6 +314,9 @@ impl Manifest {
                                 name: feature_name.clone(),
                                 platforms: Some(PixiSpanned::from(vec![*platform])),
                                 system_requirements: Default::default(),
+                                pypi_options: None,
+                                task_environment: None,
+                                env_file: Vec::new(),
                                 targets: Default::default(),
                                 channels: None,
                             });
@@ -332,6 +386,19 @@ impl Manifest {
         let platforms_array = self.specific_array_mut("platforms", feature_name)?;
         platforms_array.retain(|x| !removed_platforms.contains(&x.as_str().unwrap().to_string()));
 
+        // A feature without platforms uses the platforms of the project.
+        if let Some(feature) = self.parsed.features.get_mut(feature_name) {
+            if !feature.is_default()
+                && feature
+                    .platforms
+                    .as_ref()
+                    .is_some_and(|platforms| platforms.value.is_empty())
+            {
+                feature.platforms = None;
+                self.remove_specific_array("platforms", feature_name);
+            }
+        }
+
         Ok(())
     }
 
@@ -490,6 +557,113 @@ impl Manifest {
             .expect("dependency should exist"))
     }
 
+    /// Sets the version of a conda dependency in `pixi.toml`. The other fields of the spec, like
+    /// the build string or channel, and the formatting of the entry are kept as they are.
+    pub fn set_dependency_version(
+        &mut self,
+        dep: &PackageName,
+        spec_type: SpecType,
+        platform: Option<Platform>,
+        feature_name: &FeatureName,
+        version: Option<VersionSpec>,
+    ) -> miette::Result<()> {
+        let mut spec = self
+            .feature(feature_name)
+            .and_then(|feature| {
+                feature
+                    .targets
+                    .for_opt_target(platform.map(TargetSelector::Platform).as_ref())
+            })
+            .and_then(|target| target.dependencies.get(&spec_type)?.get(dep))
+            .cloned()
+            .ok_or_else(|| {
+                miette!(
+                    "Couldn't find {} in [{}]",
+                    console::style(dep.as_source()).bold(),
+                    console::style(get_nested_toml_table_name(
+                        feature_name,
+                        platform,
+                        spec_type.name()
+                    ))
+                    .bold(),
+                )
+            })?;
+        spec.version = version;
+
+        self.set_toml_dependency_version(
+            feature_name,
+            platform,
+            spec_type.name(),
+            dep.as_source(),
+            spec.to_string(),
+            spec.version.as_ref().map(ToString::to_string),
+        )
+    }
+
+    /// Sets the version of a pypi dependency in `pixi.toml`. The other fields of the requirement,
+    /// like the extras, and the formatting of the entry are kept as they are.
+    pub fn set_pypi_dependency_version(
+        &mut self,
+        dep: &rip::types::PackageName,
+        platform: Option<Platform>,
+        feature_name: &FeatureName,
+        version: Option<pep440_rs::VersionSpecifiers>,
+    ) -> miette::Result<()> {
+        let version = version.map(|version| version.to_string());
+        self.set_toml_dependency_version(
+            feature_name,
+            platform,
+            consts::PYPI_DEPENDENCIES,
+            dep.as_source_str(),
+            version.clone().unwrap_or_else(|| String::from("*")),
+            version,
+        )
+    }
+
+    /// Sets the version of the dependency `key` in the dependency table `table_name`. If the
+    /// dependency is a plain string it is replaced by `string_value`, otherwise only the `version`
+    /// key of the table is replaced by `version` or removed if `version` is `None`.
+    fn set_toml_dependency_version(
+        &mut self,
+        feature_name: &FeatureName,
+        platform: Option<Platform>,
+        table_name: &str,
+        key: &str,
+        string_value: String,
+        version: Option<String>,
+    ) -> miette::Result<()> {
+        let mut document = self.document.clone();
+        let item = get_or_insert_toml_table(&mut document, platform, feature_name, table_name)?
+            .get_mut(key)
+            .ok_or_else(|| {
+                miette!(
+                    "Couldn't find {} in [{}]",
+                    console::style(key).bold(),
+                    console::style(get_nested_toml_table_name(
+                        feature_name,
+                        platform,
+                        table_name
+                    ))
+                    .bold(),
+                )
+            })?;
+
+        match (item.as_table_like_mut(), version) {
+            (Some(table), Some(version)) => match table.get_mut("version") {
+                Some(item) => update_toml_item(item, Item::Value(version.into())),
+                None => {
+                    table.insert("version", Item::Value(version.into()));
+                }
+            },
+            (Some(table), None) => {
+                table.remove("version");
+            }
+            (None, _) => update_toml_item(item, Item::Value(string_value.into())),
+        }
+
+        self.set_document(document)
+    }
+
     /// Returns true if any of the features has pypi dependencies defined.
     ///
     /// This also returns true if the `pypi-dependencies` key is defined but empty.
@@ -501,6 +675,18 @@ impl Manifest {
             .any(|f| f.pypi_dependencies.is_some())
     }
 
+    /// Removes the array `array_name` of a named feature from the toml document.
+    fn remove_specific_array(&mut self, array_name: &str, feature_name: &FeatureName) {
+        if let Some(feature) = self
+            .document
+            .get_mut("feature")
+            .and_then(|features| features.get_mut(feature_name.as_str()))
+            .and_then(Item::as_table_like_mut)
+        {
+            feature.remove(array_name);
+        }
+    }
+
     /// Returns a mutable reference to the specified array either in project or feature.
     fn specific_array_mut(
         &mut self,
@@ -554,111 +740,102 @@ impl Manifest {
         channels: impl IntoIterator<Item = PrioritizedChannel>,
         feature_name: &FeatureName,
     ) -> miette::Result<()> {
-        // First add the channels to the manifest
-        let mut stored_channels = IndexSet::new();
-        match feature_name {
-            FeatureName::Default => {
-                for channel in channels {
-                    // TODO: Make channels a IndexSet to avoid duplicates.
-                    if self.parsed.project.channels.iter().any(|x| x == &channel) {
+        // First add the channels to the manifest. A channel that is already present only has its
+        // priority updated, and keeps its priority if no new priority is given.
+        let mut stored_channels = Vec::new();
+        let feature_channels = match feature_name {
+            FeatureName::Default => &mut self.parsed.project.channels,
+            FeatureName::Named(_) => self
+                .parsed
+                .features
+                .entry(feature_name.clone())
+                .or_insert_with(|| Feature {
+                    name: feature_name.clone(),
+                    platforms: None,
+                    channels: None,
+                    system_requirements: Default::default(),
+                    pypi_options: None,
+                    task_environment: None,
+                    env_file: Vec::new(),
+                    targets: Default::default(),
+                })
+                .channels
+                .get_or_insert_with(Vec::new),
+        };
+        for channel in channels {
+            match feature_channels
+                .iter_mut()
+                .find(|x| x.channel == channel.channel)
+            {
+                Some(existing) => {
+                    if channel.priority.is_none() || existing.priority == channel.priority {
                         continue;
                     }
-                    self.parsed.project.channels.push(channel.clone());
-
-                    stored_channels.insert(channel.channel.name().to_string());
-                }
-            }
-            FeatureName::Named(_) => {
-                for channel in channels {
-                    match self.parsed.features.entry(feature_name.clone()) {
-                        Entry::Occupied(mut entry) => {
-                            if let Some(channels) = &mut entry.get_mut().channels {
-                                if channels.iter().any(|x| x == &channel) {
-                                    continue;
-                                }
-                            }
-                            // If the feature already exists, just push the new channel
-                            entry
-                                .get_mut()
-                                .channels
-                                .get_or_insert_with(Vec::new)
-                                .push(channel.clone());
-                        }
-                        Entry::Vacant(entry) => {
-                            // If the feature does not exist, insert a new feature with the new channel
-                            entry.insert(Feature {
-                                name: feature_name.clone(),
-                                platforms: None,
-                                channels: Some(vec![channel.clone()]),
-                                system_requirements: Default::default(),
-                                targets: Default::default(),
-                            });
-                        }
-                    }
-                    stored_channels.insert(channel.channel.name().to_string());
+                    existing.priority = channel.priority;
                 }
+                None => feature_channels.push(channel.clone()),
             }
+            stored_channels.push(channel);
         }
+
         // Then add the channels to the toml document
         let channels_array = self.specific_array_mut("channels", feature_name)?;
         for channel in stored_channels {
-            channels_array.push(channel);
+            let value = channel_to_toml_value(&channel);
+            let index = channels_array
+                .iter()
+                .position(|x| is_channel_entry(x, &channel.channel));
+            match index {
+                Some(index) => {
+                    channels_array.replace(index, value);
+                }
+                None => channels_array.push(value),
+            }
         }
 
         Ok(())
     }
 
-    /// Remove the specified channels to the manifest.
+    /// Remove the specified channels to the manifest. Channels are removed regardless of their
+    /// priority. If no channels are left in a feature, its `channels` are removed altogether.
     pub fn remove_channels(
         &mut self,
         channels: impl IntoIterator<Item = PrioritizedChannel>,
         feature_name: &FeatureName,
     ) -> miette::Result<()> {
-        let mut removed_channels = Vec::new();
-
-        match feature_name {
-            FeatureName::Default => {
-                for channel in channels {
-                    // TODO: Make channels a IndexSet to simplify this.
-                    if self.parsed.project.channels.iter().any(|x| x == &channel) {
-                        if let Some(index) = self
-                            .parsed
-                            .project
-                            .channels
-                            .iter()
-                            .position(|x| *x == channel)
-                        {
-                            self.parsed.project.channels.remove(index);
-                        }
-                        removed_channels.push(channel.channel.name().to_string());
-                    }
-                }
-            }
-            FeatureName::Named(_) => {
-                for channel in channels {
-                    match self.parsed.features.entry(feature_name.clone()) {
-                        Entry::Occupied(mut entry) => {
-                            if let Some(channels) = &mut entry.get_mut().channels {
-                                if let Some(index) = channels.iter().position(|x| *x == channel) {
-                                    channels.remove(index);
-                                }
-                            }
-                        }
-                        Entry::Vacant(_entry) => {
-                            return Err(miette!(
-                                "Feature {} does not exist",
-                                feature_name.as_str()
-                            ));
-                        }
-                    }
-                    removed_channels.push(channel.channel.name().to_string());
-                }
-            }
+        let feature_channels = match feature_name {
+            FeatureName::Default => Some(&mut self.parsed.project.channels),
+            FeatureName::Named(_) => self
+                .parsed
+                .features
+                .get_mut(feature_name)
+                .ok_or_else(|| miette!("Feature {} does not exist", feature_name.as_str()))?
+                .channels
+                .as_mut(),
+        };
+        let removed_channels = channels
+            .into_iter()
+            .map(|channel| channel.channel)
+            .collect_vec();
+        if let Some(feature_channels) = feature_channels {
+            feature_channels.retain(|x| !removed_channels.contains(&x.channel));
         }
 
         // remove the channels from the toml
         let channels_array = self.specific_array_mut("channels", feature_name)?;
-        channels_array.retain(|x| !removed_channels.contains(&x.as_str().unwrap().to_string()));
+        channels_array.retain(|x| {
+            !removed_channels
+                .iter()
+                .any(|channel| is_channel_entry(x, channel))
+        });
+
+        // The channels of the project are required, but a feature without channels uses them.
+        if let Some(feature) = self.parsed.features.get_mut(feature_name) {
+            if !feature.is_default() && feature.channels.as_ref().is_some_and(Vec::is_empty) {
+                feature.channels = None;
+                self.remove_specific_array("channels", feature_name);
+            }
+        }
 
         Ok(())
     }
@@ -681,6 +858,163 @@ impl Manifest {
         Ok(())
     }
 
+    /// Add an environment to the project that consists of the given `features` and that is
+    /// optionally part of a `solve_group`.
+    pub fn add_environment(
+        &mut self,
+        name: &EnvironmentName,
+        features: Vec<String>,
+        solve_group: Option<String>,
+    ) -> miette::Result<()> {
+        let mut document = self.document.clone();
+        let environments = document
+            .entry("environments")
+            .or_insert(Item::Table(Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| miette!("the `environments` of the manifest is not a table"))?;
+        if environments.contains_key(name.as_str()) {
+            miette::bail!("environment {} already exists", name.fancy_display());
+        }
+        if let Some(feature) = features
+            .iter()
+            .find(|&feature| self.feature(&FeatureName::Named(feature.clone())).is_none())
+        {
+            miette::bail!(
+                help = "add the feature to the project manifest",
+                "the feature '{feature}' is not defined in the project manifest"
+            );
+        }
+
+        // Environments without a solve group are written as a list of features.
+        let features = Array::from_iter(features);
+        let environment = match solve_group {
+            Some(solve_group) => {
+                let mut table = toml_edit::InlineTable::new();
+                table.insert("features", Value::Array(features));
+                table.insert("solve-group", solve_group.into());
+                Value::InlineTable(table)
+            }
+            None => Value::Array(features),
+        };
+        environments.insert(name.as_str(), Item::Value(environment));
+
+        self.set_document(document)
+    }
+
+    /// Remove an environment from the project
+    pub fn remove_environment(&mut self, name: &EnvironmentName) -> miette::Result<()> {
+        let mut document = self.document.clone();
+        let environments = document
+            .get_mut("environments")
+            .and_then(Item::as_table_like_mut);
+        let Some(environments) = environments else {
+            miette::bail!("environment {} does not exist", name.fancy_display());
+        };
+        if environments.remove(name.as_str()).is_none() {
+            miette::bail!("environment {} does not exist", name.fancy_display());
+        }
+
+        self.set_document(document)
+    }
+
+    /// Add an empty feature to the project
+    pub fn add_feature(&mut self, name: &FeatureName) -> miette::Result<()> {
+        let FeatureName::Named(feature_name) = name else {
+            miette::bail!("the default feature cannot be added");
+        };
+        if self.feature(name).is_some() {
+            miette::bail!("feature {} already exists", name.fancy_display());
+        }
+
+        let mut document = self.document.clone();
+        let features = document
+            .entry("feature")
+            .or_insert_with(|| {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            })
+            .as_table_mut()
+            .ok_or_else(|| miette!("the `feature` of the manifest is not a table"))?;
+        features.insert(feature_name, Item::Table(Table::new()));
+
+        self.set_document(document)
+    }
+
+    /// Remove a feature from the project. If environments still include the feature, an error is
+    /// returned unless `remove_from_environments` is true, in which case the feature is removed
+    /// from these environments as well.
+    pub fn remove_feature(
+        &mut self,
+        name: &FeatureName,
+        remove_from_environments: bool,
+    ) -> miette::Result<()> {
+        let FeatureName::Named(feature_name) = name else {
+            miette::bail!("the default feature cannot be removed");
+        };
+        if self.feature(name).is_none() {
+            miette::bail!("feature {} does not exist", name.fancy_display());
+        }
+
+        let environments = self
+            .parsed
+            .environments
+            .iter()
+            .filter(|env| env.features.contains(feature_name))
+            .map(|env| env.name.clone())
+            .collect_vec();
+        if !environments.is_empty() && !remove_from_environments {
+            miette::bail!(
+                help = "remove the feature from these environments first",
+                "feature {} is used by the environments {}",
+                name.fancy_display(),
+                environments
+                    .iter()
+                    .map(|env| env.fancy_display())
+                    .format(", ")
+            );
+        }
+
+        let mut document = self.document.clone();
+        if let Some(features) = document
+            .get_mut("feature")
+            .and_then(Item::as_table_like_mut)
+        {
+            features.remove(feature_name);
+        }
+        for environment in environments {
+            let features = document
+                .get_mut("environments")
+                .and_then(|environments| environments.get_mut(environment.as_str()))
+                .and_then(|item| match item.is_array() {
+                    true => item.as_array_mut(),
+                    false => item.get_mut("features").and_then(Item::as_array_mut),
+                });
+            if let Some(features) = features {
+                features.retain(|feature| feature.as_str() != Some(feature_name));
+                features.fmt();
+            }
+        }
+
+        self.set_document(document)
+    }
+
+    /// Replaces the toml document of the manifest and updates the parsed manifest to match it.
+    /// The manifest is not changed if the new document is not a valid manifest.
+    fn set_document(&mut self, document: Document) -> miette::Result<()> {
+        let contents = document.to_string();
+        let parsed = ProjectManifest::from_toml_str(&contents).into_diagnostic()?;
+        let root = self
+            .path
+            .parent()
+            .expect("Path should always have a parent");
+        parsed.validate(NamedSource::new(consts::PROJECT_MANIFEST, contents), root)?;
+
+        self.document = document;
+        self.parsed = parsed;
+        Ok(())
+    }
+
     /// Returns the default feature.
     ///
     /// This is the feature that is added implicitly by the tables at the root of the project
@@ -735,6 +1069,32 @@ impl Manifest {
     }
 }
 
+/// Returns true if `value`, an entry of a `channels` array, refers to `channel`. The entry is
+/// either the name of the channel or a table with the channel and its priority.
+fn is_channel_entry(value: &Value, channel: &Channel) -> bool {
+    let name = match value {
+        Value::InlineTable(table) => table.get("channel").and_then(Value::as_str),
+        value => value.as_str(),
+    };
+    name.and_then(|name| Channel::from_str(name, &ChannelConfig::default()).ok())
+        .is_some_and(|entry| &entry == channel)
+}
+
+/// Returns the entry of a `channels` array for `channel`, which is a table if the channel has a
+/// priority.
+fn channel_to_toml_value(channel: &PrioritizedChannel) -> Value {
+    let name = channel.channel.name().to_string();
+    match channel.priority {
+        Some(priority) => {
+            let mut table = toml_edit::InlineTable::new();
+            table.insert("channel", name.into());
+            table.insert("priority", i64::from(priority).into());
+            Value::InlineTable(table)
+        }
+        None => name.into(),
+    }
+}
+
 /// Returns the name of a nested TOML table.
 /// If `platform` and `feature_name` are `None`, the table name is returned as-is.
 /// Otherwise, the table name is prefixed with the feature, platform, or both.
@@ -760,6 +1120,78 @@ fn get_nested_toml_table_name(
     }
 }
 
+/// Renames the keys of the `new_item` table to the spelling that `item` already uses for them.
+/// Fields of a task can be written in kebab-case as well as in snake_case, e.g. `depends-on` and
+/// `depends_on`, the existing spelling is kept so the field is updated in place.
+fn with_existing_key_spelling(item: &Item, mut new_item: Item) -> Item {
+    let (Some(table), Some(new_table)) = (item.as_table_like(), new_item.as_table_like_mut())
+    else {
+        return new_item;
+    };
+    let normalize = |key: &str| key.replace('-', "_");
+    let renames = new_table
+        .iter()
+        .filter(|(new_key, _)| !table.contains_key(new_key))
+        .filter_map(|(new_key, _)| {
+            let (key, _) = table
+                .iter()
+                .find(|(key, _)| normalize(key) == normalize(new_key))?;
+            Some((new_key.to_string(), key.to_string()))
+        })
+        .collect_vec();
+    for (new_key, key) in renames {
+        if let Some(value) = new_table.remove(&new_key) {
+            new_table.insert(&key, value);
+        }
+    }
+    new_item
+}
+
+/// Replaces the contents of `item` with `new_item` while preserving as much of the formatting of
+/// `item` as possible. If both are tables, only the keys that changed are replaced, removed or
+/// added, so the other keys keep their position and comments.
+fn update_toml_item(item: &mut Item, new_item: Item) {
+    if let (Some(table), Some(new_table)) = (item.as_table_like_mut(), new_item.as_table_like()) {
+        let removed_keys = table
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .filter(|key| !new_table.contains_key(key))
+            .collect_vec();
+        let mut reformat = !removed_keys.is_empty();
+        for key in removed_keys {
+            table.remove(&key);
+        }
+        for (key, new_value) in new_table.iter() {
+            match table.get_mut(key) {
+                Some(value) => update_toml_item(value, new_value.clone()),
+                None => {
+                    table.insert(key, new_value.clone());
+                    reformat = true;
+                }
+            }
+        }
+        // Adding or removing keys of an inline table leaves the spacing around the braces in the
+        // middle of the table.
+        if reformat {
+            if let Some(inline_table) = item.as_inline_table_mut() {
+                inline_table.fmt();
+            }
+        }
+        return;
+    }
+
+    match (item.as_value_mut(), new_item) {
+        (Some(value), Item::Value(mut new_value)) => {
+            // Only replace values that changed, to keep their formatting.
+            if value.to_string().trim() != new_value.to_string().trim() {
+                *new_value.decor_mut() = value.decor().clone();
+                *value = new_value;
+            }
+        }
+        (_, new_item) => *item = new_item,
+    }
+}
+
 /// Retrieve a mutable reference to a target table `table_name`
 /// for a specific platform.
 /// If table not found, its inserted into the document.
@@ -1008,6 +1440,8 @@ impl<'de> Deserialize<'de> for ProjectManifest {
             #[serde(default)]
             system_requirements: SystemRequirements,
             #[serde(default)]
+            pypi_options: Option<PypiOptions>,
+            #[serde(default)]
             target: IndexMap<PixiSpanned<TargetSelector>, Target>,
 
             // HACK: If we use `flatten`, unknown keys will point to the wrong location in the file.
@@ -1074,6 +1508,14 @@ impl<'de> Deserialize<'de> for ProjectManifest {
 
             system_requirements: toml_manifest.system_requirements,
 
+            pypi_options: toml_manifest.pypi_options,
+
+            // The tasks of the default feature are searched for in all environments.
+            task_environment: None,
+
+            // The env files of the project are stored in the project metadata.
+            env_file: Vec::new(),
+
             // Combine the default target with all user specified targets
             targets: Targets::from_default_and_user_defined(default_target, toml_manifest.target),
         };
@@ -1467,6 +1909,29 @@ mod tests {
             .join("\n"));
     }
 
+    #[test]
+    fn test_task_fields_accept_both_spellings() {
+        let contents = format!(
+            r#"
+            {PROJECT_BOILERPLATE}
+            [tasks]
+            snake = {{ cmd = "echo", depends_on = ["kebab"], env_file = [".env"], allow_failure = true }}
+            kebab = {{ cmd = "echo", depends-on = ["alias"], env-file = [".env"], allow-failure = true }}
+            alias = {{ depends-on = ["snake"] }}
+            "#
+        );
+
+        let manifest = ProjectManifest::from_toml_str(&contents).unwrap();
+        let tasks = &manifest.default_feature().targets.default().tasks;
+        for name in ["snake", "kebab"] {
+            let task = &tasks[&TaskName::from(name)];
+            assert_eq!(task.depends_on().len(), 1);
+            assert_eq!(task.env_files(), [PathBuf::from(".env")]);
+            assert!(task.allow_failure());
+        }
+        assert_eq!(tasks[&TaskName::from("alias")].depends_on().len(), 1);
+    }
+
     #[test]
     fn test_python_dependencies() {
         let contents = format!(
@@ -1503,7 +1968,7 @@ mod tests {
         // Initially the dependency should exist
         assert!(manifest
             .feature_mut(feature_name)
-            .expect(&format!("feature `{}` should exist", feature_name.as_str()))
+            .unwrap_or_else(|| panic!("feature `{}` should exist", feature_name.as_str()))
             .targets
             .for_opt_target(platform.map(TargetSelector::Platform).as_ref())
             .unwrap()
@@ -1526,7 +1991,7 @@ mod tests {
         // The dependency should no longer exist
         assert!(manifest
             .feature_mut(feature_name)
-            .expect(&format!("feature `{}` should exist", feature_name.as_str()))
+            .unwrap_or_else(|| panic!("feature `{}` should exist", feature_name.as_str()))
             .targets
             .for_opt_target(platform.map(TargetSelector::Platform).as_ref())
             .unwrap()
@@ -1556,7 +2021,7 @@ mod tests {
         // Initially the dependency should exist
         assert!(manifest
             .feature_mut(feature_name)
-            .expect(&format!("feature `{}` should exist", feature_name.as_str()))
+            .unwrap_or_else(|| panic!("feature `{}` should exist", feature_name.as_str()))
             .targets
             .for_opt_target(platform.map(TargetSelector::Platform).as_ref())
             .unwrap()
@@ -1574,7 +2039,7 @@ mod tests {
         // The dependency should no longer exist
         assert!(manifest
             .feature_mut(feature_name)
-            .expect(&format!("feature `{}` should exist", feature_name.as_str()))
+            .unwrap_or_else(|| panic!("feature `{}` should exist", feature_name.as_str()))
             .targets
             .for_opt_target(platform.map(TargetSelector::Platform).as_ref())
             .unwrap()
@@ -1631,6 +2096,95 @@ feature_target_dep = "*"
         test_remove_pypi(pixi_cfg, package_name, platform, &feature_name);
     }
 
+    #[test]
+    fn test_set_dependency_version() {
+        let file_contents = r#"
+            [project]
+            name = "foo"
+            channels = []
+            platforms = ["linux-64"]
+
+            [dependencies]
+            foo = ">=1.2.0,<1.3" # the foo library
+            bar = { version = "==1.0", build = "py*", channel = "conda-forge" }
+
+            [target.linux-64.host-dependencies]
+            baz = "1.2.* py*"
+
+            [feature.test.pypi-dependencies]
+            qux = ">=1.0"
+            quux = { version = "~=1.0", extras = ["a"] }
+        "#;
+        let mut manifest = Manifest::from_str(Path::new(""), file_contents).unwrap();
+        let test_feature = FeatureName::Named("test".to_string());
+
+        manifest
+            .set_dependency_version(
+                &PackageName::from_str("foo").unwrap(),
+                SpecType::Run,
+                None,
+                &FeatureName::Default,
+                Some(VersionSpec::from_str(">=1.4.1,<1.5").unwrap()),
+            )
+            .unwrap();
+        manifest
+            .set_dependency_version(
+                &PackageName::from_str("bar").unwrap(),
+                SpecType::Run,
+                None,
+                &FeatureName::Default,
+                Some(VersionSpec::from_str("==2.0").unwrap()),
+            )
+            .unwrap();
+        manifest
+            .set_dependency_version(
+                &PackageName::from_str("baz").unwrap(),
+                SpecType::Host,
+                Some(Platform::Linux64),
+                &FeatureName::Default,
+                Some(VersionSpec::from_str("1.3.*").unwrap()),
+            )
+            .unwrap();
+        manifest
+            .set_pypi_dependency_version(
+                &rip::types::PackageName::from_str("qux").unwrap(),
+                None,
+                &test_feature,
+                Some(pep440_rs::VersionSpecifiers::from_str(">=2.0").unwrap()),
+            )
+            .unwrap();
+        manifest
+            .set_pypi_dependency_version(
+                &rip::types::PackageName::from_str("quux").unwrap(),
+                None,
+                &test_feature,
+                None,
+            )
+            .unwrap();
+
+        // Unknown dependencies are an error
+        assert!(manifest
+            .set_dependency_version(
+                &PackageName::from_str("unknown").unwrap(),
+                SpecType::Run,
+                None,
+                &FeatureName::Default,
+                None,
+            )
+            .is_err());
+
+        // The parsed manifest should be updated as well
+        let bar = &manifest.default_feature().targets.default().dependencies[&SpecType::Run]
+            [&PackageName::from_str("bar").unwrap()];
+        assert_eq!(bar.version, Some(VersionSpec::from_str("==2.0").unwrap()));
+        assert_eq!(
+            bar.build.as_ref().map(ToString::to_string).as_deref(),
+            Some("py*")
+        );
+
+        assert_display_snapshot!(manifest.document.to_string());
+    }
+
     #[test]
     fn test_remove_target_dependencies() {
         // Using known files in the project so the test succeed including the file check.
@@ -1950,7 +2504,7 @@ platforms = ["linux-64", "win-64"]
             Channel::from_str("conda-forge", &ChannelConfig::default()).unwrap(),
         );
         manifest
-            .add_channels([conda_forge.clone()].into_iter(), &FeatureName::Default)
+            .add_channels([conda_forge.clone()], &FeatureName::Default)
             .unwrap();
 
         let cuda_feature = FeatureName::Named("cuda".to_string());
@@ -1958,7 +2512,7 @@ platforms = ["linux-64", "win-64"]
             Channel::from_str("nvidia", &ChannelConfig::default()).unwrap(),
         );
         manifest
-            .add_channels([nvidia.clone()].into_iter(), &cuda_feature)
+            .add_channels([nvidia.clone()], &cuda_feature)
             .unwrap();
 
         let test_feature = FeatureName::Named("test".to_string());
@@ -1971,8 +2525,7 @@ platforms = ["linux-64", "win-64"]
                     PrioritizedChannel::from_channel(
                         Channel::from_str("test2", &ChannelConfig::default()).unwrap(),
                     ),
-                ]
-                .into_iter(),
+                ],
                 &test_feature,
             )
             .unwrap();
@@ -1987,7 +2540,7 @@ platforms = ["linux-64", "win-64"]
 
         // Try to add again, should not add more channels
         manifest
-            .add_channels([conda_forge.clone()].into_iter(), &FeatureName::Default)
+            .add_channels([conda_forge.clone()], &FeatureName::Default)
             .unwrap();
 
         assert_eq!(
@@ -2014,7 +2567,7 @@ platforms = ["linux-64", "win-64"]
         );
         // Try to add again, should not add more channels
         manifest
-            .add_channels([nvidia.clone()].into_iter(), &cuda_feature)
+            .add_channels([nvidia.clone()], &cuda_feature)
             .unwrap();
         assert_eq!(
             manifest
@@ -2084,8 +2637,7 @@ platforms = ["linux-64", "win-64"]
                 [PrioritizedChannel {
                     channel: Channel::from_str("conda-forge", &ChannelConfig::default()).unwrap(),
                     priority: None,
-                }]
-                .into_iter(),
+                }],
                 &FeatureName::Default,
             )
             .unwrap();
@@ -2097,19 +2649,120 @@ platforms = ["linux-64", "win-64"]
                 [PrioritizedChannel {
                     channel: Channel::from_str("test_channel", &ChannelConfig::default()).unwrap(),
                     priority: None,
-                }]
-                .into_iter(),
+                }],
                 &FeatureName::Named("test".to_string()),
             )
             .unwrap();
 
+        // A feature without channels uses the channels of the project.
         let feature_channels = manifest
             .feature(&FeatureName::Named("test".to_string()))
             .unwrap()
             .channels
-            .clone()
+            .clone();
+        assert_eq!(feature_channels, None);
+        assert!(!manifest.document.to_string().contains("test_channel"));
+    }
+
+    #[test]
+    fn test_channel_priority() {
+        let file_contents = r#"
+[project]
+name = "foo"
+channels = ["conda-forge"]
+platforms = ["linux-64", "win-64"]
+
+[feature.cuda]
+channels = ["nvidia", { channel = "pytorch", priority = 1 }]
+platforms = ["linux-64"]
+        "#;
+
+        let mut manifest = Manifest::from_str(Path::new(""), file_contents).unwrap();
+        let channel = |name: &str, priority: Option<i32>| PrioritizedChannel {
+            channel: Channel::from_str(name, &ChannelConfig::default()).unwrap(),
+            priority,
+        };
+        let cuda = FeatureName::Named("cuda".to_string());
+        let test = FeatureName::Named("test".to_string());
+
+        // Adding a channel that exists updates its priority.
+        manifest
+            .add_channels(
+                [channel("nvidia", Some(2)), channel("bioconda", Some(-1))],
+                &cuda,
+            )
+            .unwrap();
+        manifest
+            .add_channels([channel("bioconda", Some(1))], &test)
+            .unwrap();
+        assert_eq!(
+            manifest.feature(&cuda).unwrap().channels,
+            Some(vec![
+                channel("nvidia", Some(2)),
+                channel("pytorch", Some(1)),
+                channel("bioconda", Some(-1)),
+            ])
+        );
+
+        // Channels are removed regardless of their priority.
+        manifest
+            .remove_channels([channel("pytorch", None)], &cuda)
+            .unwrap();
+        assert_eq!(
+            manifest.feature(&cuda).unwrap().channels,
+            Some(vec![
+                channel("nvidia", Some(2)),
+                channel("bioconda", Some(-1))
+            ])
+        );
+
+        // Empty arrays of features are removed.
+        manifest
+            .remove_channels([channel("bioconda", None)], &test)
+            .unwrap();
+        manifest
+            .remove_platforms(&vec![Platform::Linux64], &cuda)
             .unwrap();
-        assert_eq!(feature_channels, vec![]);
+        assert_eq!(manifest.feature(&test).unwrap().channels, None);
+        assert_eq!(manifest.feature(&cuda).unwrap().platforms, None);
+
+        assert_display_snapshot!(manifest.document.to_string());
+    }
+
+    #[test]
+    fn test_add_existing_channel_keeps_priority() {
+        let file_contents = r#"
+[project]
+name = "foo"
+channels = [{ channel = "conda-forge", priority = 1 }]
+platforms = ["linux-64"]
+        "#;
+
+        let mut manifest = Manifest::from_str(Path::new(""), file_contents).unwrap();
+        let conda_forge = |priority: Option<i32>| PrioritizedChannel {
+            channel: Channel::from_str("conda-forge", &ChannelConfig::default()).unwrap(),
+            priority,
+        };
+
+        // Adding a channel again without a priority keeps its priority.
+        manifest
+            .add_channels([conda_forge(None)], &FeatureName::Default)
+            .unwrap();
+        assert_eq!(manifest.parsed.project.channels, vec![conda_forge(Some(1))]);
+        assert!(manifest
+            .document
+            .to_string()
+            .contains(r#"channels = [{ channel = "conda-forge", priority = 1 }]"#));
+
+        // Adding it with a priority updates its priority.
+        manifest
+            .add_channels([conda_forge(Some(2))], &FeatureName::Default)
+            .unwrap();
+        assert_eq!(manifest.parsed.project.channels, vec![conda_forge(Some(2))]);
+        assert!(manifest
+            .document
+            .to_string()
+            .contains(r#"channels = [{ channel = "conda-forge", priority = 2 }]"#));
     }
 
     #[test]
@@ -2399,6 +3052,211 @@ test = "test initial"
         assert_display_snapshot!(manifest.document.to_string());
     }
 
+    #[test]
+    fn test_update_task() {
+        let file_contents = r#"
+[project]
+name = "foo"
+version = "0.1.0"
+channels = []
+platforms = ["linux-64", "win-64"]
+
+[tasks]
+# Builds the project
+build = { cmd = "make", cwd = "src" } # in the source directory
+test = "pytest"
+lint = "ruff check ."
+
+[target.linux-64.tasks]
+test = "pytest --linux"
+        "#;
+
+        let mut manifest = Manifest::from_str(Path::new(""), file_contents).unwrap();
+
+        manifest
+            .update_task(&"build".into(), None, &FeatureName::Default, |task| {
+                let Task::Execute(execute) = task else {
+                    panic!("expected an execute task");
+                };
+                let mut execute = execute.clone();
+                execute.cmd = crate::task::CmdArgs::Single("make all".to_string());
+                execute.depends_on = vec![TaskName::from("lint").into()];
+                Ok(Task::Execute(execute))
+            })
+            .unwrap();
+        manifest
+            .update_task(
+                &"test".into(),
+                Some(Platform::Linux64),
+                &FeatureName::Default,
+                |_| Ok(Task::Plain("pytest -x".to_string())),
+            )
+            .unwrap();
+
+        // Tasks that don't exist in the selected target can't be updated.
+        assert!(manifest
+            .update_task(
+                &"lint".into(),
+                Some(Platform::Linux64),
+                &FeatureName::Default,
+                |task| Ok(task.clone()),
+            )
+            .is_err());
+
+        assert_display_snapshot!(manifest.document.to_string());
+        assert_eq!(
+            manifest
+                .default_feature()
+                .targets
+                .for_target(&TargetSelector::Platform(Platform::Linux64))
+                .unwrap()
+                .tasks
+                .get(&"test".into())
+                .unwrap()
+                .as_single_command()
+                .as_deref(),
+            Some("pytest -x")
+        );
+    }
+
+    #[test]
+    fn test_update_kebab_case_task() {
+        let file_contents = r#"
+[project]
+name = "foo"
+channels = []
+platforms = ["linux-64"]
+
+# Runs the tests
+[tasks.test]
+cmd = "pytest"
+depends-on = ["build"] # build first
+allow-failure = false
+env-file = [".env"] # local settings
+"#;
+
+        let mut manifest = Manifest::from_str(Path::new(""), file_contents).unwrap();
+
+        manifest
+            .update_task(&"test".into(), None, &FeatureName::Default, |task| {
+                let Task::Execute(execute) = task else {
+                    panic!("expected an execute task");
+                };
+                let mut execute = execute.clone();
+                execute.cmd = crate::task::CmdArgs::Single("pytest -x".to_string());
+                execute.depends_on.push(TaskName::from("lint").into());
+                execute.allow_failure = Some(true);
+                Ok(Task::Execute(execute))
+            })
+            .unwrap();
+
+        assert_display_snapshot!(manifest.document.to_string());
+    }
+
+    #[test]
+    fn test_add_remove_environment() {
+        let file_contents = r#"
+[project]
+name = "foo"
+channels = []
+platforms = ["linux-64"]
+
+[feature.py39.dependencies]
+python = "3.9.*"
+
+[feature.test.dependencies]
+pytest = "*"
+
+# The environments of the project
+[environments]
+py39 = ["py39"] # Python 3.9
+        "#;
+
+        let mut manifest = Manifest::from_str(Path::new(""), file_contents).unwrap();
+        let name = |name: &str| EnvironmentName::from_str(name).unwrap();
+
+        manifest
+            .add_environment(
+                &name("test"),
+                vec!["py39".to_string(), "test".to_string()],
+                Some("group".to_string()),
+            )
+            .unwrap();
+        manifest
+            .add_environment(&name("lint"), vec![], None)
+            .unwrap();
+        let environment = manifest.environment(&name("test")).unwrap();
+        assert_eq!(environment.features, vec!["py39", "test"]);
+        assert_eq!(
+            manifest
+                .parsed
+                .solve_groups
+                .iter()
+                .position(|g| g.name == "group"),
+            environment.solve_group
+        );
+        assert!(manifest.environment(&name("lint")).is_some());
+
+        // Environments that already exist or with unknown features are rejected.
+        assert!(manifest
+            .add_environment(&name("test"), vec![], None)
+            .is_err());
+        assert!(manifest
+            .add_environment(&name("docs"), vec!["docs".to_string()], None)
+            .is_err());
+        assert!(manifest.environment(&name("docs")).is_none());
+
+        manifest.remove_environment(&name("lint")).unwrap();
+        assert!(manifest.environment(&name("lint")).is_none());
+        assert!(manifest.remove_environment(&name("lint")).is_err());
+
+        assert_display_snapshot!(manifest.document.to_string());
+    }
+
+    #[test]
+    fn test_add_remove_feature() {
+        let file_contents = r#"
+[project]
+name = "foo"
+channels = []
+platforms = ["linux-64"]
+
+[feature.py39.dependencies]
+python = "3.9.*"
+
+[feature.test.dependencies]
+pytest = "*"
+
+[environments]
+py39 = ["py39", "test"] # Python 3.9
+test = { features = ["test"], solve-group = "group" }
+        "#;
+
+        let mut manifest = Manifest::from_str(Path::new(""), file_contents).unwrap();
+        let name = |name: &str| FeatureName::Named(name.to_string());
+
+        manifest.add_feature(&name("lint")).unwrap();
+        assert!(manifest.feature(&name("lint")).is_some());
+        assert!(manifest.add_feature(&name("lint")).is_err());
+        assert!(manifest.add_feature(&FeatureName::Default).is_err());
+
+        // Features that are used by environments are only removed when requested.
+        assert!(manifest.remove_feature(&name("test"), false).is_err());
+        assert!(manifest.feature(&name("test")).is_some());
+        manifest.remove_feature(&name("test"), true).unwrap();
+        assert!(manifest.feature(&name("test")).is_none());
+        assert_eq!(
+            manifest
+                .environment(&EnvironmentName::from_str("py39").unwrap())
+                .unwrap()
+                .features,
+            vec!["py39"]
+        );
+        assert!(manifest.remove_feature(&name("test"), true).is_err());
+
+        assert_display_snapshot!(manifest.document.to_string());
+    }
+
     #[test]
     fn test_get_nested_toml_table_name() {
         // Test all different options for the feature name and platform
@@ -0,0 +1,258 @@
+use indexmap::IndexSet;
+use miette::Diagnostic;
+use rip::normalize_index_url;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use thiserror::Error;
+use url::Url;
+
+/// Describes the options that are used to find PyPI packages. These are defined in the
+/// `[pypi-options]` table of the project manifest or of a feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "TomlPypiOptions")]
+pub struct PypiOptions {
+    /// The primary index to look for packages. Defaults to `https://pypi.org/simple/`.
+    pub index_url: Option<Url>,
+
+    /// Additional indexes that are searched after the primary index.
+    pub extra_index_urls: Option<Vec<Url>>,
+
+    /// Flat indexes, directories or html pages that link to the distributions of packages, that
+    /// are searched next to the indexes.
+    pub find_links: Option<Vec<FindLinksUrlOrPath>>,
+}
+
+/// A flat index in the `find-links` of the pypi options, written as `{ path = "./links" }` or
+/// `{ url = "https://example.com/links.html" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "RawFindLinks", into = "RawFindLinks")]
+pub enum FindLinksUrlOrPath {
+    /// A local directory that contains distributions, relative to the project root.
+    Path(PathBuf),
+
+    /// An html page that links to distributions.
+    Url(Url),
+}
+
+/// The table of a [`FindLinksUrlOrPath`] as it is written in the manifest and the lock-file.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFindLinks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<Url>,
+}
+
+impl TryFrom<RawFindLinks> for FindLinksUrlOrPath {
+    type Error = String;
+
+    fn try_from(value: RawFindLinks) -> Result<Self, Self::Error> {
+        match (value.path, value.url) {
+            (Some(path), None) => Ok(Self::Path(path)),
+            (None, Some(url)) => Ok(Self::Url(url)),
+            _ => Err(String::from(
+                "exactly one of `path` or `url` must be specified for `find-links`",
+            )),
+        }
+    }
+}
+
+impl From<FindLinksUrlOrPath> for RawFindLinks {
+    fn from(value: FindLinksUrlOrPath) -> Self {
+        match value {
+            FindLinksUrlOrPath::Path(path) => Self {
+                path: Some(path),
+                url: None,
+            },
+            FindLinksUrlOrPath::Url(url) => Self {
+                path: None,
+                url: Some(url),
+            },
+        }
+    }
+}
+
+/// The `[pypi-options]` table as it is written in the manifest.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct TomlPypiOptions {
+    index_url: Option<Url>,
+    extra_index_urls: Option<Vec<Url>>,
+    find_links: Option<Vec<FindLinksUrlOrPath>>,
+}
+
+impl From<TomlPypiOptions> for PypiOptions {
+    fn from(value: TomlPypiOptions) -> Self {
+        Self {
+            index_url: value.index_url,
+            extra_index_urls: value.extra_index_urls,
+            find_links: value.find_links,
+        }
+    }
+}
+
+impl PypiOptions {
+    /// Returns true if none of the options are specified.
+    pub fn is_empty(&self) -> bool {
+        self.index_url.is_none() && self.extra_index_urls.is_none() && self.find_links.is_none()
+    }
+
+    /// Returns the primary index url or the default PyPI index if none was specified.
+    pub fn index_url(&self) -> Url {
+        normalize_index_url(
+            self.index_url
+                .clone()
+                .unwrap_or_else(|| Url::parse("https://pypi.org/simple/").unwrap()),
+        )
+    }
+
+    /// Returns the normalized extra index urls in the order they were specified.
+    pub fn extra_index_urls(&self) -> impl Iterator<Item = Url> + '_ {
+        self.extra_index_urls
+            .iter()
+            .flatten()
+            .cloned()
+            .map(normalize_index_url)
+    }
+
+    /// Returns the flat indexes in the order they were specified.
+    pub fn find_links(&self) -> impl Iterator<Item = &FindLinksUrlOrPath> + '_ {
+        self.find_links.iter().flatten()
+    }
+
+    /// Returns the combination of two sets of pypi options.
+    ///
+    /// The extra indexes and the flat indexes of both options are concatenated and deduplicated. Only a
+    /// single primary index can be used, an error is returned if both specify a different one.
+    pub fn union(&self, other: &Self) -> Result<Self, PypiOptionsUnionError> {
+        let index_url = match (&self.index_url, &other.index_url) {
+            (Some(a), Some(b)) if a != b => {
+                return Err(PypiOptionsUnionError::MultipleIndexUrls(
+                    Box::new(a.clone()),
+                    Box::new(b.clone()),
+                ))
+            }
+            (Some(a), _) => Some(a.clone()),
+            (None, b) => b.clone(),
+        };
+
+        fn union_vec<T: Clone + Eq + std::hash::Hash>(
+            a: &Option<Vec<T>>,
+            b: &Option<Vec<T>>,
+        ) -> Option<Vec<T>> {
+            match (a, b) {
+                (None, None) => None,
+                _ => Some(
+                    a.iter()
+                        .chain(b.iter())
+                        .flatten()
+                        .cloned()
+                        .collect::<IndexSet<_>>()
+                        .into_iter()
+                        .collect(),
+                ),
+            }
+        }
+
+        Ok(Self {
+            index_url,
+            extra_index_urls: union_vec(&self.extra_index_urls, &other.extra_index_urls),
+            find_links: union_vec(&self.find_links, &other.find_links),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Error, Diagnostic)]
+pub enum PypiOptionsUnionError {
+    #[error("multiple primary pypi indexes are not supported, found both '{0}' and '{1}'")]
+    MultipleIndexUrls(Box<Url>, Box<Url>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_pypi_options() {
+        let options: PypiOptions = toml_edit::de::from_str(
+            r#"
+            index-url = "https://example.com/simple"
+            extra-index-urls = ["https://example.com/extra"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            options.index_url().as_str(),
+            "https://example.com/simple/",
+            "the index url should be normalized"
+        );
+        assert_eq!(
+            options
+                .extra_index_urls()
+                .map(String::from)
+                .collect::<Vec<_>>(),
+            vec!["https://example.com/extra/"]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_find_links() {
+        let options: PypiOptions = toml_edit::de::from_str(
+            r#"
+            find-links = [{ path = "./links" }, { url = "https://example.com/links.html" }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            options.find_links().cloned().collect::<Vec<_>>(),
+            vec![
+                FindLinksUrlOrPath::Path(PathBuf::from("./links")),
+                FindLinksUrlOrPath::Url(Url::parse("https://example.com/links.html").unwrap()),
+            ]
+        );
+        assert!(!options.is_empty());
+
+        let err = toml_edit::de::from_str::<PypiOptions>(
+            r#"
+            find-links = [{ path = "./links", url = "https://example.com/links.html" }]
+            "#,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("exactly one of `path` or `url`"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let a = PypiOptions {
+            index_url: Some(url("https://a.com/simple")),
+            extra_index_urls: Some(vec![url("https://extra.com/1")]),
+            find_links: None,
+        };
+        let b = PypiOptions {
+            index_url: None,
+            extra_index_urls: Some(vec![url("https://extra.com/1"), url("https://extra.com/2")]),
+            find_links: Some(vec![FindLinksUrlOrPath::Path(PathBuf::from("./links"))]),
+        };
+
+        let combined = a.union(&b).unwrap();
+        assert_eq!(combined.index_url, Some(url("https://a.com/simple")));
+        assert_eq!(
+            combined.extra_index_urls,
+            Some(vec![url("https://extra.com/1"), url("https://extra.com/2")])
+        );
+        assert_eq!(combined.find_links, b.find_links);
+
+        let c = PypiOptions {
+            index_url: Some(url("https://c.com/simple")),
+            ..Default::default()
+        };
+        assert!(a.union(&c).is_err());
+        assert!(b.union(&c).is_ok());
+    }
+}
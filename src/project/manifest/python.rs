@@ -1,14 +1,36 @@
+use crate::pypi_git::{GitReference, GitSource};
 use pep440_rs::VersionSpecifiers;
 use serde::{de, de::Error, Deserialize, Deserializer};
-use std::{fmt, fmt::Formatter, str::FromStr};
+use std::{
+    fmt,
+    fmt::Formatter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use thiserror::Error;
 use toml_edit::Item;
+use url::Url;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct PyPiRequirement {
     pub(crate) version: Option<pep440_rs::VersionSpecifiers>,
     pub(crate) extras: Option<Vec<String>>,
-    pub(crate) index: Option<String>,
+    /// The index the package is fetched from, instead of the indexes of the environment.
+    pub(crate) index: Option<Url>,
+    /// A path to a local source tree or distribution, relative to the project root.
+    pub(crate) path: Option<PathBuf>,
+    /// Whether a path dependency should be installed in editable mode.
+    pub(crate) editable: Option<bool>,
+    /// The url of a git repository that contains the source of the package.
+    pub(crate) git: Option<Url>,
+    /// The revision, e.g. a commit hash, of the git repository to use.
+    pub(crate) rev: Option<String>,
+    /// The tag of the git repository to use.
+    pub(crate) tag: Option<String>,
+    /// The branch of the git repository to use.
+    pub(crate) branch: Option<String>,
+    /// A direct url to a source or wheel distribution of the package.
+    pub(crate) url: Option<Url>,
 }
 
 /// The type of parse error that occurred when parsing match spec.
@@ -34,37 +56,62 @@ impl fmt::Display for PyPiRequirement {
 impl From<PyPiRequirement> for Item {
     /// PyPiRequirement to a toml_edit item, to put in the manifest file.
     fn from(val: PyPiRequirement) -> Item {
-        if val.extras.is_some() {
-            // If extras is defined use an inline table
-            let mut table = toml_edit::Table::new().into_inline_table();
-
-            // First add the version
-            if val.version.is_some() {
-                let v = val.version.expect("Expect a version here").to_string();
-                table.insert(
-                    "version",
-                    toml_edit::Value::String(toml_edit::Formatted::new(v)),
-                );
-            } else {
-                table.insert(
-                    "version",
-                    toml_edit::Value::String(toml_edit::Formatted::new("*".to_string())),
-                );
-            }
-            // Add extras as an array.
-            table.insert(
-                "extras",
-                toml_edit::Value::Array(toml_edit::Array::from_iter(val.extras.unwrap())),
-            );
-            Item::Value(toml_edit::Value::InlineTable(table))
-        } else {
+        if val.extras.is_none()
+            && val.index.is_none()
+            && val.path.is_none()
+            && val.git.is_none()
+            && val.url.is_none()
+        {
             // Without extras use the string representation.
-            if val.version.is_some() {
+            return if val.version.is_some() {
                 Item::Value(val.version.unwrap().to_string().into())
             } else {
                 Item::Value("*".into())
+            };
+        }
+
+        let string_value =
+            |value: String| toml_edit::Value::String(toml_edit::Formatted::new(value));
+
+        // If extras, an index or a source is defined use an inline table
+        let mut table = toml_edit::Table::new().into_inline_table();
+
+        // First add the version or the source of the package
+        if let Some(path) = val.path {
+            table.insert("path", string_value(path.to_string_lossy().into_owned()));
+        } else if let Some(git) = val.git {
+            table.insert("git", string_value(git.to_string()));
+            for (key, value) in [("rev", val.rev), ("tag", val.tag), ("branch", val.branch)] {
+                if let Some(value) = value {
+                    table.insert(key, string_value(value));
+                }
             }
+        } else if let Some(url) = val.url {
+            table.insert("url", string_value(url.to_string()));
+        } else {
+            let version = val
+                .version
+                .map_or_else(|| "*".to_string(), |v| v.to_string());
+            table.insert("version", string_value(version));
+        }
+        // Add extras as an array.
+        if let Some(extras) = val.extras {
+            table.insert(
+                "extras",
+                toml_edit::Value::Array(toml_edit::Array::from_iter(extras)),
+            );
+        }
+        // Add the index the package should be fetched from.
+        if let Some(index) = val.index {
+            table.insert("index", string_value(index.to_string()));
+        }
+        if let Some(editable) = val.editable {
+            table.insert(
+                "editable",
+                toml_edit::Value::Boolean(toml_edit::Formatted::new(editable)),
+            );
         }
+        Item::Value(toml_edit::Value::InlineTable(table))
     }
 }
 impl FromStr for PyPiRequirement {
@@ -78,11 +125,7 @@ impl FromStr for PyPiRequirement {
         }
         if s == "*" {
             // Accept a star as an any requirement, which is represented by the none.
-            Ok(Self {
-                version: None,
-                extras: None,
-                index: None,
-            })
+            Ok(Self::default())
         } else if s.starts_with(|c: char| c.is_ascii_digit()) {
             Err(ParsePyPiRequirementError::MissingOperator(s.to_string()))
         } else {
@@ -92,8 +135,7 @@ impl FromStr for PyPiRequirement {
                     pep440_rs::VersionSpecifiers::from_str(s)
                         .map_err(ParsePyPiRequirementError::Pep440Error)?,
                 ),
-                extras: None,
-                index: None,
+                ..Default::default()
             })
         }
     }
@@ -102,36 +144,128 @@ impl FromStr for PyPiRequirement {
 /// Implement from [`pep508_rs::Requirement`] to make the conversion easier.
 impl From<pep508_rs::Requirement> for PyPiRequirement {
     fn from(req: pep508_rs::Requirement) -> Self {
-        let version = if let Some(version_or_url) = req.version_or_url {
-            match version_or_url {
-                pep508_rs::VersionOrUrl::VersionSpecifier(v) => Some(v),
-                pep508_rs::VersionOrUrl::Url(_) => None,
-            }
-        } else {
-            None
-        };
-        PyPiRequirement {
-            version,
+        let mut requirement = PyPiRequirement {
             extras: req.extras,
-            index: None,
+            ..Default::default()
+        };
+        match req.version_or_url {
+            Some(pep508_rs::VersionOrUrl::VersionSpecifier(v)) => requirement.version = Some(v),
+            Some(pep508_rs::VersionOrUrl::Url(url)) => {
+                if let Some((git, rev)) = split_git_url(&url) {
+                    requirement.git = Some(git);
+                    requirement.rev = rev;
+                } else if let Ok(path) = url.to_file_path() {
+                    requirement.path = Some(path);
+                } else {
+                    requirement.url = Some(url);
+                }
+            }
+            None => {}
         }
+        requirement
     }
 }
 
+/// Splits a pip style git url, e.g. `git+https://github.com/org/repo.git@v1.0#egg=repo`, into the
+/// url of the repository and the revision. Returns `None` if the url does not refer to a git
+/// repository.
+fn split_git_url(url: &Url) -> Option<(Url, Option<String>)> {
+    let mut repository = Url::parse(url.as_str().strip_prefix("git+")?).ok()?;
+    repository.set_fragment(None);
+    let path = repository.path().to_string();
+    let rev = match path.rsplit_once('@') {
+        Some((path, rev)) => {
+            repository.set_path(path);
+            Some(rev.to_string())
+        }
+        None => None,
+    };
+    Some((repository, rev))
+}
+
 impl PyPiRequirement {
-    /// Returns the requirements as [`pep508_rs::Requirement`]s.
-    pub fn as_pep508(&self, name: &rip::types::PackageName) -> pep508_rs::Requirement {
+    /// Returns true if this requirement refers to a local path instead of a package on an index.
+    pub fn is_path(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Returns true if this requirement should be installed in editable mode.
+    pub fn is_editable(&self) -> bool {
+        self.editable.unwrap_or(false)
+    }
+
+    /// Returns the git repository and the reference within it if this requirement refers to a git
+    /// repository.
+    pub fn git_source(&self) -> Option<GitSource> {
+        let reference = match (&self.rev, &self.tag, &self.branch) {
+            (Some(rev), _, _) => GitReference::Rev(rev.clone()),
+            (_, Some(tag), _) => GitReference::Tag(tag.clone()),
+            (_, _, Some(branch)) => GitReference::Branch(branch.clone()),
+            _ => GitReference::DefaultBranch,
+        };
+        Some(GitSource {
+            repository: self.git.clone()?,
+            reference,
+        })
+    }
+
+    /// Makes the path of a path requirement relative to the `project_root` if it is located in the
+    /// project.
+    pub fn with_path_relative_to(mut self, project_root: &Path) -> Self {
+        if let Some(path) = self.path.take() {
+            self.path = Some(match path.strip_prefix(project_root) {
+                Ok(relative) => Path::new(".").join(relative),
+                Err(_) => path,
+            });
+        }
+        self
+    }
+
+    /// Returns the absolute path of a path requirement, paths are relative to the `project_root`.
+    pub fn absolute_path(&self, project_root: &Path) -> Option<PathBuf> {
+        let path = project_root.join(self.path.as_ref()?);
+        Some(dunce::canonicalize(&path).unwrap_or(path))
+    }
+
+    /// Returns the requirements as [`pep508_rs::Requirement`]s. Path requirements are converted to
+    /// `file://` urls, relative paths are resolved against the `project_root`. Git requirements are
+    /// converted to pip style `git+` urls.
+    pub fn as_pep508(
+        &self,
+        name: &rip::types::PackageName,
+        project_root: &Path,
+    ) -> pep508_rs::Requirement {
+        let version_or_url = if let Some(path) = self.absolute_path(project_root) {
+            Url::from_file_path(path)
+                .ok()
+                .map(pep508_rs::VersionOrUrl::Url)
+        } else if let Some(git) = &self.git {
+            let rev = self
+                .rev
+                .as_ref()
+                .or(self.tag.as_ref())
+                .or(self.branch.as_ref());
+            let url = match rev {
+                Some(rev) => format!("git+{git}@{rev}"),
+                None => format!("git+{git}"),
+            };
+            Url::parse(&url).ok().map(pep508_rs::VersionOrUrl::Url)
+        } else if let Some(url) = &self.url {
+            Some(pep508_rs::VersionOrUrl::Url(url.clone()))
+        } else {
+            self.version
+                .clone()
+                .map(pep508_rs::VersionOrUrl::VersionSpecifier)
+        };
         pep508_rs::Requirement {
             name: name.as_str().to_string(),
             extras: self.extras.clone(),
-            version_or_url: self
-                .version
-                .clone()
-                .map(pep508_rs::VersionOrUrl::VersionSpecifier),
+            version_or_url,
             marker: None,
         }
     }
 }
+
 impl<'de> Deserialize<'de> for PyPiRequirement {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -145,10 +279,49 @@ impl<'de> Deserialize<'de> for PyPiRequirement {
                 struct RawPyPiRequirement {
                     version: Option<String>,
                     extras: Option<Vec<String>>,
-                    index: Option<String>,
+                    index: Option<Url>,
+                    path: Option<PathBuf>,
+                    editable: Option<bool>,
+                    git: Option<Url>,
+                    rev: Option<String>,
+                    tag: Option<String>,
+                    branch: Option<String>,
+                    url: Option<Url>,
                 }
                 let raw_requirement =
                     RawPyPiRequirement::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                let sources = [
+                    raw_requirement.version.is_some(),
+                    raw_requirement.path.is_some(),
+                    raw_requirement.git.is_some(),
+                    raw_requirement.url.is_some(),
+                ];
+                if sources.into_iter().filter(|&source| source).count() > 1 {
+                    return Err(Error::custom(
+                        "only one of `version`, `path`, `git` or `url` can be specified",
+                    ));
+                }
+                if raw_requirement.editable.is_some() && raw_requirement.path.is_none() {
+                    return Err(Error::custom(
+                        "`editable` is only supported for `path` dependencies",
+                    ));
+                }
+                let references = [
+                    raw_requirement.rev.is_some(),
+                    raw_requirement.tag.is_some(),
+                    raw_requirement.branch.is_some(),
+                ];
+                let reference_count = references.into_iter().filter(|&r| r).count();
+                if reference_count > 0 && raw_requirement.git.is_none() {
+                    return Err(Error::custom(
+                        "`rev`, `tag` and `branch` are only supported for `git` dependencies",
+                    ));
+                }
+                if reference_count > 1 {
+                    return Err(Error::custom(
+                        "only one of `rev`, `tag` or `branch` can be specified",
+                    ));
+                }
                 // Parse the * in version or allow for no version with extras.
                 let mut version = None;
                 if let Some(raw_version) = raw_requirement.version {
@@ -163,6 +336,13 @@ impl<'de> Deserialize<'de> for PyPiRequirement {
                     version,
                     extras: raw_requirement.extras,
                     index: raw_requirement.index,
+                    path: raw_requirement.path,
+                    editable: raw_requirement.editable,
+                    git: raw_requirement.git,
+                    rev: raw_requirement.rev,
+                    tag: raw_requirement.tag,
+                    branch: raw_requirement.branch,
+                    url: raw_requirement.url,
                 })
             })
             .expecting("either a map or a string")
@@ -186,6 +366,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pypi_with_index_to_string() {
+        let req = PyPiRequirement {
+            index: Some(Url::parse("https://download.pytorch.org/whl/cu121").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            req.to_string(),
+            "{ version = \"*\", index = \"https://download.pytorch.org/whl/cu121\" }"
+        );
+    }
+
     #[test]
     fn test_only_version() {
         let requirement: IndexMap<rip::types::PackageName, PyPiRequirement> =
@@ -198,8 +390,7 @@ mod tests {
             requirement.first().unwrap().1,
             &PyPiRequirement {
                 version: Some(pep440_rs::VersionSpecifiers::from_str(">=3.12").unwrap()),
-                extras: None,
-                index: None,
+                ..Default::default()
             }
         );
         let requirement: IndexMap<rip::types::PackageName, PyPiRequirement> =
@@ -208,8 +399,7 @@ mod tests {
             requirement.first().unwrap().1,
             &PyPiRequirement {
                 version: Some(pep440_rs::VersionSpecifiers::from_str("==3.12.0").unwrap()),
-                extras: None,
-                index: None,
+                ..Default::default()
             }
         );
 
@@ -219,21 +409,13 @@ mod tests {
             requirement.first().unwrap().1,
             &PyPiRequirement {
                 version: Some(pep440_rs::VersionSpecifiers::from_str("~=2.1.3").unwrap()),
-                extras: None,
-                index: None,
+                ..Default::default()
             }
         );
 
         let requirement: IndexMap<rip::types::PackageName, PyPiRequirement> =
             toml_edit::de::from_str(r#"foo = "*""#).unwrap();
-        assert_eq!(
-            requirement.first().unwrap().1,
-            &PyPiRequirement {
-                version: None,
-                extras: None,
-                index: None,
-            }
-        );
+        assert_eq!(requirement.first().unwrap().1, &PyPiRequirement::default());
     }
 
     #[test]
@@ -241,7 +423,7 @@ mod tests {
         let requirement: IndexMap<rip::types::PackageName, PyPiRequirement> =
             toml_edit::de::from_str(
                 r#"
-                foo = { version=">=3.12", extras = ["bar"], index = "artifact-registry" }
+                foo = { version=">=3.12", extras = ["bar"], index = "https://example.com/simple" }
                 "#,
             )
             .unwrap();
@@ -255,7 +437,8 @@ mod tests {
             &PyPiRequirement {
                 version: Some(pep440_rs::VersionSpecifiers::from_str(">=3.12").unwrap()),
                 extras: Some(vec!("bar".to_string())),
-                index: Some("artifact-registry".to_string()),
+                index: Some(Url::parse("https://example.com/simple").unwrap()),
+                ..Default::default()
             }
         );
 
@@ -273,7 +456,7 @@ mod tests {
             &PyPiRequirement {
                 version: Some(pep440_rs::VersionSpecifiers::from_str(">=3.12,<3.13.0").unwrap()),
                 extras: Some(vec!("bar".to_string(), "foo".to_string())),
-                index: None,
+                ..Default::default()
             }
         );
     }
@@ -294,7 +477,7 @@ mod tests {
             PyPiRequirement {
                 version: VersionSpecifiers::from_str("==1.2.3").ok(),
                 extras: Some(vec!["feature1".to_owned(), "feature2".to_owned()]),
-                index: None,
+                ..Default::default()
             }
         );
     }
@@ -309,8 +492,7 @@ mod tests {
             pypi_requirement,
             PyPiRequirement {
                 version: VersionSpecifiers::from_str("==1.2.3").ok(),
-                extras: None,
-                index: None,
+                ..Default::default()
             }
         );
     }
@@ -321,13 +503,159 @@ mod tests {
         let result: Result<PyPiRequirement, _> = serde_json::from_str(json_string);
         assert!(result.is_ok());
         let pypi_requirement: PyPiRequirement = result.unwrap();
+        assert_eq!(pypi_requirement, PyPiRequirement::default());
+    }
+
+    #[test]
+    fn test_path_requirement() {
+        let requirement: IndexMap<rip::types::PackageName, PyPiRequirement> =
+            toml_edit::de::from_str(r#"mylib = { path = "./libs/mylib", editable = true }"#)
+                .unwrap();
+        let requirement = requirement.first().unwrap().1;
         assert_eq!(
-            pypi_requirement,
-            PyPiRequirement {
-                version: None,
-                extras: None,
-                index: None,
+            requirement,
+            &PyPiRequirement {
+                path: Some(PathBuf::from("./libs/mylib")),
+                editable: Some(true),
+                ..Default::default()
             }
         );
+        assert!(requirement.is_editable());
+        assert_eq!(
+            requirement.to_string(),
+            "{ path = \"./libs/mylib\", editable = true }"
+        );
+
+        let root = std::env::temp_dir();
+        let pep508 =
+            requirement.as_pep508(&rip::types::PackageName::from_str("mylib").unwrap(), &root);
+        assert_eq!(
+            pep508.version_or_url,
+            Some(pep508_rs::VersionOrUrl::Url(
+                Url::from_file_path(requirement.absolute_path(&root).unwrap()).unwrap()
+            ))
+        );
+
+        assert!(
+            toml_edit::de::from_str::<IndexMap<rip::types::PackageName, PyPiRequirement>>(
+                r#"mylib = { path = "./libs/mylib", version = "==1.0" }"#
+            )
+            .is_err()
+        );
+        assert!(
+            toml_edit::de::from_str::<IndexMap<rip::types::PackageName, PyPiRequirement>>(
+                r#"mylib = { version = "==1.0", editable = true }"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_git_requirement() {
+        let requirement: IndexMap<rip::types::PackageName, PyPiRequirement> =
+            toml_edit::de::from_str(
+                r#"foo = { git = "https://github.com/org/foo.git", tag = "v1.0", extras = ["bar"] }"#,
+            )
+            .unwrap();
+        let requirement = requirement.first().unwrap().1;
+        assert_eq!(
+            requirement.git_source(),
+            Some(GitSource {
+                repository: Url::parse("https://github.com/org/foo.git").unwrap(),
+                reference: GitReference::Tag("v1.0".to_string()),
+            })
+        );
+        assert_eq!(
+            requirement.to_string(),
+            "{ git = \"https://github.com/org/foo.git\", tag = \"v1.0\", extras = [\"bar\"] }"
+        );
+        let pep508 = requirement.as_pep508(
+            &rip::types::PackageName::from_str("foo").unwrap(),
+            &std::env::temp_dir(),
+        );
+        assert_eq!(
+            pep508.to_string(),
+            "foo[bar] @ git+https://github.com/org/foo.git@v1.0"
+        );
+        assert_eq!(PyPiRequirement::from(pep508).git_source().unwrap(), {
+            GitSource {
+                repository: Url::parse("https://github.com/org/foo.git").unwrap(),
+                reference: GitReference::Rev("v1.0".to_string()),
+            }
+        });
+
+        let pypi = PyPiRequirement::from(
+            pep508_rs::Requirement::from_str(
+                "foo @ git+https://git@github.com/org/foo.git#egg=foo",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            pypi.to_string(),
+            "{ git = \"https://git@github.com/org/foo.git\" }"
+        );
+
+        let invalid = [
+            r#"foo = { git = "https://github.com/org/foo.git", version = "==1.0" }"#,
+            r#"foo = { git = "https://github.com/org/foo.git", tag = "v1.0", branch = "main" }"#,
+            r#"foo = { version = "==1.0", rev = "abcdef" }"#,
+            r#"foo = { git = "https://github.com/org/foo.git", editable = true }"#,
+        ];
+        for invalid in invalid {
+            assert!(
+                toml_edit::de::from_str::<IndexMap<rip::types::PackageName, PyPiRequirement>>(
+                    invalid
+                )
+                .is_err(),
+                "{invalid} should not be valid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_url_requirement() {
+        let requirement: IndexMap<rip::types::PackageName, PyPiRequirement> =
+            toml_edit::de::from_str(
+                r#"foo = { url = "https://example.com/foo-1.0-py3-none-any.whl" }"#,
+            )
+            .unwrap();
+        let requirement = requirement.first().unwrap().1;
+        let url = Url::parse("https://example.com/foo-1.0-py3-none-any.whl").unwrap();
+        assert_eq!(requirement.url, Some(url.clone()));
+        assert_eq!(
+            requirement.to_string(),
+            "{ url = \"https://example.com/foo-1.0-py3-none-any.whl\" }"
+        );
+
+        let pep508 = requirement.as_pep508(
+            &rip::types::PackageName::from_str("foo").unwrap(),
+            &std::env::temp_dir(),
+        );
+        assert_eq!(
+            pep508.version_or_url,
+            Some(pep508_rs::VersionOrUrl::Url(url))
+        );
+        assert_eq!(&PyPiRequirement::from(pep508), requirement);
+
+        // A `file://` url is converted to a path dependency, which is made relative to the project.
+        let root = std::env::temp_dir().join("project");
+        let pep508 = pep508_rs::Requirement::from_str(&format!(
+            "foo @ {}",
+            Url::from_directory_path(root.join("foo")).unwrap()
+        ))
+        .unwrap();
+        let requirement = PyPiRequirement::from(pep508);
+        assert_eq!(requirement.path, Some(root.join("foo")));
+        assert_eq!(
+            requirement.with_path_relative_to(&root).path,
+            Some(Path::new(".").join("foo"))
+        );
+
+        assert!(
+            toml_edit::de::from_str::<IndexMap<rip::types::PackageName, PyPiRequirement>>(
+                r#"foo = { url = "https://example.com/foo-1.0.tar.gz", path = "./foo" }"#
+            )
+            .is_err()
+        );
     }
 }
@@ -1,4 +1,4 @@
-use crate::project::manifest::{Environment, FeatureName, SystemRequirements};
+use crate::project::manifest::{Environment, FeatureName, PypiOptions, SystemRequirements};
 use crate::{
     consts,
     project::manifest::{Feature, ProjectManifest, TargetSelector},
@@ -132,6 +132,80 @@ impl ProjectManifest {
             }
         }
 
+        // Check that the environments that task dependencies should run in exist.
+        for task in self
+            .features
+            .values()
+            .flat_map(|feature| feature.targets.targets())
+            .flat_map(|target| target.tasks.values())
+        {
+            for dependency in task.depends_on() {
+                if let Some(environment) = &dependency.environment {
+                    if self.environments.find(environment).is_none() {
+                        return Err(miette::miette!(
+                            "the task '{}' should run in the environment '{}', which does not exist",
+                            dependency.task_name.fancy_display(),
+                            environment.fancy_display()
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Check that the environments that tasks prefer to run in exist and contain the tasks.
+        for feature in self.features.values() {
+            let task_environments = feature
+                .targets
+                .targets()
+                .flat_map(|target| target.tasks.iter())
+                .filter_map(|(name, task)| Some((Some(name), task.environment()?)))
+                .chain(feature.task_environment.iter().map(|env| (None, env)));
+            for (task_name, environment_name) in task_environments {
+                let contains_feature = self.environments.find(environment_name).map(|env| {
+                    feature.is_default() || env.features.iter().any(|f| f == feature.name.as_str())
+                });
+                let owner = match task_name {
+                    Some(task_name) => format!("task '{}'", task_name.fancy_display()),
+                    None => format!("tasks of the feature '{}'", feature.name.fancy_display()),
+                };
+                match contains_feature {
+                    None => {
+                        return Err(miette::miette!(
+                            "the {owner} should run in the environment '{}', which does not exist",
+                            environment_name.fancy_display()
+                        ))
+                    }
+                    Some(false) => {
+                        return Err(miette::miette!(
+                            "the {owner} should run in the environment '{}', which does not include the feature '{}'",
+                            environment_name.fancy_display(),
+                            feature.name.fancy_display()
+                        ))
+                    }
+                    Some(true) => {}
+                }
+            }
+        }
+
+        // Environments in a solve group are solved together so they have to agree on the pypi
+        // options.
+        for solve_group in self.solve_groups.iter() {
+            if let Err(e) = solve_group
+                .environments
+                .iter()
+                .flat_map(|idx| self.environments.environments[*idx].features.iter())
+                .filter_map(|name| self.features.get(&FeatureName::Named(name.clone())))
+                .chain([self.default_feature()])
+                .filter_map(|feature| feature.pypi_options.as_ref())
+                .try_fold(PypiOptions::default(), |acc, opts| acc.union(opts))
+            {
+                return Err(miette::miette!(
+                    "{e}, while resolving the pypi options of the solve-group '{}'",
+                    solve_group.name
+                ));
+            }
+        }
+
         Ok(())
     }
 
@@ -187,6 +261,24 @@ impl ProjectManifest {
             ));
         }
 
+        // Check if there are conflicts in the pypi options between features, this includes the
+        // default feature because that is always part of the environment.
+        if let Err(e) = features
+            .iter()
+            .copied()
+            .chain([self.default_feature()])
+            .filter_map(|feature| feature.pypi_options.as_ref())
+            .try_fold(PypiOptions::default(), |acc, opts| acc.union(opts))
+        {
+            return Err(miette::miette!(
+                labels = vec![LabeledSpan::at(
+                    env.features_source_loc.clone().unwrap_or_default(),
+                    "while resolving pypi options of features defined here"
+                )],
+                "{e}",
+            ));
+        }
+
         Ok(())
     }
 }
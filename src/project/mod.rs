@@ -9,27 +9,31 @@ pub mod virtual_packages;
 use async_once_cell::OnceCell as AsyncCell;
 use indexmap::{Equivalent, IndexMap, IndexSet};
 use miette::{IntoDiagnostic, NamedSource, WrapErr};
-use once_cell::sync::OnceCell;
 
 use rattler_conda_types::{Channel, GenericVirtualPackage, Platform, Version};
 use rattler_networking::AuthenticationMiddleware;
 use reqwest_middleware::ClientWithMiddleware;
-use rip::index::PackageSources;
-use rip::{index::PackageDb, normalize_index_url};
+use rip::{
+    index::{PackageDb, PackageSourcesBuilder},
+    normalize_index_url,
+    types::NormalizedPackageName,
+};
 use std::hash::Hash;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{btree_map::Entry, BTreeMap, HashMap, HashSet},
     env,
     ffi::OsStr,
     fmt::{Debug, Formatter},
     fs,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use crate::activation::{get_environment_variables, run_activation};
 use crate::project::grouped_environment::GroupedEnvironment;
+use crate::pypi_find_links::FindLinksMiddleware;
 use crate::task::TaskName;
+use crate::utils::env_file::read_env_files;
 use crate::{
     config,
     consts::{self, PROJECT_MANIFEST},
@@ -37,12 +41,15 @@ use crate::{
 };
 pub use dependencies::Dependencies;
 pub use environment::Environment;
-use manifest::{EnvironmentName, Manifest, PyPiRequirement, SystemRequirements};
+use manifest::{EnvironmentName, Manifest, PyPiRequirement, PypiOptions, SystemRequirements};
 pub use solve_group::SolveGroup;
 use url::Url;
 
 use self::manifest::Environments;
 
+/// The pypi options and per-package index overrides a [`PackageDb`] was created for.
+type PackageDbKey = (PypiOptions, BTreeMap<NormalizedPackageName, Url>);
+
 /// The dependency types we support
 #[derive(Debug, Copy, Clone)]
 pub enum DependencyType {
@@ -94,8 +101,8 @@ impl SpecType {
 pub struct Project {
     /// Root folder of the project
     root: PathBuf,
-    /// The PyPI package db for this project
-    package_db: OnceCell<Arc<PackageDb>>,
+    /// The PyPI package dbs for this project, one for every distinct set of package sources
+    package_dbs: Arc<Mutex<HashMap<PackageDbKey, Arc<PackageDb>>>>,
     /// Reqwest client shared for this project
     client: reqwest::Client,
     /// Authenticated reqwest client shared for this project
@@ -127,7 +134,7 @@ impl Project {
 
         Self {
             root: manifest.path.parent().unwrap_or(Path::new("")).to_owned(),
-            package_db: Default::default(),
+            package_dbs: Default::default(),
             client,
             authenticated_client,
             manifest,
@@ -196,7 +203,7 @@ impl Project {
 
         Ok(Self {
             root: root.to_owned(),
-            package_db: Default::default(),
+            package_dbs: Default::default(),
             client: Default::default(),
             authenticated_client: reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
                 .with_arc(Arc::new(AuthenticationMiddleware::default()))
@@ -250,6 +257,17 @@ impl Project {
         self.pixi_dir().join(consts::SOLVE_GROUP_ENVIRONMENTS_DIR)
     }
 
+    /// Returns the directory that stores the cached state of tasks
+    pub fn task_cache_dir(&self) -> PathBuf {
+        self.pixi_dir().join(consts::TASK_CACHE_DIR)
+    }
+
+    /// Returns the directory that stores the scripts of tasks that are run by an interpreter that
+    /// only reads arguments for a script from a file.
+    pub fn task_scripts_dir(&self) -> PathBuf {
+        self.pixi_dir().join(consts::TASK_SCRIPTS_DIR)
+    }
+
     /// Returns the path to the manifest file.
     pub fn manifest_path(&self) -> PathBuf {
         self.manifest.path.clone()
@@ -405,25 +423,63 @@ impl Project {
         self.manifest.has_pypi_dependencies()
     }
 
-    /// Returns the Python index URLs to use for this project.
-    pub fn pypi_index_url(&self) -> Url {
-        normalize_index_url(Url::parse("https://pypi.org/simple/").unwrap())
-    }
-
     /// Returns the package database used for caching python metadata, wheels and more. See the
     /// documentation of [`rip::index::PackageDb`] for more information.
-    pub fn pypi_package_db(&self) -> miette::Result<Arc<PackageDb>> {
-        Ok(self
-            .package_db
-            .get_or_try_init(|| {
-                PackageDb::new(
-                    PackageSources::from(self.pypi_index_url()),
-                    self.authenticated_client().clone(),
-                    &config::get_cache_dir()?.join("pypi/"),
-                )
-                .map(Arc::new)
-            })?
-            .clone())
+    ///
+    /// The package database is created from the given `pypi_options`. Packages in `dependencies`
+    /// that specify an `index` are only looked up on that index. The database is shared between all
+    /// environments that use the same package sources.
+    pub fn pypi_package_db(
+        &self,
+        pypi_options: &PypiOptions,
+        dependencies: &IndexMap<rip::types::PackageName, Vec<PyPiRequirement>>,
+    ) -> miette::Result<Arc<PackageDb>> {
+        let key = (pypi_options.clone(), pypi_index_overrides(dependencies)?);
+
+        let mut package_dbs = self
+            .package_dbs
+            .lock()
+            .expect("the package db cache is poisoned");
+        if let Some(package_db) = package_dbs.get(&key) {
+            return Ok(package_db.clone());
+        }
+
+        let (pypi_options, index_overrides) = &key;
+
+        // The indexes that packages are pinned to have to be registered as extra indexes before
+        // packages can be mapped to them.
+        let extra_index_urls = pypi_options
+            .extra_index_urls()
+            .chain(index_overrides.values().cloned())
+            .collect::<IndexSet<_>>();
+        // The flat indexes are served as extra indexes by a middleware of the http client, they
+        // are searched after the other indexes.
+        let find_links = FindLinksMiddleware::new(pypi_options.find_links(), self.root());
+        let builder = extra_index_urls
+            .iter()
+            .chain(find_links.index_urls().iter())
+            .fold(
+                PackageSourcesBuilder::new(pypi_options.index_url()),
+                |builder, url| builder.with_index(url.as_str(), url),
+            );
+        let package_sources = index_overrides
+            .iter()
+            .fold(builder, |builder, (name, url)| {
+                builder.with_override(name.clone(), url.as_str())
+            })
+            .build()
+            .into_diagnostic()?;
+
+        let package_db = Arc::new(PackageDb::new(
+            package_sources,
+            reqwest_middleware::ClientBuilder::new(self.client().clone())
+                .with(find_links)
+                .with_arc(Arc::new(AuthenticationMiddleware::default()))
+                .build(),
+            &config::get_cache_dir()?.join("pypi/"),
+        )?);
+        package_dbs.insert(key, package_db.clone());
+        Ok(package_db)
     }
 
     /// Returns the reqwest client used for http networking
@@ -438,7 +494,8 @@ impl Project {
     }
 
     /// Return a combination of static environment variables generated from the project and the environment
-    /// and from running activation script
+    /// and from running activation script. The variables of the env files of the environment are
+    /// set on top of these.
     pub async fn get_env_variables(
         &self,
         environment: &Environment<'_>,
@@ -455,16 +512,43 @@ impl Project {
 
             let environment_variables = get_environment_variables(environment);
 
-            let all_variables: HashMap<String, String> = activation_env
+            let mut all_variables: HashMap<String, String> = activation_env
                 .into_iter()
                 .chain(environment_variables.into_iter())
                 .collect();
+            read_env_files(environment.env_files(), &mut all_variables)?;
             Ok(all_variables)
         })
         .await
     }
 }
 
+/// Returns the indexes that pypi packages are pinned to through the `index` field of their
+/// requirements.
+pub(crate) fn pypi_index_overrides(
+    dependencies: &IndexMap<rip::types::PackageName, Vec<PyPiRequirement>>,
+) -> miette::Result<BTreeMap<NormalizedPackageName, Url>> {
+    let mut overrides = BTreeMap::new();
+    for (name, requirements) in dependencies {
+        for index in requirements.iter().filter_map(|req| req.index.clone()) {
+            let url = normalize_index_url(index);
+            match overrides.entry(NormalizedPackageName::from(name.clone())) {
+                Entry::Vacant(entry) => {
+                    entry.insert(url);
+                }
+                Entry::Occupied(entry) if entry.get() != &url => miette::bail!(
+                    "the pypi dependency '{}' is pinned to multiple indexes, found both '{}' and '{}'",
+                    name.as_source_str(),
+                    entry.get(),
+                    url
+                ),
+                Entry::Occupied(_) => {}
+            }
+        }
+    }
+    Ok(overrides)
+}
+
 /// Iterates over the current directory and all its parent directories and returns the first
 /// directory path that contains the [`consts::PROJECT_MANIFEST`].
 pub fn find_project_root() -> Option<PathBuf> {
@@ -659,4 +743,46 @@ mod tests {
             .tasks(Some(Platform::Linux64), &FeatureName::Default)
             .unwrap());
     }
+
+    #[test]
+    fn test_pypi_index_overrides() {
+        let file_contents = r#"
+            [pypi-dependencies]
+            torch = { version = "*", index = "https://download.pytorch.org/whl/cu121" }
+            requests = "*"
+            "#;
+        let project = Project::from_str(
+            Path::new(""),
+            format!("{PROJECT_BOILERPLATE}\n{file_contents}").as_str(),
+        )
+        .unwrap();
+
+        let overrides =
+            pypi_index_overrides(&project.default_environment().pypi_dependencies(None)).unwrap();
+        assert_eq!(
+            overrides
+                .iter()
+                .map(|(name, url)| format!("{name}: {url}"))
+                .collect_vec(),
+            vec!["torch: https://download.pytorch.org/whl/cu121/"]
+        );
+
+        let file_contents = r#"
+            [pypi-dependencies]
+            torch = { version = "*", index = "https://download.pytorch.org/whl/cu121" }
+
+            [feature.cpu.pypi-dependencies]
+            torch = { version = "*", index = "https://download.pytorch.org/whl/cpu" }
+
+            [environments]
+            cpu = ["cpu"]
+            "#;
+        let project = Project::from_str(
+            Path::new(""),
+            format!("{PROJECT_BOILERPLATE}\n{file_contents}").as_str(),
+        )
+        .unwrap();
+        let cpu_env = project.environment("cpu").unwrap();
+        assert!(pypi_index_overrides(&cpu_env.pypi_dependencies(None)).is_err());
+    }
 }
@@ -1,5 +1,5 @@
 use super::{manifest, Dependencies, Environment, Project};
-use crate::project::manifest::{PyPiRequirement, SystemRequirements};
+use crate::project::manifest::{PyPiRequirement, PypiOptions, SystemRequirements};
 use crate::{FeatureName, SpecType};
 use indexmap::{IndexMap, IndexSet};
 use itertools::{Either, Itertools};
@@ -92,6 +92,19 @@ impl<'p> SolveGroup<'p> {
             })
     }
 
+    /// Returns the pypi options for this solve group.
+    ///
+    /// The pypi options of all the features of all the environments in the solve group are
+    /// combined.
+    pub fn pypi_options(&self) -> PypiOptions {
+        self.features(true)
+            .filter_map(|feature| feature.pypi_options.as_ref())
+            .fold(PypiOptions::default(), |acc, opts| {
+                acc.union(opts)
+                    .expect("pypi options should have been validated upfront")
+            })
+    }
+
     /// Returns all the dependencies of the solve group.
     ///
     /// The dependencies of all features of all environments are combined. This means that if two
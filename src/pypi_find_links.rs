@@ -0,0 +1,356 @@
+//! Flat indexes (`find-links`) are directories or html pages that link to the distributions of
+//! packages. The pypi resolver only understands indexes that implement the simple repository API,
+//! so the flat indexes are served to it as such indexes by a middleware of the http client.
+//!
+//! Every flat index gets a url with the [`FIND_LINKS_SCHEME`] that is added to the package sources
+//! as an extra index. The middleware answers the requests for the page of a package below that url
+//! with the links of the flat index that refer to the package. The distributions in local
+//! directories are served by the middleware as well, because the http client cannot read `file://`
+//! urls.
+
+use crate::project::manifest::FindLinksUrlOrPath;
+use async_trait::async_trait;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use rattler_digest::{compute_file_digest, Sha256};
+use regex::Regex;
+use reqwest::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    Method, Request, Response, ResponseBuilderExt, StatusCode,
+};
+use reqwest_middleware::{Middleware, Next};
+use rip::types::{ArtifactName, NormalizedPackageName};
+use std::{path::Path, str::FromStr};
+use task_local_extensions::Extensions;
+use url::Url;
+
+/// The scheme of the urls that are handled by the [`FindLinksMiddleware`].
+const FIND_LINKS_SCHEME: &str = "pixi-find-links";
+
+/// The host of the urls that refer to local directories and the distributions in them.
+const LOCAL_HOST: &str = "local";
+
+/// The host of the urls that refer to html pages, by their position in the flat indexes.
+const PAGE_HOST: &str = "page";
+
+lazy_static! {
+    static ref ANCHOR_REGEX: Regex = Regex::new(r"(?is)<a\s[^>]*>").unwrap();
+    static ref HREF_REGEX: Regex =
+        Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+}
+
+/// A middleware that serves flat indexes as indexes that implement the simple repository API.
+#[derive(Debug, Clone)]
+pub struct FindLinksMiddleware {
+    /// The flat indexes, with absolute paths.
+    find_links: Vec<FindLinksUrlOrPath>,
+}
+
+impl FindLinksMiddleware {
+    /// Constructs a middleware for the `find_links`, relative paths are resolved against the
+    /// `project_root`.
+    pub fn new<'a>(
+        find_links: impl IntoIterator<Item = &'a FindLinksUrlOrPath>,
+        project_root: &Path,
+    ) -> Self {
+        let find_links = find_links
+            .into_iter()
+            .map(|find_links| match find_links {
+                FindLinksUrlOrPath::Path(path) => {
+                    let path = project_root.join(path);
+                    FindLinksUrlOrPath::Path(dunce::canonicalize(&path).unwrap_or(path))
+                }
+                FindLinksUrlOrPath::Url(url) => FindLinksUrlOrPath::Url(url.clone()),
+            })
+            .collect();
+        Self { find_links }
+    }
+
+    /// Returns the urls of the simple indexes that serve the flat indexes, in the order of the
+    /// flat indexes.
+    pub fn index_urls(&self) -> Vec<Url> {
+        self.find_links
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, find_links)| match find_links {
+                FindLinksUrlOrPath::Path(path) => Url::from_directory_path(path)
+                    .ok()
+                    .map(|url| local_url(&url)),
+                FindLinksUrlOrPath::Url(_) => {
+                    Url::parse(&format!("{FIND_LINKS_SCHEME}://{PAGE_HOST}/{idx}/")).ok()
+                }
+            })
+            .collect()
+    }
+
+    /// Returns true if `path` is one of the local directories of the flat indexes.
+    fn is_directory(&self, path: &Path) -> bool {
+        self.find_links
+            .iter()
+            .any(|find_links| matches!(find_links, FindLinksUrlOrPath::Path(dir) if dir == path))
+    }
+
+    /// Serves the page of a package, or a distribution, of a local directory.
+    fn serve_directory(&self, url: &Url, method: &Method) -> std::io::Result<Response> {
+        let Some(path) = local_file_url(url).and_then(|url| url.to_file_path().ok()) else {
+            return Ok(status_response(url, StatusCode::NOT_FOUND));
+        };
+        let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+            return Ok(status_response(url, StatusCode::NOT_FOUND));
+        };
+        if !self.is_directory(dir) {
+            return Ok(status_response(url, StatusCode::NOT_FOUND));
+        }
+        if method != Method::GET {
+            return Ok(status_response(url, StatusCode::METHOD_NOT_ALLOWED));
+        }
+
+        // The url of the page of a package ends with a slash, the url of a distribution doesn't.
+        if !url.path().ends_with('/') {
+            return if path.is_file() {
+                Ok(response(
+                    url,
+                    "application/octet-stream",
+                    std::fs::read(&path)?,
+                ))
+            } else {
+                Ok(status_response(url, StatusCode::NOT_FOUND))
+            };
+        }
+
+        let Ok(name) = NormalizedPackageName::from_str(&file_name.to_string_lossy()) else {
+            return Ok(status_response(url, StatusCode::NOT_FOUND));
+        };
+        let mut links = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !path.is_file() || ArtifactName::from_filename(&file_name, None, &name).is_err() {
+                continue;
+            }
+            let Ok(file_url) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let hash = compute_file_digest::<Sha256>(&path)?;
+            links.push(format!(
+                "<a href=\"{}#sha256={hash:x}\">{file_name}</a>",
+                local_url(&file_url)
+            ));
+        }
+        links.sort();
+        Ok(response(url, "text/html", simple_page(&links)))
+    }
+
+    /// Serves the page of a package of an html page by fetching the html page and only keeping the
+    /// links to the distributions of the package.
+    async fn serve_page(
+        &self,
+        url: &Url,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let segments = url.path_segments().into_iter().flatten().collect_vec();
+        let (Some(page), Some(name)) = (
+            segments
+                .first()
+                .and_then(|idx| idx.parse::<usize>().ok())
+                .and_then(|idx| match self.find_links.get(idx) {
+                    Some(FindLinksUrlOrPath::Url(page)) => Some(page),
+                    _ => None,
+                }),
+            segments
+                .get(1)
+                .and_then(|name| NormalizedPackageName::from_str(name).ok()),
+        ) else {
+            return Ok(status_response(url, StatusCode::NOT_FOUND));
+        };
+
+        let page_response = next
+            .run(Request::new(Method::GET, page.clone()), extensions)
+            .await?
+            .error_for_status()?;
+        let base = page_response.url().clone();
+        let body = page_response.text().await?;
+        let links = page_links(&base, &body, &name);
+        Ok(response(url, "text/html", simple_page(&links)))
+    }
+}
+
+#[async_trait]
+impl Middleware for FindLinksMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if req.url().scheme() != FIND_LINKS_SCHEME {
+            return next.run(req, extensions).await;
+        }
+        let url = req.url().clone();
+        match url.host_str() {
+            Some(LOCAL_HOST) => self
+                .serve_directory(&url, req.method())
+                .map_err(|err| reqwest_middleware::Error::Middleware(err.into())),
+            Some(PAGE_HOST) => self.serve_page(&url, extensions, next).await,
+            _ => Ok(status_response(&url, StatusCode::NOT_FOUND)),
+        }
+    }
+}
+
+/// Converts a url of a distribution in a local directory of a flat index back into a `file://`
+/// url. Returns `None` for other urls.
+pub(crate) fn local_file_url(url: &Url) -> Option<Url> {
+    if url.scheme() != FIND_LINKS_SCHEME || url.host_str() != Some(LOCAL_HOST) {
+        return None;
+    }
+    Url::parse(&format!("file://{}", url.path())).ok()
+}
+
+/// The inverse of [`local_file_url`].
+fn local_url(file_url: &Url) -> Url {
+    Url::parse(&format!(
+        "{FIND_LINKS_SCHEME}://{LOCAL_HOST}{}",
+        file_url.path()
+    ))
+    .expect("a file url should always form a valid url")
+}
+
+/// Returns the anchors of the html page `body` that link to the distributions of the package with
+/// the given `name`, with their links resolved against the `base` url of the page.
+fn page_links(base: &Url, body: &str, name: &NormalizedPackageName) -> Vec<String> {
+    ANCHOR_REGEX
+        .find_iter(body)
+        .filter_map(|anchor| {
+            let anchor = anchor.as_str();
+            let href = HREF_REGEX.captures(anchor)?;
+            let href_match = href.get(1).or_else(|| href.get(2))?;
+            let url = base.join(href_match.as_str()).ok()?;
+            let file_name = url.path_segments()?.last()?;
+            ArtifactName::from_filename(file_name, None, name).ok()?;
+            let href = href.get(0)?;
+            Some(format!(
+                "{}href=\"{url}\"{}</a>",
+                &anchor[..href.start()],
+                &anchor[href.end()..]
+            ))
+        })
+        .collect()
+}
+
+/// Returns a simple repository API page with the given `links`.
+fn simple_page(links: &[String]) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<body>\n{}\n</body>\n</html>\n",
+        links.join("<br/>\n")
+    )
+}
+
+/// Returns a response for `url` with the given body. Pixi serves these responses itself, so they
+/// are never stored in the http cache.
+fn response(url: &Url, content_type: &str, body: impl Into<reqwest::Body>) -> Response {
+    http::Response::builder()
+        .url(url.clone())
+        .header(CONTENT_TYPE, content_type)
+        .header(CACHE_CONTROL, "no-store")
+        .body(body.into())
+        .expect("the response should always be valid")
+        .into()
+}
+
+/// Returns an empty response for `url` with the given `status`.
+fn status_response(url: &Url, status: StatusCode) -> Response {
+    http::Response::builder()
+        .url(url.clone())
+        .status(status)
+        .header(CACHE_CONTROL, "no-store")
+        .body(reqwest::Body::from(Vec::new()))
+        .expect("the response should always be valid")
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn client(middleware: FindLinksMiddleware) -> reqwest_middleware::ClientWithMiddleware {
+        reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(middleware)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_serve_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("links")).unwrap();
+        std::fs::write(dir.path().join("links/foo-1.0-py3-none-any.whl"), "wheel").unwrap();
+        std::fs::write(dir.path().join("links/foo-2.0.tar.gz"), "sdist").unwrap();
+        std::fs::write(dir.path().join("links/bar-1.0-py3-none-any.whl"), "other").unwrap();
+        std::fs::write(dir.path().join("secret.whl"), "secret").unwrap();
+
+        let middleware = FindLinksMiddleware::new(
+            &[FindLinksUrlOrPath::Path(PathBuf::from("links"))],
+            dir.path(),
+        );
+        let index_url = middleware.index_urls().pop().unwrap();
+        let client = client(middleware);
+
+        // The page of a package only links to the distributions of the package.
+        let page = client
+            .get(index_url.join("foo/").unwrap())
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let links = HREF_REGEX
+            .captures_iter(&page)
+            .map(|href| Url::parse(&href[1]).unwrap())
+            .collect_vec();
+        let file_names = links
+            .iter()
+            .map(|url| url.path_segments().unwrap().last().unwrap())
+            .collect_vec();
+        assert_eq!(file_names, ["foo-1.0-py3-none-any.whl", "foo-2.0.tar.gz"]);
+        assert!(links.iter().all(|url| url
+            .fragment()
+            .is_some_and(|fragment| fragment.starts_with("sha256="))));
+
+        // The distributions are served from the directory and can be converted back to file urls.
+        let wheel = client.get(links[0].clone()).send().await.unwrap();
+        assert_eq!(wheel.bytes().await.unwrap().as_ref(), b"wheel");
+        let mut wheel_url = links[0].clone();
+        wheel_url.set_fragment(None);
+        assert_eq!(
+            local_file_url(&wheel_url).unwrap().to_file_path().unwrap(),
+            dunce::canonicalize(dir.path())
+                .unwrap()
+                .join("links/foo-1.0-py3-none-any.whl")
+        );
+
+        // Files outside of the directories of the flat indexes are not served.
+        let secret_url = local_url(&Url::from_file_path(dir.path().join("secret.whl")).unwrap());
+        let response = client.get(secret_url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_page_links() {
+        let base = Url::parse("https://example.com/whl/links.html").unwrap();
+        let body = r#"
+            <a href="foo-1.0-py3-none-any.whl#sha256=abc">foo-1.0-py3-none-any.whl</a><br>
+            <a data-requires-python="&gt;=3.8" href='https://files.example.com/foo-2.0.tar.gz'>foo</a>
+            <a href="bar-1.0-py3-none-any.whl">bar-1.0-py3-none-any.whl</a>
+        "#;
+        let name = NormalizedPackageName::from_str("foo").unwrap();
+        assert_eq!(
+            page_links(&base, body, &name),
+            [
+                "<a href=\"https://example.com/whl/foo-1.0-py3-none-any.whl#sha256=abc\"></a>",
+                "<a data-requires-python=\"&gt;=3.8\" href=\"https://files.example.com/foo-2.0.tar.gz\"></a>",
+            ]
+        );
+    }
+}
@@ -0,0 +1,424 @@
+use crate::config;
+use miette::{Context, IntoDiagnostic};
+use rattler_digest::{compute_bytes_digest, Sha256};
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use url::Url;
+
+/// The prefix of the scheme of urls that refer to a git repository, e.g. `git+https://...`.
+const GIT_SCHEME_PREFIX: &str = "git+";
+
+/// The reference in a git repository that a `git` pypi dependency refers to.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub enum GitReference {
+    /// A branch in the repository.
+    Branch(String),
+    /// A tag in the repository.
+    Tag(String),
+    /// Any revision that git understands, e.g. a (short) commit hash.
+    Rev(String),
+    /// The branch that `HEAD` of the repository points to.
+    #[default]
+    DefaultBranch,
+}
+
+impl GitReference {
+    /// Returns the revision that can be passed to `git rev-parse` to determine the commit this
+    /// reference points to.
+    fn as_rev(&self) -> String {
+        match self {
+            GitReference::Branch(branch) => format!("refs/heads/{branch}"),
+            GitReference::Tag(tag) => format!("refs/tags/{tag}"),
+            GitReference::Rev(rev) => rev.clone(),
+            GitReference::DefaultBranch => String::from("HEAD"),
+        }
+    }
+
+    /// Returns the key and the value that are used to store this reference in the query of a url.
+    fn as_query_pair(&self) -> Option<(&'static str, &str)> {
+        match self {
+            GitReference::Branch(branch) => Some(("branch", branch)),
+            GitReference::Tag(tag) => Some(("tag", tag)),
+            GitReference::Rev(rev) => Some(("rev", rev)),
+            GitReference::DefaultBranch => None,
+        }
+    }
+
+    /// The inverse of [`Self::as_query_pair`].
+    fn from_query_pair(key: &str, value: &str) -> Option<Self> {
+        match key {
+            "branch" => Some(GitReference::Branch(value.to_string())),
+            "tag" => Some(GitReference::Tag(value.to_string())),
+            "rev" => Some(GitReference::Rev(value.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GitReference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitReference::Branch(branch) => write!(f, "branch '{branch}'"),
+            GitReference::Tag(tag) => write!(f, "tag '{tag}'"),
+            GitReference::Rev(rev) => write!(f, "revision '{rev}'"),
+            GitReference::DefaultBranch => write!(f, "the default branch"),
+        }
+    }
+}
+
+/// A reference in a git repository.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GitSource {
+    /// The url of the repository.
+    pub repository: Url,
+    /// The reference that is checked out.
+    pub reference: GitReference,
+}
+
+impl GitSource {
+    /// Returns the url that is used to store this source, resolved to the given `commit`, in the
+    /// lock-file, e.g. `git+https://github.com/org/repo.git?tag=v1.0#<commit>`.
+    ///
+    /// Next to the commit, the url also contains the reference from the manifest. This way we can
+    /// detect that the reference was modified without having to access the repository.
+    pub fn locked_url(&self, commit: &str) -> Url {
+        let mut url = Url::parse(&format!("{GIT_SCHEME_PREFIX}{}", self.repository))
+            .expect("prefixing the scheme of a valid url results in a valid url");
+        if let Some((key, value)) = self.reference.as_query_pair() {
+            url.query_pairs_mut().append_pair(key, value);
+        }
+        url.set_fragment(Some(commit));
+        url
+    }
+
+    /// The inverse of [`Self::locked_url`], returns the source and the commit stored in a url from
+    /// the lock-file. Returns `None` if the url does not refer to a git repository.
+    pub fn from_locked_url(url: &Url) -> Option<(Self, String)> {
+        let mut repository = Url::parse(url.as_str().strip_prefix(GIT_SCHEME_PREFIX)?).ok()?;
+        let commit = repository.fragment()?.to_string();
+        let reference = match repository.query_pairs().next() {
+            Some((key, value)) => GitReference::from_query_pair(&key, &value)?,
+            None => GitReference::DefaultBranch,
+        };
+        repository.set_query(None);
+        repository.set_fragment(None);
+        Some((
+            Self {
+                repository,
+                reference,
+            },
+            commit,
+        ))
+    }
+}
+
+/// A git repository checked out at a specific commit.
+#[derive(Debug, Clone)]
+pub struct GitCheckout {
+    /// The full hash of the commit that is checked out.
+    pub commit: String,
+    /// The directory that contains the source tree of the commit.
+    pub path: PathBuf,
+}
+
+impl GitCheckout {
+    /// Returns the `file://` url of the checked out source tree.
+    pub fn source_url(&self) -> Url {
+        Url::from_directory_path(&self.path).expect("the checkout path must be absolute")
+    }
+}
+
+/// Resolves the reference of the source to a commit and checks out the source tree of that commit.
+/// The repository is cloned, or fetched if it was cloned before, to make sure that the latest
+/// commit of a branch is used.
+pub async fn checkout(source: GitSource) -> miette::Result<GitCheckout> {
+    let cache_dir = git_cache_dir()?;
+    tokio::task::spawn_blocking(move || checkout_source(&cache_dir, &source))
+        .await
+        .into_diagnostic()?
+}
+
+/// Checks out the source tree of a commit in the repository. If the commit was checked out before
+/// the repository is not accessed.
+pub async fn checkout_commit(repository: Url, commit: String) -> miette::Result<GitCheckout> {
+    let cache_dir = git_cache_dir()?;
+    tokio::task::spawn_blocking(move || checkout_locked_commit(&cache_dir, &repository, &commit))
+        .await
+        .into_diagnostic()?
+}
+
+/// Returns the directory in which git repositories are cached.
+fn git_cache_dir() -> miette::Result<PathBuf> {
+    Ok(config::get_cache_dir()?.join("git"))
+}
+
+fn checkout_source(cache_dir: &Path, source: &GitSource) -> miette::Result<GitCheckout> {
+    let db = fetch_repository(cache_dir, &source.repository)?;
+    let rev = format!("{}^{{commit}}", source.reference.as_rev());
+    let commit =
+        git(&db, ["rev-parse", "--verify", "--quiet", rev.as_str()]).wrap_err_with(|| {
+            format!(
+                "failed to find {} in the git repository '{}'",
+                source.reference, source.repository
+            )
+        })?;
+    checkout_locked_commit(cache_dir, &source.repository, &commit)
+}
+
+fn checkout_locked_commit(
+    cache_dir: &Path,
+    repository: &Url,
+    commit: &str,
+) -> miette::Result<GitCheckout> {
+    let path = cache_dir
+        .join("checkouts")
+        .join(repository_id(repository))
+        .join(commit);
+    if path.is_dir() {
+        return Ok(GitCheckout {
+            commit: commit.to_string(),
+            path,
+        });
+    }
+
+    // Make sure the commit is available in our copy of the repository.
+    let db = cache_dir.join("db").join(repository_id(repository));
+    let rev = format!("{commit}^{{commit}}");
+    if !db.is_dir() || git(&db, ["cat-file", "-e", rev.as_str()]).is_err() {
+        fetch_repository(cache_dir, repository)?;
+    }
+
+    // Checkout the source tree in a temporary directory first so we never end up with a partial
+    // checkout in the cache.
+    let parent = path.parent().expect("the checkout path must have a parent");
+    std::fs::create_dir_all(parent).into_diagnostic()?;
+    let temp_dir = tempfile::tempdir_in(parent).into_diagnostic()?;
+    git(
+        parent,
+        [
+            OsStr::new("clone"),
+            OsStr::new("--quiet"),
+            OsStr::new("--no-checkout"),
+            db.as_os_str(),
+            temp_dir.path().as_os_str(),
+        ],
+    )?;
+    git(temp_dir.path(), ["checkout", "--quiet", "--detach", commit]).wrap_err_with(|| {
+        format!("failed to checkout commit '{commit}' of the git repository '{repository}'")
+    })?;
+    if let Err(err) = std::fs::rename(temp_dir.path(), &path) {
+        // Another process might have checked out the same commit in the meantime.
+        if !path.is_dir() {
+            return Err(err).into_diagnostic();
+        }
+    }
+
+    Ok(GitCheckout {
+        commit: commit.to_string(),
+        path,
+    })
+}
+
+/// Clones the repository into the cache, or fetches the latest changes if it was cloned before.
+/// Returns the path of the (bare) clone.
+fn fetch_repository(cache_dir: &Path, repository: &Url) -> miette::Result<PathBuf> {
+    let db = cache_dir.join("db").join(repository_id(repository));
+    let result = if db.is_dir() {
+        git(&db, ["fetch", "--quiet", "--prune", "origin"]).map(|_| ())
+    } else {
+        clone_repository(repository, &db)
+    };
+    result.wrap_err_with(|| format!("failed to fetch the git repository '{repository}'"))?;
+    Ok(db)
+}
+
+/// Creates a bare clone of the repository at `path`.
+fn clone_repository(repository: &Url, path: &Path) -> miette::Result<()> {
+    let parent = path
+        .parent()
+        .expect("the repository path must have a parent");
+    std::fs::create_dir_all(parent).into_diagnostic()?;
+    let temp_dir = tempfile::tempdir_in(parent).into_diagnostic()?;
+    git(
+        parent,
+        [
+            OsStr::new("clone"),
+            OsStr::new("--quiet"),
+            OsStr::new("--mirror"),
+            OsStr::new(repository.as_str()),
+            temp_dir.path().as_os_str(),
+        ],
+    )?;
+    if let Err(err) = std::fs::rename(temp_dir.path(), path) {
+        // Another process might have cloned the same repository in the meantime.
+        if !path.is_dir() {
+            return Err(err).into_diagnostic();
+        }
+    }
+    Ok(())
+}
+
+/// Returns a unique and human readable name for the repository that is used as directory name in
+/// the cache.
+fn repository_id(repository: &Url) -> String {
+    let name = repository
+        .path_segments()
+        .and_then(|segments| segments.filter(|s| !s.is_empty()).last())
+        .map(|name| name.trim_end_matches(".git"))
+        .unwrap_or("repository");
+    let hash = compute_bytes_digest::<Sha256>(repository.as_str());
+    format!("{name}-{}", &format!("{hash:x}")[..16])
+}
+
+/// Runs a git command in the given directory and returns its trimmed output.
+fn git<I, S>(cwd: &Path, args: I) -> miette::Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = Command::new("git");
+    command.args(args).current_dir(cwd);
+    let output = command
+        .output()
+        .into_diagnostic()
+        .wrap_err("failed to execute `git`, make sure it is installed and available in the PATH")?;
+    if !output.status.success() {
+        miette::bail!(
+            "`{:?}` failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a repository with two commits on the `main` branch, the first commit is tagged with
+    /// `v1.0`. Returns the hashes of both commits.
+    fn create_repository(path: &Path) -> (String, String) {
+        std::fs::create_dir_all(path).unwrap();
+        let commit = |version: &str| {
+            std::fs::write(
+                path.join("pyproject.toml"),
+                format!("[project]\nname = \"foo\"\nversion = \"{version}\""),
+            )
+            .unwrap();
+            git(path, ["add", "pyproject.toml"]).unwrap();
+            git(
+                path,
+                [
+                    "-c",
+                    "user.name=pixi",
+                    "-c",
+                    "user.email=pixi@example.com",
+                    "commit",
+                    "--quiet",
+                    "-m",
+                    version,
+                ],
+            )
+            .unwrap();
+            git(path, ["rev-parse", "HEAD"]).unwrap()
+        };
+        git(path, ["-c", "init.defaultBranch=main", "init", "--quiet"]).unwrap();
+        let first = commit("1.0");
+        git(path, ["tag", "v1.0"]).unwrap();
+        let second = commit("2.0");
+        (first, second)
+    }
+
+    #[test]
+    fn test_checkout() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let repo = dir.path().join("repo");
+        let (first, second) = create_repository(&repo);
+        let repository = Url::from_directory_path(&repo).unwrap();
+
+        let source = |reference| GitSource {
+            repository: repository.clone(),
+            reference,
+        };
+        let checkout = checkout_source(&cache_dir, &source(GitReference::DefaultBranch)).unwrap();
+        assert_eq!(checkout.commit, second);
+        assert!(
+            std::fs::read_to_string(checkout.path.join("pyproject.toml"))
+                .unwrap()
+                .contains("2.0")
+        );
+
+        let checkout =
+            checkout_source(&cache_dir, &source(GitReference::Tag("v1.0".to_string()))).unwrap();
+        assert_eq!(checkout.commit, first);
+        assert!(
+            std::fs::read_to_string(checkout.path.join("pyproject.toml"))
+                .unwrap()
+                .contains("1.0")
+        );
+
+        let checkout = checkout_source(
+            &cache_dir,
+            &source(GitReference::Branch("main".to_string())),
+        )
+        .unwrap();
+        assert_eq!(checkout.commit, second);
+
+        let checkout = checkout_source(
+            &cache_dir,
+            &source(GitReference::Rev(first[..8].to_string())),
+        )
+        .unwrap();
+        assert_eq!(checkout.commit, first);
+
+        assert!(checkout_source(
+            &cache_dir,
+            &source(GitReference::Branch("missing".to_string()))
+        )
+        .is_err());
+
+        // A checked out commit does not require access to the repository anymore.
+        std::fs::remove_dir_all(&repo).unwrap();
+        let checkout = checkout_locked_commit(&cache_dir, &repository, &first).unwrap();
+        assert_eq!(checkout.commit, first);
+    }
+
+    #[test]
+    fn test_locked_url() {
+        let source = GitSource {
+            repository: Url::parse("https://github.com/org/repo.git").unwrap(),
+            reference: GitReference::Tag("v1.0".to_string()),
+        };
+        let url = source.locked_url("abcdef");
+        assert_eq!(
+            url.as_str(),
+            "git+https://github.com/org/repo.git?tag=v1.0#abcdef"
+        );
+        assert_eq!(
+            GitSource::from_locked_url(&url),
+            Some((source, "abcdef".to_string()))
+        );
+
+        let source = GitSource {
+            repository: Url::parse("file:///home/user/repo").unwrap(),
+            reference: GitReference::DefaultBranch,
+        };
+        let url = source.locked_url("abcdef");
+        assert_eq!(url.as_str(), "git+file:///home/user/repo#abcdef");
+        assert_eq!(
+            GitSource::from_locked_url(&url),
+            Some((source, "abcdef".to_string()))
+        );
+
+        assert_eq!(
+            GitSource::from_locked_url(
+                &Url::parse("https://files.pythonhosted.org/foo-1.0.tar.gz").unwrap()
+            ),
+            None
+        );
+    }
+}
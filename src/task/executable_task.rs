@@ -1,20 +1,25 @@
+use crate::activation::expand_env_vars;
 use crate::consts::TASK_STYLE;
 use crate::project::Environment;
-use crate::task::TaskName;
+use crate::task::{task_cache, TaskCacheError, TaskHash, TaskName};
+use crate::utils::env_file::{read_env_files, EnvFileError};
 use crate::{
     task::task_graph::{TaskGraph, TaskId},
-    task::{quote_arguments, Task},
+    task::{quote, quote_arguments, quote_literal, CmdArgs, Task, TaskShell},
     Project,
 };
 use deno_task_shell::{
     execute_with_pipes, parser::SequentialList, pipe, ShellPipeWriter, ShellState,
 };
-use itertools::Itertools;
+use indexmap::IndexMap;
 use miette::Diagnostic;
+use minijinja::UndefinedBehavior;
+use rattler_digest::{compute_bytes_digest, Sha256};
 use std::{
     borrow::Cow,
     collections::HashMap,
     fmt::{Display, Formatter},
+    io::Write,
     path::PathBuf,
 };
 use thiserror::Error;
@@ -41,12 +46,56 @@ pub struct InvalidWorkingDirectory {
     pub path: String,
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("the task '{task}' runs with {shell}, but {shell} is not installed in the environment '{environment}'")]
+#[diagnostic(help("add '{package}' to the dependencies of the environment"))]
+pub struct MissingInterpreter {
+    pub task: String,
+    pub shell: TaskShell,
+    pub package: &'static str,
+    pub environment: String,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("failed to write the script of the task '{task}' to '{}'", .path.display())]
+pub struct FailedToWriteScript {
+    pub task: String,
+    pub path: PathBuf,
+    #[source]
+    pub source: std::io::Error,
+}
+
 #[derive(Debug, Error, Diagnostic)]
 pub enum TaskExecutionError {
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    MissingInterpreter(#[from] MissingInterpreter),
+    #[error(transparent)]
+    FailedToWriteScript(#[from] FailedToWriteScript),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    EnvFileError(#[from] EnvFileError),
+    #[error("no value given for the argument '{argument}' of task '{task}'")]
+    #[diagnostic(help(
+        "pass the value after the name of the task, e.g. `pixi run {task} <{argument}>`, or add a default to the argument"
+    ))]
+    MissingArgument { task: String, argument: String },
+    #[error("task '{task}' takes {expected} argument(s) but {actual} were given")]
+    TooManyArguments {
+        task: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("failed to render the command of task '{task}'")]
+    InvalidTemplate {
+        task: String,
+        #[source]
+        source: minijinja::Error,
+    },
 }
 
 /// A task that contains enough information to be able to execute it. The lifetime [`'p`] refers to
@@ -95,7 +144,12 @@ impl<'p> ExecutableTask<'p> {
 
     /// Returns a [`SequentialList`] which can be executed by deno task shell. Returns `None` if the
     /// command is not executable like in the case of an alias.
-    pub fn as_deno_script(&self) -> Result<Option<SequentialList>, FailedToParseShellScript> {
+    ///
+    /// If the task has a `shell`, the script invokes that interpreter from the environment of the
+    /// task with the command of the task. The command is quoted so it reaches the interpreter
+    /// unchanged instead of being parsed by deno task shell. PowerShell only binds arguments to
+    /// `$args` when it runs a file, so its script is written to [`Project::task_scripts_dir`].
+    pub fn as_deno_script(&self) -> Result<Option<SequentialList>, TaskExecutionError> {
         // Convert the task into an executable string
         let Some(task) = self.task.as_single_command() else {
             return Ok(None);
@@ -103,7 +157,32 @@ impl<'p> ExecutableTask<'p> {
 
         // Append the command line arguments
         let cli_args = quote_arguments(self.additional_args.iter().map(|arg| arg.as_str()));
-        let full_script = format!("{task} {cli_args}");
+        let full_script = match self.task.shell() {
+            Some(shell) => {
+                let interpreter = self.interpreter_path(shell)?;
+                let interpreter = quote_literal(&interpreter.to_string_lossy()).into_owned();
+                let script = quote_literal(&task);
+                match shell {
+                    // The name of the task becomes `$0` of the script, the arguments `$1` and
+                    // onwards.
+                    TaskShell::Bash => format!(
+                        "{interpreter} -c {script} {} {cli_args}",
+                        quote(self.name().unwrap_or("bash"))
+                    ),
+                    TaskShell::Python => format!("{interpreter} -c {script} {cli_args}"),
+                    // With `-Command` PowerShell would append the arguments to the command and
+                    // parse them as code, with `-File` they are bound to `$args`.
+                    TaskShell::Pwsh => {
+                        let path = self.write_script(&task, "ps1")?;
+                        format!(
+                            "{interpreter} -NoProfile -File {} {cli_args}",
+                            quote_literal(&path.to_string_lossy())
+                        )
+                    }
+                }
+            }
+            None => format!("{task} {cli_args}"),
+        };
 
         // Parse the shell command
         deno_task_shell::parser::parse(full_script.trim())
@@ -112,6 +191,65 @@ impl<'p> ExecutableTask<'p> {
                 error: e.to_string(),
             })
             .map(Some)
+            .map_err(TaskExecutionError::from)
+    }
+
+    /// Writes the `script` of this task to a file with the given `extension` and returns its path.
+    /// The name of the file is the hash of the script, so tasks that run at the same time never
+    /// write to the same file unless the script is the same.
+    fn write_script(&self, script: &str, extension: &str) -> Result<PathBuf, FailedToWriteScript> {
+        let hash = compute_bytes_digest::<Sha256>(script);
+        let path = self
+            .project
+            .task_scripts_dir()
+            .join(format!("{hash:x}.{extension}"));
+        if path.is_file() {
+            return Ok(path);
+        }
+        let error = |source| FailedToWriteScript {
+            task: self.name().unwrap_or_default().to_string(),
+            path: path.clone(),
+            source,
+        };
+        let dir = self.project.task_scripts_dir();
+        std::fs::create_dir_all(&dir).map_err(error)?;
+
+        // Write to a temporary file first, so a task running at the same time never reads a
+        // partially written script.
+        let mut file = tempfile::NamedTempFile::new_in(&dir).map_err(error)?;
+        file.write_all(script.as_bytes()).map_err(error)?;
+        file.persist(&path).map_err(|err| error(err.error))?;
+        Ok(path)
+    }
+
+    /// Returns the path of the interpreter `shell` in the prefix of the environment of the task.
+    /// The interpreter is looked up in the directories that activation adds to the `PATH`, so an
+    /// interpreter outside of the environment is never used.
+    fn interpreter_path(&self, shell: TaskShell) -> Result<PathBuf, MissingInterpreter> {
+        let prefix = self.run_environment.dir();
+        let bin_dirs = if cfg!(windows) {
+            vec![
+                prefix.clone(),
+                prefix.join("Library/mingw-w64/bin"),
+                prefix.join("Library/usr/bin"),
+                prefix.join("Library/bin"),
+                prefix.join("Scripts"),
+                prefix.join("bin"),
+            ]
+        } else {
+            vec![prefix.join("bin")]
+        };
+        let executable = format!("{}{}", shell.as_str(), std::env::consts::EXE_SUFFIX);
+        bin_dirs
+            .into_iter()
+            .map(|dir| dir.join(&executable))
+            .find(|path| path.is_file())
+            .ok_or_else(|| MissingInterpreter {
+                task: self.name().unwrap_or_default().to_string(),
+                shell,
+                package: shell.package_name(),
+                environment: self.run_environment.name().to_string(),
+            })
     }
 
     /// Returns the working directory for this task.
@@ -131,8 +269,53 @@ impl<'p> ExecutableTask<'p> {
         })
     }
 
+    /// Returns the environment variables to execute this task with. The variables of the env files
+    /// of the task and then the variables defined by the task are set on top of `command_env`, and
+    /// can reference variables from it using `$VAR` or `${VAR}`.
+    pub fn task_env<'e>(
+        &self,
+        command_env: &'e HashMap<String, String>,
+    ) -> Result<Cow<'e, HashMap<String, String>>, EnvFileError> {
+        let env_files = self.task.env_files();
+        let task_env = self.task.env().filter(|env| !env.is_empty());
+        if env_files.is_empty() && task_env.is_none() {
+            return Ok(Cow::Borrowed(command_env));
+        }
+        let mut env = command_env.clone();
+        let root = self.project.root();
+        read_env_files(env_files.iter().map(|path| root.join(path)), &mut env)?;
+        for (key, value) in task_env.into_iter().flatten() {
+            let value = expand_env_vars(value, &env);
+            env.insert(key.clone(), value);
+        }
+        Ok(Cow::Owned(env))
+    }
+
+    /// Computes the [`TaskHash`] of this task when executed with the environment variables
+    /// `command_env`. Returns `None` if the task does not define `inputs` and can therefore not
+    /// be cached.
+    pub fn compute_hash(
+        &self,
+        command_env: &HashMap<String, String>,
+    ) -> Result<Option<TaskHash>, TaskCacheError> {
+        let task_env = self.task_env(command_env)?;
+        TaskHash::from_task(self, &task_env)
+    }
+
+    /// Returns true if this task previously ran successfully with the same `hash`, in which case
+    /// it does not have to be executed again.
+    pub fn is_up_to_date(&self, hash: &TaskHash) -> bool {
+        task_cache::is_up_to_date(self, hash)
+    }
+
+    /// Stores the `hash` of this task in the task cache after it ran successfully.
+    pub fn save_to_cache(&self, hash: TaskHash) -> Result<(), TaskCacheError> {
+        task_cache::write_cache(self, hash)
+    }
+
     /// Returns the full command that should be executed for this task. This includes any
-    /// additional arguments that should be passed to the command.
+    /// additional arguments that should be passed to the command, quoted the same way as when
+    /// the task is executed.
     ///
     /// This function returns `None` if the task does not define a command to execute. This is the
     /// case for alias only commands.
@@ -141,7 +324,9 @@ impl<'p> ExecutableTask<'p> {
 
         if !self.additional_args.is_empty() {
             cmd.push(' ');
-            cmd.push_str(&self.additional_args.join(" "));
+            cmd.push_str(&quote_arguments(
+                self.additional_args.iter().map(String::as_str),
+            ));
         }
 
         Some(cmd)
@@ -173,7 +358,11 @@ impl<'p> ExecutableTask<'p> {
         drop(stdin_writer); // prevent a deadlock by dropping the writer
         let (stdout, stdout_handle) = get_output_writer_and_handle();
         let (stderr, stderr_handle) = get_output_writer_and_handle();
-        let state = ShellState::new(command_env.clone(), &cwd, Default::default());
+        let state = ShellState::new(
+            self.task_env(command_env)?.into_owned(),
+            &cwd,
+            Default::default(),
+        );
         let code = execute_with_pipes(script, state, stdin, stdout, stderr).await;
         Ok(RunOutput {
             exit_code: code,
@@ -183,6 +372,94 @@ impl<'p> ExecutableTask<'p> {
     }
 }
 
+/// Binds the `values` passed to a task to the arguments it declares, in order. Arguments without a
+/// value get their default value.
+///
+/// Returns `None` if the task does not declare any arguments.
+pub(crate) fn bind_arguments(
+    name: Option<&TaskName>,
+    task: &Task,
+    values: &[String],
+) -> Result<Option<IndexMap<String, String>>, TaskExecutionError> {
+    let Some(args) = task.args().filter(|args| !args.is_empty()) else {
+        return Ok(None);
+    };
+    let task_name = || name.map(TaskName::as_str).unwrap_or_default().to_string();
+
+    if values.len() > args.len() {
+        return Err(TaskExecutionError::TooManyArguments {
+            task: task_name(),
+            expected: args.len(),
+            actual: values.len(),
+        });
+    }
+
+    args.iter()
+        .enumerate()
+        .map(|(idx, arg)| {
+            let value = values.get(idx).or(arg.default.as_ref()).ok_or_else(|| {
+                TaskExecutionError::MissingArgument {
+                    task: task_name(),
+                    argument: arg.name.clone(),
+                }
+            })?;
+            Ok((arg.name.clone(), value.clone()))
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+/// Renders `template` with the values of the `arguments` of a task.
+pub(crate) fn render_template(
+    name: Option<&TaskName>,
+    template: &str,
+    arguments: &IndexMap<String, String>,
+) -> Result<String, TaskExecutionError> {
+    render(name, template, arguments, minijinja::Environment::new())
+}
+
+/// Returns a copy of `task` with the `arguments` rendered into its command. Every value is quoted
+/// for the shell that runs the command, so the built-in shell and bash pass it to the command as a
+/// single argument and python and PowerShell read it as a string literal. A value is never executed
+/// as code.
+pub(crate) fn render_command(
+    name: Option<&TaskName>,
+    task: &Task,
+    arguments: &IndexMap<String, String>,
+) -> Result<Task, TaskExecutionError> {
+    let mut task = task.clone();
+    let shell = task.shell();
+    if let Task::Execute(execute) = &mut task {
+        let mut env = minijinja::Environment::new();
+        env.set_formatter(move |out, _, value| {
+            let value = value.to_string();
+            let quoted = match shell {
+                Some(shell) => shell.quote_literal(&value),
+                None => quote_literal(&value),
+            };
+            out.write_str(&quoted).map_err(minijinja::Error::from)
+        });
+        let command = render(name, &execute.cmd.as_single(), arguments, env)?;
+        execute.cmd = CmdArgs::Single(command);
+    }
+    Ok(task)
+}
+
+/// Renders `template` with the values of the `arguments` in the minijinja environment `env`.
+fn render(
+    name: Option<&TaskName>,
+    template: &str,
+    arguments: &IndexMap<String, String>,
+    mut env: minijinja::Environment,
+) -> Result<String, TaskExecutionError> {
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.render_str(template, arguments)
+        .map_err(|source| TaskExecutionError::InvalidTemplate {
+            task: name.map(TaskName::as_str).unwrap_or_default().to_string(),
+            source,
+        })
+}
+
 /// A helper object that implements [`Display`] to display (with ascii color) the command of the
 /// task.
 struct ExecutableTaskConsoleDisplay<'p, 't> {
@@ -203,7 +480,9 @@ impl<'p, 't> Display for ExecutableTaskConsoleDisplay<'p, 't> {
             write!(
                 f,
                 " {}",
-                TASK_STYLE.apply_to(self.task.additional_args.iter().format(" "))
+                TASK_STYLE.apply_to(quote_arguments(
+                    self.task.additional_args.iter().map(String::as_str)
+                ))
             )?;
         }
         Ok(())
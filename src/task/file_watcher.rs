@@ -0,0 +1,320 @@
+use crate::consts;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use itertools::Itertools;
+use miette::Diagnostic;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+/// The time to wait for more changes after a file changed, so that saving multiple files at once
+/// only results in a single change.
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum FileWatcherError {
+    #[error("failed to create a file watcher")]
+    FailedToCreateWatcher(#[source] notify::Error),
+
+    #[error("failed to watch '{}'", .path.display())]
+    FailedToWatch {
+        path: PathBuf,
+        #[source]
+        source: notify::Error,
+    },
+
+    #[error("invalid glob pattern '{pattern}'")]
+    InvalidGlob {
+        pattern: String,
+        #[source]
+        source: glob::PatternError,
+    },
+
+    #[error("failed to read the ignore files of the project")]
+    InvalidIgnoreFile(#[source] ignore::Error),
+}
+
+/// Determines which changed files are reported by the [`FileWatcher`].
+enum FileFilter {
+    /// Only changes to files that match any of the glob patterns are reported.
+    Patterns(Vec<glob::Pattern>),
+
+    /// Changes to all files that are not ignored by the `.gitignore` of the project are reported.
+    NotIgnored(Gitignore),
+}
+
+/// Watches the files of a project and reports when they change.
+///
+/// If `inputs` are given, only changes to files that match these glob patterns are reported.
+/// Otherwise all files in the project are watched except for the `.pixi` directory and the files
+/// that are ignored by git. Changes to files that match the `outputs` are never reported.
+pub struct FileWatcher {
+    root: PathBuf,
+    watcher: RecommendedWatcher,
+    events: UnboundedReceiver<notify::Result<Event>>,
+    filter: FileFilter,
+    excluded: Vec<glob::Pattern>,
+    changed: Vec<PathBuf>,
+}
+
+impl FileWatcher {
+    /// Starts watching the files in the project at `root`.
+    pub fn new(
+        root: &Path,
+        inputs: &[String],
+        outputs: &[String],
+    ) -> Result<Self, FileWatcherError> {
+        let root = dunce::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+
+        let filter = if inputs.is_empty() {
+            let mut builder = GitignoreBuilder::new(&root);
+            for ignore_file in [".gitignore", ".ignore"] {
+                let path = root.join(ignore_file);
+                if path.is_file() {
+                    if let Some(err) = builder.add(path) {
+                        tracing::warn!("failed to read '{}': {err}", ignore_file);
+                    }
+                }
+            }
+            FileFilter::NotIgnored(
+                builder
+                    .build()
+                    .map_err(FileWatcherError::InvalidIgnoreFile)?,
+            )
+        } else {
+            FileFilter::Patterns(parse_patterns(inputs)?)
+        };
+
+        let (sender, events) = unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(FileWatcherError::FailedToCreateWatcher)?;
+
+        let mut file_watcher = Self {
+            excluded: parse_patterns(outputs)?,
+            root,
+            watcher,
+            events,
+            filter,
+            changed: Vec::new(),
+        };
+
+        // Watch every directory that is not ignored separately. This avoids watching the (large)
+        // `.pixi` directory and ignored directories like build folders.
+        let directories = WalkBuilder::new(&file_watcher.root)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| !is_pixi_or_git_dir(entry.file_name()))
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir())
+            })
+            .map(|entry| entry.into_path())
+            .collect_vec();
+        for directory in directories {
+            file_watcher.watch(directory)?;
+        }
+
+        Ok(file_watcher)
+    }
+
+    /// Waits until one or more files changed and returns the paths of the changed files.
+    ///
+    /// This function is cancel safe, changes that were observed before the returned future is
+    /// dropped are returned by the next call.
+    pub async fn next_change(&mut self) -> Result<Vec<PathBuf>, FileWatcherError> {
+        // Wait for the first relevant change.
+        while self.changed.is_empty() {
+            let Some(event) = self.events.recv().await else {
+                break;
+            };
+            self.handle_event(event)?;
+        }
+
+        // Collect all the changes that follow shortly after.
+        while let Ok(Some(event)) =
+            tokio::time::timeout(DEBOUNCE_DURATION, self.events.recv()).await
+        {
+            self.handle_event(event)?;
+        }
+
+        Ok(std::mem::take(&mut self.changed))
+    }
+
+    /// Starts watching the files in the given directory.
+    fn watch(&mut self, path: PathBuf) -> Result<(), FileWatcherError> {
+        self.watcher
+            .watch(&path, RecursiveMode::NonRecursive)
+            .map_err(|source| FileWatcherError::FailedToWatch { path, source })
+    }
+
+    /// Records the relevant paths of the `event` as changed.
+    fn handle_event(&mut self, event: notify::Result<Event>) -> Result<(), FileWatcherError> {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                tracing::warn!("error while watching files: {err}");
+                return Ok(());
+            }
+        };
+
+        // Only modifications are relevant, reading a file (e.g. to compute the hash of a task) is
+        // not.
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            return Ok(());
+        }
+
+        for path in event.paths {
+            let Some(relative_path) = self.relative_path(&path) else {
+                continue;
+            };
+            let is_dir = path.is_dir();
+
+            // Newly created directories have to be watched as well.
+            if is_dir && matches!(event.kind, EventKind::Create(_)) && !self.is_ignored(&path, true)
+            {
+                self.watch(path.clone())?;
+            }
+
+            if self.is_relevant(&path, &relative_path, is_dir) && !self.changed.contains(&path) {
+                self.changed.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path relative to the root of the project with `/` as separator, or `None` if
+    /// the path is not part of the project.
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative_path = path.strip_prefix(&self.root).ok()?;
+        let mut components = relative_path.components().peekable();
+        if components
+            .peek()
+            .is_some_and(|component| is_pixi_or_git_dir(component.as_os_str()))
+        {
+            return None;
+        }
+        Some(
+            components
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy()),
+                    _ => None,
+                })
+                .join("/"),
+        )
+    }
+
+    /// Returns true if the path is ignored by the `.gitignore` of the project.
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        match &self.filter {
+            FileFilter::NotIgnored(gitignore) => gitignore
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore(),
+            FileFilter::Patterns(_) => false,
+        }
+    }
+
+    /// Returns true if a change to the path should be reported.
+    fn is_relevant(&self, path: &Path, relative_path: &str, is_dir: bool) -> bool {
+        if relative_path.is_empty() || matches_any(&self.excluded, relative_path) {
+            return false;
+        }
+        match &self.filter {
+            FileFilter::Patterns(patterns) => matches_any(patterns, relative_path),
+            FileFilter::NotIgnored(_) => !self.is_ignored(path, is_dir),
+        }
+    }
+}
+
+/// Returns true if the name is the name of the `.pixi` or `.git` directory.
+fn is_pixi_or_git_dir(name: &std::ffi::OsStr) -> bool {
+    name == consts::PIXI_DIR || name == ".git"
+}
+
+/// Parses glob patterns that are relative to the root of the project.
+fn parse_patterns(patterns: &[String]) -> Result<Vec<glob::Pattern>, FileWatcherError> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern.trim_start_matches("./")).map_err(|source| {
+                FileWatcherError::InvalidGlob {
+                    pattern: pattern.clone(),
+                    source,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Returns true if the relative path matches any of the patterns.
+fn matches_any(patterns: &[glob::Pattern], relative_path: &str) -> bool {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::default()
+    };
+    patterns
+        .iter()
+        .any(|pattern| pattern.matches_with(relative_path, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileWatcher;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_file_watcher_inputs() {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = dunce::canonicalize(root.path()).unwrap();
+        std::fs::create_dir_all(root_path.join("src")).unwrap();
+        std::fs::write(root_path.join("src/main.c"), "a").unwrap();
+
+        let mut watcher = FileWatcher::new(
+            &root_path,
+            &["src/*.c".to_string()],
+            &["build/*".to_string()],
+        )
+        .unwrap();
+
+        // Changes to files that don't match the inputs are not reported.
+        std::fs::write(root_path.join("README.md"), "readme").unwrap();
+        std::fs::write(root_path.join("src/main.c"), "b").unwrap();
+        let changed = tokio::time::timeout(Duration::from_secs(10), watcher.next_change())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(changed, vec![root_path.join("src/main.c")]);
+    }
+
+    #[tokio::test]
+    async fn test_file_watcher_gitignore() {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = dunce::canonicalize(root.path()).unwrap();
+        std::fs::write(root_path.join(".gitignore"), "*.log\ntarget/\n").unwrap();
+        std::fs::create_dir_all(root_path.join("target")).unwrap();
+        std::fs::create_dir_all(root_path.join(".pixi")).unwrap();
+
+        let mut watcher = FileWatcher::new(&root_path, &[], &[]).unwrap();
+
+        // Ignored files and the `.pixi` directory are not reported.
+        std::fs::write(root_path.join("debug.log"), "log").unwrap();
+        std::fs::write(root_path.join("target/out"), "out").unwrap();
+        std::fs::write(root_path.join(".pixi/state"), "state").unwrap();
+        std::fs::write(root_path.join("main.py"), "print()").unwrap();
+        let changed = tokio::time::timeout(Duration::from_secs(10), watcher.next_change())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(changed, vec![root_path.join("main.py")]);
+    }
+}
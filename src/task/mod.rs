@@ -1,24 +1,33 @@
+use crate::consts::TASK_STYLE;
+use crate::EnvironmentName;
+use indexmap::IndexMap;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use serde_with::{formats::PreferMany, serde_as, OneOrMany};
+use serde_with::{formats::PreferMany, serde_as, DisplayFromStr, OneOrMany};
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 
 mod error;
 mod executable_task;
+mod file_watcher;
+mod task_cache;
 mod task_environment;
 mod task_graph;
+mod task_processes;
 
 pub use executable_task::{
-    ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory, RunOutput,
-    TaskExecutionError,
+    ExecutableTask, FailedToParseShellScript, FailedToWriteScript, InvalidWorkingDirectory,
+    MissingInterpreter, RunOutput, TaskExecutionError,
 };
+pub use file_watcher::{FileWatcher, FileWatcherError};
+pub use task_cache::{TaskCacheError, TaskHash};
 pub use task_environment::{
     AmbiguousTask, FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment,
     TaskDisambiguation,
 };
-pub use task_graph::{TaskGraph, TaskGraphError, TaskId, TaskNode};
+pub use task_graph::{TaskGraph, TaskGraphError, TaskGraphFormat, TaskId, TaskNode};
+pub use task_processes::TaskProcesses;
 
 /// Represents a task name
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -54,7 +63,7 @@ impl From<TaskName> for String {
 #[serde(untagged)]
 pub enum Task {
     Plain(String),
-    Execute(Execute),
+    Execute(Box<Execute>),
     Alias(Alias),
     // We want a way for the deserializer to except a custom task, as they are meant for tasks
     // given in the command line.
@@ -63,8 +72,8 @@ pub enum Task {
 }
 
 impl Task {
-    /// Returns the names of the task that this task depends on
-    pub fn depends_on(&self) -> &[TaskName] {
+    /// Returns the tasks that this task depends on
+    pub fn depends_on(&self) -> &[Dependency] {
         match self {
             Task::Plain(_) | Task::Custom(_) => &[],
             Task::Execute(cmd) => &cmd.depends_on,
@@ -134,6 +143,98 @@ impl Task {
         }
     }
 
+    /// Returns the environment variables that are set for this task.
+    pub fn env(&self) -> Option<&IndexMap<String, String>> {
+        match self {
+            Task::Execute(exe) => exe.env.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the paths of the dotenv files that are loaded when running the task.
+    pub fn env_files(&self) -> &[PathBuf] {
+        match self {
+            Task::Execute(exe) => exe.env_file.as_deref().unwrap_or_default(),
+            _ => &[],
+        }
+    }
+
+    /// Returns the human-readable description of the task.
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Task::Execute(exe) => exe.description.as_deref(),
+            Task::Alias(alias) => alias.description.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the environment this task runs in, unless another environment is selected
+    /// explicitly.
+    pub fn environment(&self) -> Option<&EnvironmentName> {
+        match self {
+            Task::Execute(exe) => exe.environment.as_ref(),
+            Task::Alias(alias) => alias.environment.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the interpreter that runs the command of the task, or `None` if the command is run
+    /// by the built-in shell.
+    pub fn shell(&self) -> Option<TaskShell> {
+        match self {
+            Task::Execute(exe) => exe.shell,
+            _ => None,
+        }
+    }
+
+    /// Returns the maximum time the task may run.
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        match self {
+            Task::Execute(exe) => exe.timeout.map(Into::into),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of times the task is retried when it fails.
+    pub fn retries(&self) -> u32 {
+        match self {
+            Task::Execute(exe) => exe.retries.unwrap_or_default(),
+            _ => 0,
+        }
+    }
+
+    /// Returns true if the tasks that depend on this task are executed even if this task fails.
+    pub fn allow_failure(&self) -> bool {
+        match self {
+            Task::Execute(exe) => exe.allow_failure.unwrap_or_default(),
+            _ => false,
+        }
+    }
+
+    /// Returns the arguments that this task declares.
+    pub fn args(&self) -> Option<&[TaskArg]> {
+        match self {
+            Task::Execute(exe) => exe.args.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the glob patterns of the files that are used as input by this task.
+    pub fn inputs(&self) -> Option<&[String]> {
+        match self {
+            Task::Execute(exe) => exe.inputs.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the glob patterns of the files that are produced by this task.
+    pub fn outputs(&self) -> Option<&[String]> {
+        match self {
+            Task::Execute(exe) => exe.outputs.as_deref(),
+            _ => None,
+        }
+    }
+
     /// True if this task is a custom task instead of something defined in a project.
     pub fn is_custom(&self) -> bool {
         matches!(self, Task::Custom(_))
@@ -150,17 +251,205 @@ pub struct Execute {
     pub cmd: CmdArgs,
 
     /// A list of commands that should be run before this one
-    #[serde(default)]
+    #[serde(default, alias = "depends-on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<Dependency>,
 
     /// The working directory for the command relative to the root of the project.
     pub cwd: Option<PathBuf>,
+
+    /// Environment variables that are set when running the command, on top of the variables of the
+    /// activated environment.
+    pub env: Option<IndexMap<String, String>>,
+
+    /// Dotenv files, relative to the root of the project, with environment variables that are set
+    /// when running the command. The variables of `env` take precedence over these.
+    #[serde(alias = "env-file")]
+    pub env_file: Option<Vec<PathBuf>>,
+
+    /// Glob patterns, relative to the root of the project, of the files that are used as input by
+    /// the command. If the inputs did not change since the last run the command is skipped.
+    pub inputs: Option<Vec<String>>,
+
+    /// Glob patterns, relative to the root of the project, of the files that are produced by the
+    /// command. If the outputs did not change since the last run the command is skipped.
+    pub outputs: Option<Vec<String>>,
+
+    /// A human-readable description of what the task does.
+    pub description: Option<String>,
+
+    /// The arguments of the task. The values of the arguments can be used in the command with
+    /// `{{ name }}`.
+    pub args: Option<Vec<TaskArg>>,
+
+    /// The maximum time the command may run, e.g. `"10m"`. The command is killed when it takes
+    /// longer.
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<DisplayFromStr>")]
+    pub timeout: Option<humantime::Duration>,
+
+    /// The number of times the command is retried when it fails or times out.
+    pub retries: Option<u32>,
+
+    /// Whether the tasks that depend on this one are still executed when the command fails.
+    #[serde(alias = "allow-failure")]
+    pub allow_failure: Option<bool>,
+
+    /// The environment the command runs in, unless another environment is selected explicitly.
+    pub environment: Option<EnvironmentName>,
+
+    /// The interpreter that runs the command as a script instead of the built-in shell.
+    pub shell: Option<TaskShell>,
 }
 
 impl From<Execute> for Task {
     fn from(value: Execute) -> Self {
-        Task::Execute(value)
+        Task::Execute(Box::new(value))
+    }
+}
+
+/// An interpreter, installed in the environment of a task, that runs the command of the task as a
+/// script. This allows using features that the built-in shell does not support, like functions,
+/// heredocs or `set -e`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskShell {
+    Bash,
+    Python,
+    Pwsh,
+}
+
+impl TaskShell {
+    /// Returns the name of the executable of the interpreter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskShell::Bash => "bash",
+            TaskShell::Python => "python",
+            TaskShell::Pwsh => "pwsh",
+        }
+    }
+
+    /// Returns the name of the conda package that provides the interpreter.
+    pub fn package_name(&self) -> &'static str {
+        match self {
+            TaskShell::Bash => "bash",
+            TaskShell::Python => "python",
+            TaskShell::Pwsh => "powershell",
+        }
+    }
+
+    /// Quotes a string so the interpreter reads it as a literal string without interpreting any of
+    /// its characters, see [`quote_literal`], [`quote_python_literal`] and
+    /// [`quote_pwsh_literal`].
+    pub fn quote_literal<'a>(&self, in_str: &'a str) -> Cow<'a, str> {
+        match self {
+            TaskShell::Bash => quote_literal(in_str),
+            TaskShell::Python => quote_python_literal(in_str).into(),
+            TaskShell::Pwsh => quote_pwsh_literal(in_str).into(),
+        }
+    }
+}
+
+impl Display for TaskShell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A named argument of a task.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskArg {
+    /// The name of the argument, the value can be used in the command of the task with
+    /// `{{ name }}`.
+    pub name: String,
+
+    /// The value of the argument if no value is passed, if `None` a value is required.
+    pub default: Option<String>,
+}
+
+/// A dependency on another task, optionally passing values for the arguments of that task.
+///
+/// In the manifest a dependency is either the name of a task, or a table with the name of the task
+/// and the arguments to pass, e.g. `{ task = "build", args = ["release"] }`. The table can also
+/// name the environment the task runs in, e.g. `{ task = "build-docs", environment = "docs" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "RawDependency")]
+pub struct Dependency {
+    /// The name of the task to depend on.
+    pub task_name: TaskName,
+
+    /// The values to pass to the arguments of the task. The values can refer to the arguments of
+    /// the dependent task with `{{ name }}`.
+    pub args: Option<Vec<String>>,
+
+    /// The environment to run the task in. If `None` the task is searched for in the same way as
+    /// the dependent task.
+    pub environment: Option<EnvironmentName>,
+}
+
+impl Dependency {
+    /// Returns the name of the task this dependency refers to.
+    pub fn as_str(&self) -> &str {
+        self.task_name.as_str()
+    }
+}
+
+impl From<TaskName> for Dependency {
+    fn from(task_name: TaskName) -> Self {
+        Self {
+            task_name,
+            args: None,
+            environment: None,
+        }
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.args {
+            Some(args) if !args.is_empty() => {
+                write!(
+                    f,
+                    "{}({})",
+                    self.task_name.as_str(),
+                    args.iter().format(", ")
+                )?;
+            }
+            _ => write!(f, "{}", self.task_name.as_str())?,
+        }
+        if let Some(environment) = &self.environment {
+            write!(f, " [{}]", environment.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDependency {
+    Name(TaskName),
+    Table {
+        task: TaskName,
+        args: Option<Vec<String>>,
+        environment: Option<EnvironmentName>,
+    },
+}
+
+impl From<RawDependency> for Dependency {
+    fn from(value: RawDependency) -> Self {
+        match value {
+            RawDependency::Name(task_name) => task_name.into(),
+            RawDependency::Table {
+                task,
+                args,
+                environment,
+            } => Self {
+                task_name: task,
+                args,
+                environment,
+            },
+        }
     }
 }
 
@@ -222,8 +511,16 @@ impl CmdArgs {
 #[serde_as]
 pub struct Alias {
     /// A list of commands that should be run before this one
+    #[serde(alias = "depends-on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<Dependency>,
+
+    /// A human-readable description of what the alias does.
+    pub description: Option<String>,
+
+    /// The environment the tasks of the alias are searched in, unless another environment is
+    /// selected explicitly.
+    pub environment: Option<EnvironmentName>,
 }
 
 impl Display for Task {
@@ -237,6 +534,16 @@ impl Display for Task {
                     CmdArgs::Single(cmd) => write!(f, "{}", cmd)?,
                     CmdArgs::Multiple(mult) => write!(f, "{}", mult.join(" "))?,
                 };
+                if let Some(env) = cmd.env.as_ref().filter(|env| !env.is_empty()) {
+                    write!(
+                        f,
+                        ", env = {{{}}}",
+                        env.iter()
+                            .format_with(", ", |(key, value), f| f(&format_args!(
+                                "{key} = \"{value}\""
+                            )))
+                    )?;
+                }
                 if !cmd.depends_on.is_empty() {
                     write!(f, ", ")?;
                 }
@@ -250,13 +557,19 @@ impl Display for Task {
                 write!(
                     f,
                     "depends_on = '{}'",
-                    depends_on.iter().map(|t| t.fancy_display()).join(",")
+                    depends_on
+                        .iter()
+                        .map(|t| TASK_STYLE.apply_to(t.to_string()))
+                        .join(",")
                 )
             } else {
                 write!(
                     f,
                     "depends_on = [{}]",
-                    depends_on.iter().map(|t| t.fancy_display()).join(",")
+                    depends_on
+                        .iter()
+                        .map(|t| TASK_STYLE.apply_to(t.to_string()))
+                        .join(",")
                 )
             }
         } else {
@@ -290,6 +603,57 @@ pub fn quote(in_str: &str) -> Cow<str> {
     }
 }
 
+/// Quotes a string so our shell implementation passes it to a command as a single argument without
+/// interpreting any of its characters. Unlike [`quote`], variables like `$PATH` are not expanded
+/// and characters like `;` or `|` don't end the command.
+pub fn quote_literal(in_str: &str) -> Cow<str> {
+    if !in_str.is_empty()
+        && in_str.chars().all(|c| {
+            c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '@' | '%' | '+' | ',')
+        })
+    {
+        return in_str.into();
+    }
+
+    // Single quoted strings cannot contain a single quote, so these are put in double quotes.
+    format!("'{}'", in_str.replace('\'', "'\"'\"'")).into()
+}
+
+/// Quotes a string as a python string literal. Unlike [`quote_literal`] the string is always
+/// quoted, because an unquoted word is a name in python.
+pub fn quote_python_literal(in_str: &str) -> String {
+    let mut out = String::with_capacity(in_str.len() + 2);
+    out.push('\'');
+    for c in in_str.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Quotes a string as a verbatim PowerShell string, in which nothing is expanded. PowerShell also
+/// accepts typographic single quotes as quotes, so these are escaped as well.
+pub fn quote_pwsh_literal(in_str: &str) -> String {
+    let mut out = String::with_capacity(in_str.len() + 2);
+    out.push('\'');
+    for c in in_str.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+            out.push(c);
+        }
+        out.push(c);
+    }
+    out.push('\'');
+    out
+}
+
 /// Quotes multiple string arguments and joins them together to form a single string.
 pub fn quote_arguments<'a>(args: impl IntoIterator<Item = &'a str>) -> String {
     args.into_iter().map(quote).join(" ")
@@ -297,7 +661,7 @@ pub fn quote_arguments<'a>(args: impl IntoIterator<Item = &'a str>) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::quote;
+    use super::{quote, quote_literal, quote_pwsh_literal, quote_python_literal};
 
     #[test]
     fn test_quote() {
@@ -313,4 +677,33 @@ mod tests {
         );
         assert_eq!(quote("name=[64,64]"), "\"name=[64,64]\"");
     }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("foobar"), "foobar");
+        assert_eq!(quote_literal("./build/v1.2"), "./build/v1.2");
+        assert_eq!(quote_literal("foo bar"), "'foo bar'");
+        assert_eq!(quote_literal("x;rm"), "'x;rm'");
+        assert_eq!(quote_literal("$PATH"), "'$PATH'");
+        assert_eq!(quote_literal("it's"), "'it'\"'\"'s'");
+        assert_eq!(quote_literal(""), "''");
+    }
+
+    #[test]
+    fn test_quote_python_literal() {
+        assert_eq!(quote_python_literal("foobar"), "'foobar'");
+        assert_eq!(quote_python_literal("it's"), r"'it\'s'");
+        assert_eq!(quote_python_literal(r"C:\temp"), r"'C:\\temp'");
+        assert_eq!(quote_python_literal("a\nb\u{7}"), r"'a\nb\u0007'");
+        assert_eq!(quote_python_literal(""), "''");
+    }
+
+    #[test]
+    fn test_quote_pwsh_literal() {
+        assert_eq!(quote_pwsh_literal("foobar"), "'foobar'");
+        assert_eq!(quote_pwsh_literal("it's"), "'it''s'");
+        assert_eq!(quote_pwsh_literal("it\u{2019}s"), "'it\u{2019}\u{2019}s'");
+        assert_eq!(quote_pwsh_literal("$env:PATH"), "'$env:PATH'");
+        assert_eq!(quote_pwsh_literal(""), "''");
+    }
 }
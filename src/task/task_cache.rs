@@ -0,0 +1,315 @@
+use crate::task::ExecutableTask;
+use crate::utils::env_file::EnvFileError;
+use itertools::Itertools;
+use miette::Diagnostic;
+use rattler_digest::{compute_bytes_digest, compute_file_digest, Sha256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum TaskCacheError {
+    #[error("invalid glob pattern '{pattern}'")]
+    InvalidGlob {
+        pattern: String,
+        #[source]
+        source: glob::PatternError,
+    },
+
+    #[error("failed to hash '{}'", .path.display())]
+    FailedToHashFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(transparent)]
+    GlobError(#[from] glob::GlobError),
+
+    #[error("failed to write the task cache to '{}'", .path.display())]
+    FailedToWriteCache {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    EnvFileError(#[from] EnvFileError),
+}
+
+/// A hash that describes the state of a task: its command, its environment variables, the packages
+/// installed in its environment and the contents of the files matched by its `inputs` and
+/// `outputs`. If the hash of a task did not change since it last ran successfully, the task does
+/// not have to be executed again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TaskHash(String);
+
+/// The information that is stored in the cache after a task ran successfully.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedTask {
+    hash: TaskHash,
+}
+
+impl TaskHash {
+    /// Computes the hash of the given task. The `env` are the environment variables the task is
+    /// executed with.
+    ///
+    /// Returns `None` if the task does not define any `inputs`, in which case the task is never
+    /// cached. Without inputs there is no way to tell whether the outputs are stale.
+    pub fn from_task(
+        task: &ExecutableTask,
+        env: &HashMap<String, String>,
+    ) -> Result<Option<Self>, TaskCacheError> {
+        let inputs = task.task().inputs().unwrap_or_default();
+        let outputs = task.task().outputs().unwrap_or_default();
+        if inputs.is_empty() {
+            return Ok(None);
+        }
+
+        let root = task.project().root();
+        let mut state = String::new();
+        writeln!(
+            state,
+            "command: {}",
+            task.full_command().unwrap_or_default()
+        )
+        .unwrap();
+        if let Some(shell) = task.task().shell() {
+            writeln!(state, "shell: {shell}").unwrap();
+        }
+        for key in task.task().env().into_iter().flat_map(|env| env.keys()) {
+            let value = env.get(key).map(String::as_str).unwrap_or_default();
+            writeln!(state, "env: {key}={value}").unwrap();
+        }
+        for path in task.task().env_files() {
+            // Env files that do not exist are skipped when running the task.
+            if let Ok(hash) = compute_file_digest::<Sha256>(root.join(path)) {
+                writeln!(state, "env_file: {} {hash:x}", path.display()).unwrap();
+            }
+        }
+        for package in installed_packages(&task.run_environment.dir()) {
+            writeln!(state, "package: {package}").unwrap();
+        }
+        for (path, hash) in hash_files(root, inputs)? {
+            writeln!(state, "input: {path} {hash}").unwrap();
+        }
+        for (path, hash) in hash_files(root, outputs)? {
+            writeln!(state, "output: {path} {hash}").unwrap();
+        }
+
+        let hash = compute_bytes_digest::<Sha256>(state.as_bytes());
+        Ok(Some(Self(format!("{hash:x}"))))
+    }
+}
+
+/// Returns the hashes of all files that match any of the glob `patterns`, keyed by their path
+/// relative to `root`.
+fn hash_files(
+    root: &Path,
+    patterns: &[String],
+) -> Result<BTreeMap<String, String>, TaskCacheError> {
+    let escaped_root = glob::Pattern::escape(&root.to_string_lossy());
+    let mut hashes = BTreeMap::new();
+    for pattern in patterns {
+        let full_pattern = format!("{escaped_root}/{}", pattern.trim_start_matches("./"));
+        let paths = glob::glob(&full_pattern).map_err(|source| TaskCacheError::InvalidGlob {
+            pattern: pattern.clone(),
+            source,
+        })?;
+        for path in paths {
+            let path = path?;
+            if !path.is_file() {
+                continue;
+            }
+            let relative_path = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .join("/");
+            if hashes.contains_key(&relative_path) {
+                continue;
+            }
+            let hash = compute_file_digest::<Sha256>(&path)
+                .map_err(|source| TaskCacheError::FailedToHashFile { path, source })?;
+            hashes.insert(relative_path, format!("{hash:x}"));
+        }
+    }
+    Ok(hashes)
+}
+
+/// Returns the packages that are installed in the environment at `prefix`. The conda packages are
+/// identified by the names of their records in `conda-meta`, which contain the name, version and
+/// build string of the package. The python packages are identified by the names of their
+/// `.dist-info` directories, which contain the name and version of the package.
+fn installed_packages(prefix: &Path) -> Vec<String> {
+    let escaped_prefix = glob::Pattern::escape(&prefix.to_string_lossy());
+    let patterns = [
+        "conda-meta/*.json",
+        "lib/python*/site-packages/*.dist-info",
+        "Lib/site-packages/*.dist-info",
+    ];
+    patterns
+        .iter()
+        .filter_map(|pattern| glob::glob(&format!("{escaped_prefix}/{pattern}")).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|path| {
+            let relative_path = path.strip_prefix(prefix).ok()?;
+            Some(
+                relative_path
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .join("/"),
+            )
+        })
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// Returns the path of the file that stores the cached state of the task, or `None` if the task
+/// cannot be cached because it has no name.
+///
+/// The file is named after the hash of the name of the environment and the name of the task, so
+/// every combination of the two gets its own file whatever characters the names contain.
+fn cache_file(task: &ExecutableTask) -> Option<PathBuf> {
+    let name = task.name()?;
+    let key = format!("{}\0{}", task.run_environment.name().as_str(), name);
+    let hash = compute_bytes_digest::<Sha256>(key.as_bytes());
+    Some(
+        task.project()
+            .task_cache_dir()
+            .join(format!("{hash:x}.json")),
+    )
+}
+
+/// Returns true if the task ran successfully before with the same `hash`.
+pub(crate) fn is_up_to_date(task: &ExecutableTask, hash: &TaskHash) -> bool {
+    let Some(path) = cache_file(task) else {
+        return false;
+    };
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<CachedTask>(&contents).ok())
+        .is_some_and(|cached| &cached.hash == hash)
+}
+
+/// Stores the `hash` of a task that ran successfully in the cache.
+pub(crate) fn write_cache(task: &ExecutableTask, hash: TaskHash) -> Result<(), TaskCacheError> {
+    let Some(path) = cache_file(task) else {
+        return Ok(());
+    };
+    let contents = serde_json::to_string_pretty(&CachedTask { hash })
+        .expect("serializing the task cache cannot fail");
+    path.parent()
+        .map(std::fs::create_dir_all)
+        .transpose()
+        .and_then(|_| std::fs::write(&path, contents))
+        .map_err(|source| TaskCacheError::FailedToWriteCache { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_file, hash_files, installed_packages};
+    use crate::task::{ExecutableTask, TaskName};
+    use crate::Project;
+    use std::{borrow::Cow, path::Path};
+
+    #[test]
+    fn test_hash_files() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("src/nested")).unwrap();
+        std::fs::write(root.path().join("src/a.c"), "a").unwrap();
+        std::fs::write(root.path().join("src/nested/b.c"), "b").unwrap();
+        std::fs::write(root.path().join("src/c.h"), "c").unwrap();
+
+        let patterns = vec!["src/**/*.c".to_string(), "./src/a.c".to_string()];
+        let hashes = hash_files(root.path(), &patterns).unwrap();
+        assert_eq!(
+            hashes.keys().collect::<Vec<_>>(),
+            vec!["src/a.c", "src/nested/b.c"]
+        );
+
+        // Changing the contents of a file changes its hash
+        std::fs::write(root.path().join("src/a.c"), "changed").unwrap();
+        let changed = hash_files(root.path(), &patterns).unwrap();
+        assert_ne!(hashes["src/a.c"], changed["src/a.c"]);
+        assert_eq!(hashes["src/nested/b.c"], changed["src/nested/b.c"]);
+
+        // Patterns that match nothing are fine
+        let patterns = vec!["build/*.so".to_string()];
+        assert!(hash_files(root.path(), &patterns).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_installed_packages() {
+        let prefix = tempfile::tempdir().unwrap();
+        assert!(installed_packages(prefix.path()).is_empty());
+
+        std::fs::create_dir_all(prefix.path().join("conda-meta")).unwrap();
+        std::fs::write(prefix.path().join("conda-meta/history"), "").unwrap();
+        std::fs::write(prefix.path().join("conda-meta/zlib-1.3-h0_0.json"), "{}").unwrap();
+        std::fs::write(
+            prefix.path().join("conda-meta/python-3.12.1-h0_0.json"),
+            "{}",
+        )
+        .unwrap();
+        let site_packages = prefix.path().join("lib/python3.12/site-packages");
+        std::fs::create_dir_all(site_packages.join("rich-13.7.0.dist-info")).unwrap();
+        std::fs::create_dir_all(site_packages.join("rich")).unwrap();
+
+        assert_eq!(
+            installed_packages(prefix.path()),
+            vec![
+                "conda-meta/python-3.12.1-h0_0.json",
+                "conda-meta/zlib-1.3-h0_0.json",
+                "lib/python3.12/site-packages/rich-13.7.0.dist-info",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cache_file_is_unique() {
+        let project = Project::from_str(
+            Path::new(""),
+            r#"
+            [project]
+            name = "foo"
+            channels = []
+            platforms = []
+
+            [feature.a.tasks]
+            b-c = "echo a"
+
+            [feature.a-b.tasks]
+            c = "echo a-b"
+
+            [environments]
+            a = ["a"]
+            a-b = ["a-b"]
+            "#,
+        )
+        .unwrap();
+        let cache_file = |environment: &str, name: &str| {
+            let environment = project.environment(environment).unwrap();
+            let name = TaskName::from(name);
+            let task = environment.task(&name, None).unwrap();
+            cache_file(&ExecutableTask {
+                project: &project,
+                name: Some(name),
+                task: Cow::Borrowed(task),
+                run_environment: environment,
+                additional_args: Vec::new(),
+            })
+            .unwrap()
+        };
+        assert_ne!(cache_file("a", "b-c"), cache_file("a-b", "c"));
+    }
+}
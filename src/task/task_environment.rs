@@ -2,7 +2,7 @@ use crate::project::virtual_packages::verify_current_platform_has_required_virtu
 use crate::project::Environment;
 use crate::task::error::{AmbiguousTaskError, MissingTaskError};
 use crate::task::TaskName;
-use crate::{Project, Task};
+use crate::{EnvironmentName, Project, Task};
 use miette::Diagnostic;
 use rattler_conda_types::Platform;
 use thiserror::Error;
@@ -16,6 +16,25 @@ pub enum FindTaskSource<'p> {
 
 pub type TaskAndEnvironment<'p> = (Environment<'p>, &'p Task);
 
+/// Returns the name of the environment a task of the given environment prefers to run in. This is
+/// either set on the task itself, or on the feature that defines the task.
+pub fn preferred_environment<'p>(
+    environment: &Environment<'p>,
+    task: &'p Task,
+) -> Option<&'p EnvironmentName> {
+    task.environment().or_else(|| {
+        environment
+            .features(true)
+            .find(|feature| {
+                feature
+                    .targets
+                    .targets()
+                    .any(|target| target.tasks.values().any(|t| std::ptr::eq(t, task)))
+            })
+            .and_then(|feature| feature.task_environment.as_ref())
+    })
+}
+
 pub trait TaskDisambiguation<'p> {
     fn disambiguate(&self, task: &AmbiguousTask<'p>) -> Option<TaskAndEnvironment<'p>>;
 }
@@ -117,6 +136,17 @@ impl<'p, D: TaskDisambiguation<'p>> SearchEnvironments<'p, D> {
         name: TaskName,
         source: FindTaskSource<'p>,
     ) -> Result<TaskAndEnvironment<'p>, FindTaskError> {
+        // If no explicit environment was specified, use the environment the task prefers to run in
+        if self.explicit_environment.is_none() {
+            let preferred = self.project.environments().into_iter().find_map(|env| {
+                let task = env.task(&name, self.platform).ok()?;
+                (preferred_environment(&env, task) == Some(env.name())).then_some((env, task))
+            });
+            if let Some(preferred) = preferred {
+                return Ok(preferred);
+            }
+        }
+
         // If no explicit environment was specified
         if matches!(source, FindTaskSource::CmdArgs) && self.explicit_environment.is_none() {
             let default_env = self.project.default_environment();
@@ -336,4 +366,93 @@ mod tests {
         // Ambiguous task because it is the same name and code but it is defined in different environments
         assert!(matches!(result, Err(FindTaskError::AmbiguousTask(_))));
     }
+
+    #[test]
+    fn test_find_task_preferred_environment() {
+        let manifest_str = r#"
+            [project]
+            name = "foo"
+            channels = ["foo"]
+            platforms = ["linux-64", "osx-arm64", "win-64", "osx-64"]
+
+            [tasks]
+            test = { cmd = "pytest", environment = "test" }
+
+            [feature.test.tasks]
+            coverage = "pytest --cov"
+
+            [feature.lint]
+            task-environment = "lint"
+
+            [feature.lint.tasks]
+            lint = "ruff check ."
+            coverage = "echo no coverage"
+
+            [environments]
+            test = ["test"]
+            lint = ["lint"]
+            ci = ["test", "lint"]
+        "#;
+        let project = Project::from_str(Path::new(""), manifest_str).unwrap();
+        let search = SearchEnvironments::from_opt_env(&project, None, None);
+
+        // The environment of the task is used
+        let (env, _) = search
+            .find_task("test".into(), FindTaskSource::CmdArgs)
+            .unwrap();
+        assert_eq!(env.name().as_str(), "test");
+
+        // The environment of the feature that defines the task is used
+        let (env, _) = search
+            .find_task("lint".into(), FindTaskSource::CmdArgs)
+            .unwrap();
+        assert_eq!(env.name().as_str(), "lint");
+        let (env, task) = search
+            .find_task("coverage".into(), FindTaskSource::CmdArgs)
+            .unwrap();
+        assert_eq!(env.name().as_str(), "lint");
+        assert_eq!(
+            task.as_single_command().as_deref(),
+            Some("echo no coverage")
+        );
+
+        // An explicit environment overrides the preferred environment
+        let search = SearchEnvironments::from_opt_env(
+            &project,
+            Some(project.environment("ci").unwrap()),
+            None,
+        );
+        let (env, _) = search
+            .find_task("test".into(), FindTaskSource::CmdArgs)
+            .unwrap();
+        assert_eq!(env.name().as_str(), "ci");
+    }
+
+    #[test]
+    fn test_invalid_preferred_environment() {
+        let manifest_str = |environment: &str| {
+            format!(
+                r#"
+            [project]
+            name = "foo"
+            channels = ["foo"]
+            platforms = ["linux-64"]
+
+            [feature.lint.tasks]
+            lint = {{ cmd = "ruff check .", environment = "{environment}" }}
+
+            [feature.test.dependencies]
+
+            [environments]
+            lint = ["lint"]
+            test = ["test"]
+        "#
+            )
+        };
+        assert!(Project::from_str(Path::new(""), &manifest_str("lint")).is_ok());
+        // The environment does not exist
+        assert!(Project::from_str(Path::new(""), &manifest_str("docs")).is_err());
+        // The environment does not include the task
+        assert!(Project::from_str(Path::new(""), &manifest_str("test")).is_err());
+    }
 }
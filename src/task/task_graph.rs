@@ -1,18 +1,24 @@
 use crate::project::Environment;
 use crate::task::error::AmbiguousTaskError;
-use crate::task::task_environment::{FindTaskError, FindTaskSource, SearchEnvironments};
-use crate::task::{TaskDisambiguation, TaskName};
+use crate::task::executable_task::{bind_arguments, render_command, render_template};
+use crate::task::task_environment::{
+    preferred_environment, FindTaskError, FindTaskSource, SearchEnvironments,
+};
+use crate::task::{quote_arguments, TaskDisambiguation, TaskExecutionError, TaskName};
 use crate::{
     task::{error::MissingTaskError, CmdArgs, Custom, Task},
-    Project,
+    EnvironmentName, Project,
 };
 use itertools::Itertools;
 use miette::Diagnostic;
+use serde::Serialize;
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
     env, fmt,
+    fmt::Write,
     ops::Index,
+    path::{Path, PathBuf},
 };
 use thiserror::Error;
 
@@ -60,7 +66,8 @@ impl fmt::Display for TaskNode<'_> {
 
 impl<'p> TaskNode<'p> {
     /// Returns the full command that should be executed for this task. This includes any
-    /// additional arguments that should be passed to the command.
+    /// additional arguments that should be passed to the command, quoted the same way as when
+    /// the task is executed.
     ///
     /// This function returns `None` if the task does not define a command to execute. This is the
     /// case for alias only commands.
@@ -69,7 +76,9 @@ impl<'p> TaskNode<'p> {
 
         if !self.additional_args.is_empty() {
             cmd.push(' ');
-            cmd.push_str(&self.additional_args.join(" "));
+            cmd.push_str(&quote_arguments(
+                self.additional_args.iter().map(String::as_str),
+            ));
         }
 
         Some(cmd)
@@ -172,53 +181,117 @@ impl<'p> TaskGraph<'p> {
     }
 
     /// Constructs a new instance of a [`TaskGraph`] from a root task.
+    ///
+    /// Dependencies that name an environment run in that environment, and so do the dependencies
+    /// of such a task that don't name an environment themselves. The same holds for tasks that run
+    /// in the environment they prefer.
     fn from_root<D: TaskDisambiguation<'p>>(
         project: &'p Project,
         search_environments: &SearchEnvironments<'p, D>,
         root: TaskNode<'p>,
     ) -> Result<Self, TaskGraphError> {
-        let mut task_name_to_node: HashMap<TaskName, TaskId> =
-            HashMap::from_iter(root.name.clone().into_iter().map(|name| (name, TaskId(0))));
+        let mut task_name_to_node: HashMap<
+            (TaskName, Vec<String>, Option<EnvironmentName>),
+            TaskId,
+        > = HashMap::from_iter(
+            root.name
+                .clone()
+                .into_iter()
+                .map(|name| ((name, root.additional_args.clone(), None), TaskId(0))),
+        );
+        // Whether the environment of a node was selected by a dependency that names it, or is the
+        // environment the task prefers.
+        let mut pinned_environment = vec![is_preferred_environment(&root)];
         let mut nodes = vec![root];
 
         // Iterate over all the nodes in the graph and add them to the graph.
         let mut next_node_to_visit = 0;
         while next_node_to_visit < nodes.len() {
-            let dependency_names =
+            let dependencies =
                 Vec::from_iter(nodes[next_node_to_visit].task.depends_on().iter().cloned());
 
+            // The values of the arguments of this node, which can be passed on to its
+            // dependencies.
+            let arguments = if dependencies.iter().any(|dep| dep.args.is_some()) {
+                let node = &nodes[next_node_to_visit];
+                bind_arguments(node.name.as_ref(), &node.task, &node.additional_args)?
+            } else {
+                None
+            };
+
             // Iterate over all the dependencies of the node and add them to the graph.
-            let mut node_dependencies = Vec::with_capacity(dependency_names.len());
-            for dependency in dependency_names {
+            let mut node_dependencies = Vec::with_capacity(dependencies.len());
+            for dependency in dependencies {
+                let args = dependency
+                    .args
+                    .iter()
+                    .flatten()
+                    .map(|arg| match &arguments {
+                        Some(arguments) => {
+                            render_template(nodes[next_node_to_visit].name.as_ref(), arg, arguments)
+                        }
+                        None => Ok(arg.clone()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let node = &nodes[next_node_to_visit];
+                let environment = match dependency.environment {
+                    Some(name) => Some(project.environment(&name).ok_or_else(|| {
+                        TaskGraphError::UnknownEnvironment {
+                            task_name: dependency.task_name.clone(),
+                            environment: name.clone(),
+                        }
+                    })?),
+                    None if pinned_environment[next_node_to_visit] => {
+                        Some(node.run_environment.clone())
+                    }
+                    None => None,
+                };
+                let dependency = dependency.task_name;
+
                 // Check if we visited this node before already.
-                if let Some(&task_id) = task_name_to_node.get(&dependency) {
+                let key = (
+                    dependency.clone(),
+                    args,
+                    environment.as_ref().map(|env| env.name().clone()),
+                );
+                if let Some(&task_id) = task_name_to_node.get(&key) {
                     node_dependencies.push(task_id);
                     continue;
                 }
 
-                // Find the task in the project
-                let node = &nodes[next_node_to_visit];
-                let (task_env, task_dependency) = match search_environments.find_task(
-                    dependency.clone(),
-                    FindTaskSource::DependsOn(
-                        node.name
-                            .clone()
-                            .expect("only named tasks can have dependencies"),
-                        match &node.task {
-                            Cow::Borrowed(task) => task,
-                            Cow::Owned(_) => {
-                                unreachable!("only named tasks can have dependencies")
-                            }
-                        },
-                    ),
-                ) {
-                    Err(FindTaskError::MissingTask(err)) => {
-                        return Err(TaskGraphError::MissingTask(err))
-                    }
-                    Err(FindTaskError::AmbiguousTask(err)) => {
-                        return Err(TaskGraphError::AmbiguousTask(err))
+                // Find the task in the environment of the dependency, or search for it in the
+                // project
+                let (task_env, task_dependency) = if let Some(environment) = environment {
+                    let task = environment
+                        .task(&dependency, search_environments.platform)
+                        .map_err(|_| TaskGraphError::MissingTaskInEnvironment {
+                            task_name: dependency.clone(),
+                            environment: environment.name().clone(),
+                        })?;
+                    (environment, task)
+                } else {
+                    match search_environments.find_task(
+                        dependency.clone(),
+                        FindTaskSource::DependsOn(
+                            node.name
+                                .clone()
+                                .expect("only named tasks can have dependencies"),
+                            match &node.task {
+                                Cow::Borrowed(task) => task,
+                                Cow::Owned(_) => {
+                                    unreachable!("only named tasks can have dependencies")
+                                }
+                            },
+                        ),
+                    ) {
+                        Err(FindTaskError::MissingTask(err)) => {
+                            return Err(TaskGraphError::MissingTask(err))
+                        }
+                        Err(FindTaskError::AmbiguousTask(err)) => {
+                            return Err(TaskGraphError::AmbiguousTask(err))
+                        }
+                        Ok(result) => result,
                     }
-                    Ok(result) => result,
                 };
 
                 // Add the node to the graph
@@ -227,12 +300,14 @@ impl<'p> TaskGraph<'p> {
                     name: Some(dependency.clone()),
                     task: Cow::Borrowed(task_dependency),
                     run_environment: task_env,
-                    additional_args: Vec::new(),
+                    additional_args: key.1.clone(),
                     dependencies: Vec::new(),
                 });
+                pinned_environment
+                    .push(key.2.is_some() || is_preferred_environment(&nodes[task_id.0]));
 
                 // Store the task id in the map to be able to look up the name later
-                task_name_to_node.insert(dependency.clone(), task_id);
+                task_name_to_node.insert(key, task_id);
 
                 // Add the dependency to the node
                 node_dependencies.push(task_id);
@@ -242,6 +317,15 @@ impl<'p> TaskGraph<'p> {
             next_node_to_visit += 1;
         }
 
+        // Render the values of the arguments of the tasks into their commands.
+        for node in nodes.iter_mut() {
+            let name = node.name.as_ref();
+            if let Some(arguments) = bind_arguments(name, &node.task, &node.additional_args)? {
+                node.task = Cow::Owned(render_command(name, &node.task, &arguments)?);
+                node.additional_args = Vec::new();
+            }
+        }
+
         Ok(Self { project, nodes })
     }
 
@@ -278,6 +362,210 @@ impl<'p> TaskGraph<'p> {
     }
 }
 
+/// Returns true if the node runs in the environment its task prefers.
+fn is_preferred_environment(node: &TaskNode<'_>) -> bool {
+    match &node.task {
+        Cow::Borrowed(task) => {
+            preferred_environment(&node.run_environment, task) == Some(node.run_environment.name())
+        }
+        Cow::Owned(_) => false,
+    }
+}
+
+/// The format in which a [`TaskGraph`] is printed by [`TaskGraph::format`].
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskGraphFormat {
+    /// A tree of the tasks with the tasks they depend on as children.
+    #[default]
+    Tree,
+
+    /// A Graphviz DOT graph.
+    Dot,
+
+    /// A JSON list of the tasks in the order they are executed.
+    Json,
+}
+
+/// The information of a [`TaskNode`] that is printed as part of a [`TaskGraph`].
+#[derive(Debug, Serialize)]
+struct TaskNodeInfo {
+    id: usize,
+    name: Option<String>,
+    environment: String,
+    cwd: PathBuf,
+    command: Option<String>,
+    depends_on: Vec<usize>,
+}
+
+impl TaskNodeInfo {
+    /// Returns the name of the task, or the command for custom tasks.
+    fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.command.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Returns the working directory relative to the root of the project if possible.
+    fn display_cwd(&self, root: &Path) -> String {
+        match self.cwd.strip_prefix(root) {
+            Ok(path) if path.as_os_str().is_empty() => String::from("."),
+            Ok(path) => path.display().to_string(),
+            Err(_) => self.cwd.display().to_string(),
+        }
+    }
+}
+
+impl<'p> TaskGraph<'p> {
+    /// Returns a textual representation of the graph in the given format. The graph is only
+    /// described, nothing is installed or executed.
+    pub fn format(&self, format: TaskGraphFormat) -> String {
+        let nodes = self.node_infos();
+        match format {
+            TaskGraphFormat::Tree => self.format_tree(&nodes),
+            TaskGraphFormat::Dot => self.format_dot(&nodes),
+            TaskGraphFormat::Json => serde_json::to_string_pretty(
+                &self
+                    .topological_order()
+                    .into_iter()
+                    .map(|id| &nodes[id.0])
+                    .collect_vec(),
+            )
+            .expect("serializing the task graph cannot fail"),
+        }
+    }
+
+    /// Returns the information of all nodes, indexed by their id.
+    fn node_infos(&self) -> Vec<TaskNodeInfo> {
+        let root = self.project.root();
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| TaskNodeInfo {
+                id,
+                name: node.name.as_ref().map(|name| name.as_str().to_string()),
+                environment: node.run_environment.name().as_str().to_string(),
+                cwd: match node.task.working_directory() {
+                    Some(cwd) => root.join(cwd),
+                    None => root.to_path_buf(),
+                },
+                command: node.full_command(),
+                depends_on: node.dependencies.iter().map(|id| id.0).collect(),
+            })
+            .collect()
+    }
+
+    /// Formats the graph as a tree starting at the tasks that no other task depends on.
+    fn format_tree(&self, nodes: &[TaskNodeInfo]) -> String {
+        let depended_on: HashSet<usize> = nodes
+            .iter()
+            .flat_map(|node| node.depends_on.iter().copied())
+            .collect();
+        let mut output = String::new();
+        let mut visited = HashSet::new();
+        for node in nodes.iter().filter(|node| !depended_on.contains(&node.id)) {
+            self.format_tree_node(nodes, node.id, "", None, &mut visited, &mut output);
+        }
+        output
+    }
+
+    /// Writes a node of the tree and recursively the tasks it depends on. `is_last` is `None` for
+    /// the roots of the tree.
+    fn format_tree_node(
+        &self,
+        nodes: &[TaskNodeInfo],
+        id: usize,
+        prefix: &str,
+        is_last: Option<bool>,
+        visited: &mut HashSet<usize>,
+        output: &mut String,
+    ) {
+        let node = &nodes[id];
+        let connector = match is_last {
+            None => "",
+            Some(true) => "└── ",
+            Some(false) => "├── ",
+        };
+        let command = node
+            .command
+            .as_ref()
+            .map(|command| format!(" $ {command}"))
+            .unwrap_or_default();
+        let first_visit = visited.insert(id);
+        writeln!(
+            output,
+            "{prefix}{connector}{} {}{}{}",
+            console::style(node.display_name()).blue().bold(),
+            console::style(format!(
+                "(environment: {}, cwd: {})",
+                node.environment,
+                node.display_cwd(self.project.root())
+            ))
+            .dim(),
+            command,
+            if first_visit { "" } else { " (*)" }
+        )
+        .unwrap();
+
+        // The dependencies of a task that was already printed are not printed again.
+        if !first_visit {
+            return;
+        }
+        let child_prefix = match is_last {
+            None => prefix.to_string(),
+            Some(true) => format!("{prefix}    "),
+            Some(false) => format!("{prefix}│   "),
+        };
+        for (idx, &dependency) in node.depends_on.iter().enumerate() {
+            let is_last = idx + 1 == node.depends_on.len();
+            self.format_tree_node(
+                nodes,
+                dependency,
+                &child_prefix,
+                Some(is_last),
+                visited,
+                output,
+            );
+        }
+    }
+
+    /// Formats the graph as a Graphviz DOT graph with an edge from every task to the tasks it
+    /// depends on.
+    fn format_dot(&self, nodes: &[TaskNodeInfo]) -> String {
+        let escape = |value: &str| {
+            value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        };
+        let mut output = String::from("digraph tasks {\n    node [shape=box];\n");
+        for node in nodes {
+            let mut label = vec![
+                escape(&node.display_name()),
+                format!("environment: {}", escape(&node.environment)),
+                format!("cwd: {}", escape(&node.display_cwd(self.project.root()))),
+            ];
+            if let Some(command) = &node.command {
+                label.push(format!("$ {}", escape(command)));
+            }
+            writeln!(
+                output,
+                "    task{} [label=\"{}\"];",
+                node.id,
+                label.join("\\n")
+            )
+            .unwrap();
+        }
+        for node in nodes {
+            for dependency in &node.depends_on {
+                writeln!(output, "    task{} -> task{dependency};", node.id).unwrap();
+            }
+        }
+        output.push_str("}\n");
+        output
+    }
+}
+
 #[derive(Debug, Error, Diagnostic)]
 pub enum TaskGraphError {
     #[error(transparent)]
@@ -286,12 +574,29 @@ pub enum TaskGraphError {
     #[error(transparent)]
     #[diagnostic(transparent)]
     AmbiguousTask(AmbiguousTaskError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    InvalidArguments(#[from] TaskExecutionError),
+
+    #[error("the environment '{}' that '{}' should run in does not exist", environment.fancy_display(), task_name.fancy_display())]
+    UnknownEnvironment {
+        task_name: TaskName,
+        environment: EnvironmentName,
+    },
+
+    #[error("could not find the task '{}' in the environment '{}'", task_name.fancy_display(), environment.fancy_display())]
+    MissingTaskInEnvironment {
+        task_name: TaskName,
+        environment: EnvironmentName,
+    },
 }
 
 #[cfg(test)]
 mod test {
     use crate::task::task_environment::SearchEnvironments;
-    use crate::task::task_graph::TaskGraph;
+    use crate::task::task_graph::{TaskGraph, TaskGraphError, TaskGraphFormat};
+    use crate::task::TaskExecutionError;
     use crate::{EnvironmentName, Project};
     use rattler_conda_types::Platform;
     use std::path::Path;
@@ -310,7 +615,7 @@ mod test {
         let graph = TaskGraph::from_cmd_args(
             &project,
             &search_envs,
-            run_args.into_iter().map(|arg| arg.to_string()).collect(),
+            run_args.iter().map(|arg| arg.to_string()).collect(),
         )
         .unwrap();
 
@@ -411,6 +716,26 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_additional_args_are_quoted() {
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+        [tasks]
+        top = "echo top"
+    "#,
+                &["top", "a b", "c"],
+                None,
+                None
+            ),
+            vec![r#"echo top "a b" c"#]
+        );
+    }
+
     #[test]
     fn test_multi_env() {
         assert_eq!(
@@ -492,4 +817,220 @@ mod test {
             vec![r#"python train.py --cuda"#, r#"python test.py --cuda"#]
         );
     }
+
+    #[test]
+    fn test_cross_environment_dependencies() {
+        let project = Project::from_str(
+            Path::new(""),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+
+        [tasks]
+        release = { cmd = "echo release", depends_on = [{ task = "build-docs", environment = "docs" }] }
+        build = "echo build"
+
+        [feature.docs.tasks]
+        build-docs = { cmd = "mkdocs build", depends_on = ["build"] }
+        build = "echo build docs"
+
+        [environments]
+        docs = ["docs"]
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["release".to_string()]).unwrap();
+
+        // Dependencies of a task that runs in another environment run in that environment too.
+        let commands = graph
+            .topological_order()
+            .into_iter()
+            .map(|id| {
+                let node = &graph[id];
+                format!(
+                    "{} ({})",
+                    node.full_command().unwrap(),
+                    node.run_environment.name()
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            vec![
+                "echo build docs (docs)",
+                "mkdocs build (docs)",
+                "echo release (default)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cross_environment_dependency_errors() {
+        let project = Project::from_str(
+            Path::new(""),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+
+        [tasks]
+        release = { depends_on = [{ task = "build-docs", environment = "docs" }] }
+
+        [feature.docs.dependencies]
+
+        [environments]
+        docs = ["docs"]
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        assert!(matches!(
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["release".to_string()]),
+            Err(TaskGraphError::MissingTaskInEnvironment { task_name, environment })
+                if task_name == "build-docs".into() && environment.as_str() == "docs"
+        ));
+
+        // Environments that don't exist are rejected when the manifest is read.
+        assert!(Project::from_str(
+            Path::new(""),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+
+        [tasks]
+        build-docs = "mkdocs build"
+        release = { depends_on = [{ task = "build-docs", environment = "docs" }] }
+    "#,
+        )
+        .is_err());
+    }
+
+    const ARGS_PROJECT: &str = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+
+        [tasks]
+        build = { cmd = "cargo build --profile {{ target }}", args = [{ name = "target", default = "debug" }] }
+        test = { cmd = "cargo test --profile {{ target }}", args = [{ name = "target" }], depends_on = [{ task = "build", args = ["{{ target }}"] }] }
+        release = { depends_on = [{ task = "build", args = ["release"] }] }
+    "#;
+
+    #[test]
+    fn test_task_args() {
+        assert_eq!(
+            commands_in_order(ARGS_PROJECT, &["build"], None, None),
+            vec!["cargo build --profile debug"]
+        );
+        assert_eq!(
+            commands_in_order(ARGS_PROJECT, &["build", "dist"], None, None),
+            vec!["cargo build --profile dist"]
+        );
+        assert_eq!(
+            commands_in_order(ARGS_PROJECT, &["release"], None, None),
+            vec!["cargo build --profile release"]
+        );
+        assert_eq!(
+            commands_in_order(ARGS_PROJECT, &["test", "dist"], None, None),
+            vec!["cargo build --profile dist", "cargo test --profile dist"]
+        );
+
+        // Values are quoted so they are never executed as shell code.
+        assert_eq!(
+            commands_in_order(ARGS_PROJECT, &["test", "my profile; rm -rf ~"], None, None),
+            vec![
+                "cargo build --profile 'my profile; rm -rf ~'",
+                "cargo test --profile 'my profile; rm -rf ~'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_args_are_quoted_for_the_shell() {
+        let project_str = r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = []
+
+        [tasks]
+        bash = { cmd = "echo {{ value }}", args = [{ name = "value" }], shell = "bash" }
+        python = { cmd = "print({{ value }})", args = [{ name = "value" }], shell = "python" }
+        pwsh = { cmd = "Write-Output {{ value }}", args = [{ name = "value" }], shell = "pwsh" }
+        "#;
+        assert_eq!(
+            commands_in_order(project_str, &["bash", "it's"], None, None),
+            vec![r#"echo 'it'"'"'s'"#]
+        );
+        assert_eq!(
+            commands_in_order(project_str, &["python", "it's"], None, None),
+            vec![r"print('it\'s')"]
+        );
+        assert_eq!(
+            commands_in_order(project_str, &["pwsh", "it's"], None, None),
+            vec!["Write-Output 'it''s'"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_task_args() {
+        let project = Project::from_str(Path::new(""), ARGS_PROJECT).unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        let graph_error = |args: &[&str]| {
+            TaskGraph::from_cmd_args(
+                &project,
+                &search_envs,
+                args.iter().map(|arg| arg.to_string()).collect(),
+            )
+            .unwrap_err()
+        };
+
+        assert!(matches!(
+            graph_error(&["test"]),
+            TaskGraphError::InvalidArguments(TaskExecutionError::MissingArgument { task, argument })
+                if task == "test" && argument == "target"
+        ));
+        assert!(matches!(
+            graph_error(&["build", "dist", "extra"]),
+            TaskGraphError::InvalidArguments(TaskExecutionError::TooManyArguments {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_format_task_graph() {
+        let project = Project::from_str(
+            Path::new(""),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+        [tasks]
+        root = "echo root"
+        task1 = {cmd="echo task1", depends_on=["root"]}
+        task2 = {cmd="echo \"task2\"", depends_on=["root"], cwd="sub"}
+        top = {depends_on=["task1","task2"]}
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["top".to_string()]).unwrap();
+
+        insta::assert_snapshot!("tree", graph.format(TaskGraphFormat::Tree));
+        insta::assert_snapshot!("dot", graph.format(TaskGraphFormat::Dot));
+        insta::assert_snapshot!("json", graph.format(TaskGraphFormat::Json));
+    }
 }
@@ -0,0 +1,363 @@
+use deno_task_shell::{
+    parser::{Command, CommandInner, PipelineInner, Sequence, SequentialList, WordPart},
+    ExecuteResult, ShellCommand, ShellCommandContext, ShellState,
+};
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// The processes that were started by a single execution of a task.
+///
+/// The executables that the script of the task invokes are started by the commands returned from
+/// [`Self::custom_commands`], which record the id of every process they start. This way the
+/// process tree below the processes of the task can be killed without touching the processes of
+/// other tasks that run at the same time.
+#[derive(Debug, Clone, Default)]
+pub struct TaskProcesses {
+    pids: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl TaskProcesses {
+    /// Returns the custom deno task shell commands that start the executables invoked by `script`.
+    ///
+    /// Only commands whose name is known before the script runs can be recorded, a command like
+    /// `$EDITOR` is started by deno task shell itself.
+    pub fn custom_commands(
+        &self,
+        script: &SequentialList,
+        cwd: &Path,
+    ) -> HashMap<String, Rc<dyn ShellCommand>> {
+        let mut names = HashSet::new();
+        collect_command_names(script, &mut names);
+
+        // Builtin commands like `echo` don't start a process.
+        let builtins = ShellState::new(HashMap::new(), cwd, HashMap::new());
+        names
+            .into_iter()
+            .filter(|name| builtins.resolve_command(name).is_none())
+            .map(|name| {
+                let command = RecordedCommand {
+                    command_name: name.clone(),
+                    processes: self.clone(),
+                };
+                (name, Rc::new(command) as Rc<dyn ShellCommand>)
+            })
+            .collect()
+    }
+
+    /// Kills all processes of the task that are still running, together with the processes they
+    /// started.
+    pub fn kill(&self) {
+        let pids = std::mem::take(&mut *self.pids.lock().unwrap());
+        kill_process_trees(&pids);
+    }
+
+    /// Records a process that was started by the task.
+    fn insert(&self, pid: u32) {
+        self.pids.lock().unwrap().insert(pid);
+    }
+
+    /// Removes a process of the task that exited.
+    fn remove(&self, pid: u32) {
+        self.pids.lock().unwrap().remove(&pid);
+    }
+}
+
+/// Kills the processes with the given ids together with all the processes they started.
+fn kill_process_trees(pids: &HashSet<u32>) {
+    if pids.is_empty() {
+        return;
+    }
+    let mut system = sysinfo::System::new();
+    system.refresh_processes_specifics(sysinfo::ProcessRefreshKind::new());
+
+    // Threads are listed as processes as well on some platforms, they are skipped because killing
+    // a thread kills the process it belongs to.
+    let mut children: HashMap<sysinfo::Pid, Vec<&sysinfo::Process>> = HashMap::new();
+    for process in system.processes().values() {
+        if process.thread_kind().is_some() {
+            continue;
+        }
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(process);
+        }
+    }
+
+    let mut queue = pids
+        .iter()
+        .filter_map(|&pid| system.process(sysinfo::Pid::from_u32(pid)))
+        .collect::<Vec<_>>();
+    while let Some(process) = queue.pop() {
+        process.kill();
+        queue.extend(children.get(&process.pid()).into_iter().flatten().copied());
+    }
+}
+
+/// Adds the names of the commands that are invoked by `list` to `names`. Names that contain
+/// variables are skipped, because they are only known when the script runs.
+fn collect_command_names(list: &SequentialList, names: &mut HashSet<String>) {
+    for item in &list.items {
+        collect_sequence_command_names(&item.sequence, names);
+    }
+}
+
+fn collect_sequence_command_names(sequence: &Sequence, names: &mut HashSet<String>) {
+    match sequence {
+        Sequence::ShellVar(var) => collect_word_command_names(var.value.parts(), names),
+        Sequence::Pipeline(pipeline) => collect_pipeline_command_names(&pipeline.inner, names),
+        Sequence::BooleanList(list) => {
+            collect_sequence_command_names(&list.current, names);
+            collect_sequence_command_names(&list.next, names);
+        }
+    }
+}
+
+fn collect_pipeline_command_names(pipeline: &PipelineInner, names: &mut HashSet<String>) {
+    match pipeline {
+        PipelineInner::Command(command) => collect_command_command_names(command, names),
+        PipelineInner::PipeSequence(sequence) => {
+            collect_command_command_names(&sequence.current, names);
+            collect_pipeline_command_names(&sequence.next, names);
+        }
+    }
+}
+
+fn collect_command_command_names(command: &Command, names: &mut HashSet<String>) {
+    match &command.inner {
+        CommandInner::Simple(command) => {
+            if let Some(name) = command.args.first().and_then(|word| literal(word.parts())) {
+                names.insert(name);
+            }
+            for var in &command.env_vars {
+                collect_word_command_names(var.value.parts(), names);
+            }
+            for word in &command.args {
+                collect_word_command_names(word.parts(), names);
+            }
+        }
+        CommandInner::Subshell(list) => collect_command_names(list, names),
+    }
+}
+
+/// Adds the names of the commands that are invoked by command substitutions in a word.
+fn collect_word_command_names(parts: &[WordPart], names: &mut HashSet<String>) {
+    for part in parts {
+        match part {
+            WordPart::Command(list) => collect_command_names(list, names),
+            WordPart::Quoted(parts) => collect_word_command_names(parts, names),
+            WordPart::Text(_) | WordPart::Variable(_) => {}
+        }
+    }
+}
+
+/// Returns the text of a word that doesn't contain variables or command substitutions.
+fn literal(parts: &[WordPart]) -> Option<String> {
+    let mut text = String::new();
+    for part in parts {
+        match part {
+            WordPart::Text(part) => text.push_str(part),
+            WordPart::Quoted(parts) => text.push_str(&literal(parts)?),
+            WordPart::Variable(_) | WordPart::Command(_) => return None,
+        }
+    }
+    Some(text)
+}
+
+/// A command that starts an executable in a separate process like deno task shell does, but records
+/// the process in [`TaskProcesses`].
+struct RecordedCommand {
+    command_name: String,
+    processes: TaskProcesses,
+}
+
+impl ShellCommand for RecordedCommand {
+    fn execute(&self, context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        let command_name = self.command_name.clone();
+        let processes = self.processes.clone();
+        async move {
+            let mut stderr = context.stderr;
+            let Some(command_path) = resolve_command_path(&command_name, &context.state) else {
+                let _ = stderr.write_line(&format!("{command_name}: command not found"));
+                // Use the exit status that is used by bash and deno task shell.
+                return ExecuteResult::from_exit_code(127);
+            };
+
+            let mut command = std::process::Command::new(&command_path);
+            command
+                .current_dir(context.state.cwd())
+                .args(&context.args)
+                .env_clear()
+                .envs(context.state.env_vars())
+                .stdout(context.stdout.into_stdio())
+                .stdin(context.stdin.into_stdio())
+                .stderr(stderr.clone().into_stdio());
+            let mut command = tokio::process::Command::from(command);
+            let child = command.spawn();
+
+            // Avoid a deadlock, the command holds on to the pipes.
+            drop(command);
+
+            let mut child = match child {
+                Ok(child) => child,
+                Err(err) => {
+                    let _ = stderr.write_line(&format!("Error launching '{command_name}': {err}"));
+                    return ExecuteResult::from_exit_code(1);
+                }
+            };
+            let pid = child.id();
+            if let Some(pid) = pid {
+                processes.insert(pid);
+            }
+
+            let result = tokio::select! {
+                result = child.wait() => match result {
+                    Ok(status) => ExecuteResult::from_exit_code(status.code().unwrap_or(1)),
+                    Err(err) => {
+                        let _ = stderr.write_line(&format!("{err}"));
+                        ExecuteResult::from_exit_code(1)
+                    }
+                },
+                _ = context.state.token().cancelled() => {
+                    let _ = child.kill().await;
+                    ExecuteResult::for_cancellation()
+                }
+            };
+            if let Some(pid) = pid {
+                processes.remove(pid);
+            }
+            result
+        }
+        .boxed_local()
+    }
+}
+
+/// Resolves the path of the executable `command_name` the same way deno task shell does.
+fn resolve_command_path(command_name: &str, state: &ShellState) -> Option<PathBuf> {
+    if command_name.is_empty() {
+        return None;
+    }
+
+    // Absolute and relative paths are used as is.
+    let path = PathBuf::from(command_name);
+    if path.is_absolute() {
+        return Some(path);
+    }
+    if command_name.contains('/') || (cfg!(windows) && command_name.contains('\\')) {
+        return Some(state.cwd().join(command_name));
+    }
+
+    // Otherwise search the working directory and the `PATH`, on windows with the extensions in
+    // `PATHEXT` unless the name already has one of them.
+    let extensions = if cfg!(windows) {
+        let path_ext = state
+            .get_var("PATHEXT")
+            .map(String::as_str)
+            .unwrap_or(".EXE;.CMD;.BAT;.COM");
+        let extensions = path_ext
+            .split(';')
+            .map(|ext| ext.trim().to_uppercase())
+            .filter(|ext| !ext.is_empty())
+            .collect::<Vec<_>>();
+        let upper_name = command_name.to_uppercase();
+        if extensions.iter().any(|ext| upper_name.ends_with(ext)) {
+            vec![String::new()]
+        } else {
+            extensions
+        }
+    } else {
+        vec![String::new()]
+    };
+    let path_separator = if cfg!(windows) { ';' } else { ':' };
+    let search_dirs = std::iter::once(state.cwd().clone()).chain(
+        state
+            .get_var("PATH")
+            .into_iter()
+            .flat_map(|path| path.split(path_separator).map(PathBuf::from)),
+    );
+    for dir in search_dirs {
+        for extension in &extensions {
+            let path = dir.join(format!("{command_name}{extension}"));
+            if std::fs::metadata(&path).is_ok_and(|metadata| metadata.is_file()) {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_collect_command_names() {
+        let script = deno_task_shell::parser::parse(
+            "FOO=$(git describe) python -c 'print(1)' && (\"my tool\" | wc -l) || $EDITOR file",
+        )
+        .unwrap();
+        let mut names = HashSet::new();
+        collect_command_names(&script, &mut names);
+        assert_eq!(
+            names,
+            HashSet::from_iter(["git", "python", "my tool", "wc"].map(String::from))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_process_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let script =
+            deno_task_shell::parser::parse("sh -c 'sleep 60 & echo $! > bg.pid; wait'").unwrap();
+        let processes = TaskProcesses::default();
+        let execute_future = deno_task_shell::execute(
+            script.clone(),
+            std::env::vars().collect(),
+            dir.path(),
+            processes.custom_commands(&script, dir.path()),
+        );
+        tokio::pin!(execute_future);
+
+        // Wait until the process in the background was started, then kill the task.
+        let pid_file = dir.path().join("bg.pid");
+        let mut background_pid = None;
+        for _ in 0..100 {
+            tokio::select! {
+                _ = &mut execute_future => panic!("the script should still be running"),
+                _ = tokio::time::sleep(Duration::from_millis(50)) => {},
+            }
+            background_pid = std::fs::read_to_string(&pid_file)
+                .ok()
+                .and_then(|pid| pid.trim().parse::<u32>().ok());
+            if background_pid.is_some() {
+                break;
+            }
+        }
+        let background_pid = sysinfo::Pid::from_u32(background_pid.unwrap());
+        processes.kill();
+        let exit_code = tokio::time::timeout(Duration::from_secs(10), execute_future)
+            .await
+            .unwrap();
+        assert_ne!(exit_code, 0);
+
+        // The process in the background was killed as well.
+        let is_running = || {
+            let mut system = sysinfo::System::new();
+            system.refresh_processes_specifics(sysinfo::ProcessRefreshKind::new());
+            system
+                .process(background_pid)
+                .is_some_and(|process| process.status() != sysinfo::ProcessStatus::Zombie)
+        };
+        for _ in 0..50 {
+            if !is_running() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(!is_running());
+    }
+}
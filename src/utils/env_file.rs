@@ -0,0 +1,337 @@
+//! Reading of dotenv files, files with lines of `KEY=VALUE` that define environment variables.
+//!
+//! The supported syntax is:
+//!
+//! - Empty lines and lines starting with `#` are ignored.
+//! - Lines can start with `export `, which is ignored.
+//! - Unquoted values end at the end of the line or at a ` #` comment and are trimmed.
+//! - Single-quoted values are used literally and can span multiple lines.
+//! - Double-quoted values can span multiple lines and support the escapes `\n`, `\r`, `\t`, `\"`
+//!   and `\\`.
+//! - Variables in unquoted and double-quoted values, written as `$VAR` or `${VAR}`, are replaced by
+//!   their value.
+
+use crate::activation::expand_env_vars;
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum EnvFileError {
+    #[error("failed to read env file '{}'", .path.display())]
+    Io {
+        path: std::path::PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Parse(Box<EnvFileParseError>),
+}
+
+/// An error in the contents of an env file, with the location of the error in the file.
+#[derive(Debug, Error, Diagnostic)]
+#[error("failed to parse env file '{name}'")]
+pub struct EnvFileParseError {
+    name: String,
+    #[source_code]
+    source_code: NamedSource<String>,
+    #[label("{message}")]
+    span: SourceSpan,
+    message: String,
+}
+
+/// A variable defined in an env file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EnvFileVariable {
+    key: String,
+    value: String,
+    /// True if `$VAR` references in the value should be replaced by their value.
+    expand: bool,
+}
+
+/// Reads the env files at `paths`, in order, and sets the variables they define in `env`. Variables
+/// of later files override those of earlier files and values can refer to variables already in
+/// `env`.
+///
+/// Files that do not exist are skipped with a warning.
+pub fn read_env_files(
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    env: &mut HashMap<String, String>,
+) -> Result<(), EnvFileError> {
+    for path in paths {
+        let path = path.as_ref();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!("Could not find env file: {}", path.display());
+                continue;
+            }
+            Err(source) => {
+                return Err(EnvFileError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        let name = path.to_string_lossy().into_owned();
+        for variable in parse(&name, contents)? {
+            let value = if variable.expand {
+                expand_env_vars(&variable.value, env)
+            } else {
+                variable.value
+            };
+            env.insert(variable.key, value);
+        }
+    }
+    Ok(())
+}
+
+/// Parses the `contents` of an env file. The `name` of the file is used in errors.
+fn parse(name: &str, contents: String) -> Result<Vec<EnvFileVariable>, EnvFileError> {
+    let error = |offset: usize, len: usize, message: &str| {
+        EnvFileError::Parse(Box::new(EnvFileParseError {
+            name: name.to_string(),
+            source_code: NamedSource::new(name, contents.clone()),
+            span: (offset, len).into(),
+            message: message.to_string(),
+        }))
+    };
+
+    let mut variables = Vec::new();
+    let mut offset = 0;
+    while offset < contents.len() {
+        let end = line_end(&contents, offset);
+        let line = &contents[offset..end];
+        let trimmed = line.trim_start();
+        let line_start = offset + (line.len() - trimmed.len());
+        if trimmed.trim_end().is_empty() || trimmed.starts_with('#') {
+            offset = next_line(&contents, end);
+            continue;
+        }
+
+        // Split the line in the key and the value.
+        let (key_start, rest) = match trimmed.strip_prefix("export ") {
+            Some(rest) => {
+                let rest_trimmed = rest.trim_start();
+                (
+                    line_start + (trimmed.len() - rest_trimmed.len()),
+                    rest_trimmed,
+                )
+            }
+            None => (line_start, trimmed),
+        };
+        let Some(eq) = rest.find('=') else {
+            return Err(error(
+                line_start,
+                line.trim().len(),
+                "expected a line of the form `KEY=VALUE`",
+            ));
+        };
+        let key = rest[..eq].trim_end();
+        if !is_valid_key(key) {
+            return Err(error(
+                key_start,
+                key.len().max(1),
+                "invalid variable name, names can only contain letters, digits, `_` and `.` and cannot start with a digit",
+            ));
+        }
+        let value_part = &rest[eq + 1..];
+        let value_start = key_start + eq + 1 + (value_part.len() - value_part.trim_start().len());
+
+        let (value, expand, value_end) = match contents[value_start..].chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let (value, closing) = parse_quoted(&contents, value_start + 1, quote)
+                    .ok_or_else(|| error(value_start, 1, "this quote is never closed"))?;
+                // Only a comment may follow the closing quote.
+                let after_end = line_end(&contents, closing + 1);
+                let after = contents[closing + 1..after_end].trim();
+                if !after.is_empty() && !after.starts_with('#') {
+                    let after_start = closing + 1 + contents[closing + 1..].find(after).unwrap();
+                    return Err(error(
+                        after_start,
+                        after.len(),
+                        "unexpected characters after the quoted value",
+                    ));
+                }
+                (value, quote == '"', after_end)
+            }
+            _ => {
+                let value = &contents[value_start..end];
+                let value = match value.find(" #") {
+                    Some(comment) => &value[..comment],
+                    None => value,
+                };
+                (value.trim().to_string(), true, end)
+            }
+        };
+
+        variables.push(EnvFileVariable {
+            key: key.to_string(),
+            value,
+            expand,
+        });
+        offset = next_line(&contents, value_end);
+    }
+
+    Ok(variables)
+}
+
+/// Parses a quoted value that starts at `start`, directly after the opening `quote`. Returns the
+/// unescaped value and the offset of the closing quote, or `None` if the quote is never closed.
+fn parse_quoted(contents: &str, start: usize, quote: char) -> Option<(String, usize)> {
+    let mut value = String::new();
+    let mut chars = contents[start..].char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some((value, start + idx)),
+            '\\' if quote == '"' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                c @ ('"' | '\\') => value.push(c),
+                c => {
+                    value.push('\\');
+                    value.push(c);
+                }
+            },
+            '\r' if contents[start + idx..].starts_with("\r\n") => {}
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Returns true if `key` is a valid name of a variable.
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Returns the offset of the end of the line that contains `offset`, excluding the line break.
+fn line_end(contents: &str, offset: usize) -> usize {
+    let end = contents[offset..]
+        .find('\n')
+        .map_or(contents.len(), |idx| offset + idx);
+    if end > offset && contents[..end].ends_with('\r') {
+        end - 1
+    } else {
+        end
+    }
+}
+
+/// Returns the offset of the start of the line after the line that ends at `line_end`.
+fn next_line(contents: &str, line_end: usize) -> usize {
+    match contents[line_end..].find('\n') {
+        Some(idx) => line_end + idx + 1,
+        None => contents.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_values(contents: &str) -> Vec<(String, String, bool)> {
+        parse(".env", contents.to_string())
+            .unwrap()
+            .into_iter()
+            .map(|variable| (variable.key, variable.value, variable.expand))
+            .collect()
+    }
+
+    /// Returns the text the error points at and the label of the error.
+    fn parse_error(contents: &str) -> (String, String) {
+        match parse(".env", contents.to_string()).unwrap_err() {
+            EnvFileError::Parse(err) => (
+                contents[err.span.offset()..err.span.offset() + err.span.len()].to_string(),
+                err.message,
+            ),
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let values = parse_values(
+            "# A comment\n\
+             \n\
+             PLAIN=value\n\
+             export EXPORTED = spaced value  # with a comment\n\
+             SINGLE='literal $HOME # not a comment'\n\
+             DOUBLE=\"line one\\nline \\\"two\\\"\" # comment\n\
+             MULTI=\"first\n\
+             second\"\n\
+             EMPTY=\n\
+             WINDOWS=crlf\r\n\
+             dotted.name=x",
+        );
+        assert_eq!(
+            values,
+            vec![
+                ("PLAIN".into(), "value".into(), true),
+                ("EXPORTED".into(), "spaced value".into(), true),
+                (
+                    "SINGLE".into(),
+                    "literal $HOME # not a comment".into(),
+                    false
+                ),
+                ("DOUBLE".into(), "line one\nline \"two\"".into(), true),
+                ("MULTI".into(), "first\nsecond".into(), true),
+                ("EMPTY".into(), "".into(), true),
+                ("WINDOWS".into(), "crlf".into(), true),
+                ("dotted.name".into(), "x".into(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_error("A=1\n  NOT A VARIABLE\n"),
+            (
+                "NOT A VARIABLE".into(),
+                "expected a line of the form `KEY=VALUE`".into()
+            )
+        );
+        assert_eq!(parse_error("A=1\nexport 1ST=first\n").0, "1ST".to_string());
+        assert_eq!(
+            parse_error("A=1\nB=\"never closed\nC=3\n"),
+            ("\"".into(), "this quote is never closed".into())
+        );
+        assert_eq!(
+            parse_error("A='quoted' trailing # comment\n"),
+            (
+                "trailing # comment".into(),
+                "unexpected characters after the quoted value".into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_env_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_path = dir.path().join(".env");
+        let local_path = dir.path().join(".env.local");
+        std::fs::write(
+            &env_path,
+            "DATABASE=postgres://${DB_HOST}/app\nTOKEN=public\n",
+        )
+        .unwrap();
+        std::fs::write(&local_path, "TOKEN=secret\nLITERAL='$DB_HOST'\n").unwrap();
+
+        let mut env = HashMap::from_iter([("DB_HOST".to_string(), "localhost".to_string())]);
+        read_env_files(
+            [env_path, dir.path().join("missing.env"), local_path],
+            &mut env,
+        )
+        .unwrap();
+
+        assert_eq!(env["DATABASE"], "postgres://localhost/app");
+        assert_eq!(env["TOKEN"], "secret");
+        assert_eq!(env["LITERAL"], "$DB_HOST");
+    }
+}
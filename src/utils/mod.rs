@@ -1,5 +1,6 @@
 mod barrier_cell;
 pub mod conda_environment_file;
+pub mod env_file;
 pub mod spanned;
 
 pub use barrier_cell::BarrierCell;
@@ -225,6 +225,37 @@ async fn add_pypi_functionality() {
     ));
 }
 
+/// Test that `pixi add --pypi --index` pins the package to the index in the manifest
+#[tokio::test]
+async fn add_pypi_with_index() {
+    let pixi = PixiControl::new().unwrap();
+
+    pixi.init().await.unwrap();
+
+    pixi.add("torch>=2")
+        .set_type(DependencyType::PypiDependency)
+        .with_index("https://download.pytorch.org/whl/cu121")
+        .without_lockfile_update()
+        .await
+        .unwrap();
+
+    let manifest = std::fs::read_to_string(pixi.manifest_path()).unwrap();
+    assert!(
+        manifest.contains(
+            r#"torch = { version = ">=2", index = "https://download.pytorch.org/whl/cu121" }"#
+        ),
+        "{manifest}"
+    );
+
+    // An index that is not an url is rejected when the manifest is read.
+    std::fs::write(
+        pixi.manifest_path(),
+        manifest.replace("https://download.pytorch.org/whl/cu121", "pytorch"),
+    )
+    .unwrap();
+    assert!(pixi.project().is_err());
+}
+
 /// Test the sdist support for pypi packages
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 #[cfg_attr(not(feature = "slow_integration_tests"), ignore)]
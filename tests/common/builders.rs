@@ -24,9 +24,9 @@
 //! ```
 
 use futures::FutureExt;
-use pixi::task::TaskName;
+use pixi::task::{TaskArg, TaskName, TaskShell};
 use pixi::{
-    cli::{add, init, install, project, task},
+    cli::{add, init, install, project, task, update, upgrade},
     DependencyType, SpecType,
 };
 use rattler_conda_types::Platform;
@@ -132,6 +132,12 @@ impl AddBuilder {
         self.args.platform.extend(platforms.iter());
         self
     }
+
+    /// Set the index the pypi dependencies are fetched from
+    pub fn with_index(mut self, index: &str) -> Self {
+        self.args.index = Some(Url::parse(index).unwrap());
+        self
+    }
 }
 
 impl IntoFuture for AddBuilder {
@@ -167,10 +173,67 @@ impl TaskAddBuilder {
         self
     }
 
+    /// With this environment variable
+    pub fn with_env(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.args.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// With these input globs
+    pub fn with_inputs(mut self, inputs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.args.inputs = Some(string_from_iter(inputs));
+        self
+    }
+
+    /// With these output globs
+    pub fn with_outputs(mut self, outputs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.args.outputs = Some(string_from_iter(outputs));
+        self
+    }
+
+    /// With this description
+    pub fn with_description(mut self, description: impl ToString) -> Self {
+        self.args.description = Some(description.to_string());
+        self
+    }
+
+    /// With this argument and optional default value
+    pub fn with_arg(mut self, name: impl ToString, default: Option<&str>) -> Self {
+        self.args.args.push(TaskArg {
+            name: name.to_string(),
+            default: default.map(ToString::to_string),
+        });
+        self
+    }
+
+    /// With this timeout, e.g. `10m`
+    pub fn with_timeout(mut self, timeout: &str) -> Self {
+        self.args.timeout = Some(timeout.parse().expect("invalid timeout"));
+        self
+    }
+
+    /// With this number of retries
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.args.retries = Some(retries);
+        self
+    }
+
+    /// Allow this task to fail
+    pub fn with_allow_failure(mut self) -> Self {
+        self.args.allow_failure = true;
+        self
+    }
+
+    /// Run the command with this interpreter
+    pub fn with_shell(mut self, shell: TaskShell) -> Self {
+        self.args.shell = Some(shell);
+        self
+    }
+
     /// Execute the CLI command
     pub fn execute(self) -> miette::Result<()> {
         task::execute(task::Args {
-            operation: task::Operation::Add(self.args),
+            operation: task::Operation::Add(Box::new(self.args)),
             manifest_path: self.manifest_path,
         })
     }
@@ -188,6 +251,12 @@ impl TaskAliasBuilder {
         self
     }
 
+    /// With this description
+    pub fn with_description(mut self, description: impl ToString) -> Self {
+        self.args.description = Some(description.to_string());
+        self
+    }
+
     /// Execute the CLI command
     pub fn execute(self) -> miette::Result<()> {
         task::execute(task::Args {
@@ -197,6 +266,57 @@ impl TaskAliasBuilder {
     }
 }
 
+pub struct TaskUpdateBuilder {
+    pub manifest_path: Option<PathBuf>,
+    pub args: task::UpdateArgs,
+}
+
+impl TaskUpdateBuilder {
+    /// Replace the command of the task
+    pub fn with_cmd(mut self, cmd: impl ToString) -> Self {
+        self.args.cmd = Some(cmd.to_string());
+        self
+    }
+
+    /// Add these tasks to the dependencies of the task
+    pub fn with_add_depends_on(mut self, depends: Vec<TaskName>) -> Self {
+        self.args.add_depends_on = depends;
+        self
+    }
+
+    /// Remove these tasks from the dependencies of the task
+    pub fn with_remove_depends_on(mut self, depends: Vec<TaskName>) -> Self {
+        self.args.remove_depends_on = depends;
+        self
+    }
+
+    /// Set the working directory of the task
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.args.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Set this environment variable
+    pub fn with_env(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.args.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Remove these fields from the task
+    pub fn with_unset(mut self, fields: Vec<task::TaskField>) -> Self {
+        self.args.unset = fields;
+        self
+    }
+
+    /// Execute the CLI command
+    pub fn execute(self) -> miette::Result<()> {
+        task::execute(task::Args {
+            operation: task::Operation::Update(Box::new(self.args)),
+            manifest_path: self.manifest_path,
+        })
+    }
+}
+
 pub struct ProjectChannelAddBuilder {
     pub manifest_path: Option<PathBuf>,
     pub args: project::channel::add::Args,
@@ -252,3 +372,67 @@ impl IntoFuture for InstallBuilder {
         install::execute(self.args).boxed_local()
     }
 }
+
+pub struct UpdateBuilder {
+    pub args: update::Args,
+}
+
+impl UpdateBuilder {
+    /// Updates the specified package
+    pub fn with_package(mut self, name: impl Into<String>) -> Self {
+        self.args.packages.push(name.into());
+        self
+    }
+
+    /// Only update the specified environment
+    pub fn with_environment(mut self, name: impl Into<String>) -> Self {
+        self.args.environment.push(name.into());
+        self
+    }
+
+    /// Only update the specified platform
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.args.platform.push(platform);
+        self
+    }
+}
+
+impl IntoFuture for UpdateBuilder {
+    type Output = miette::Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'static>>;
+    fn into_future(self) -> Self::IntoFuture {
+        update::execute(self.args).boxed_local()
+    }
+}
+
+pub struct UpgradeBuilder {
+    pub args: upgrade::Args,
+}
+
+impl UpgradeBuilder {
+    /// Upgrades the specified dependency
+    pub fn with_package(mut self, name: impl Into<String>) -> Self {
+        self.args.packages.push(name.into());
+        self
+    }
+
+    /// Upgrades the dependencies of the specified feature
+    pub fn with_feature(mut self, name: impl Into<String>) -> Self {
+        self.args.feature = Some(name.into());
+        self
+    }
+
+    /// Only show the upgraded specs without modifying the project
+    pub fn with_dry_run(mut self) -> Self {
+        self.args.dry_run = true;
+        self
+    }
+}
+
+impl IntoFuture for UpgradeBuilder {
+    type Output = miette::Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'static>>;
+    fn into_future(self) -> Self::IntoFuture {
+        upgrade::execute(self.args).boxed_local()
+    }
+}
@@ -5,26 +5,24 @@ pub mod package_database;
 
 use crate::common::builders::{
     AddBuilder, InitBuilder, InstallBuilder, ProjectChannelAddBuilder, TaskAddBuilder,
-    TaskAliasBuilder,
+    TaskAliasBuilder, TaskUpdateBuilder, UpdateBuilder, UpgradeBuilder,
 };
 use pixi::{
     cli::{
         add, init,
         install::Args,
         project, run,
-        task::{self, AddArgs, AliasArgs},
+        task::{self, AddArgs, AliasArgs, UpdateArgs},
+        update, upgrade,
     },
-    consts, EnvironmentName, ExecutableTask, Project, RunOutput, SearchEnvironments, TaskGraph,
-    TaskGraphError,
+    consts, Project, RunOutput,
 };
 use rattler_conda_types::{MatchSpec, Platform};
 
 use miette::{Context, Diagnostic, IntoDiagnostic};
-use pixi::cli::run::get_task_env;
 use pixi::cli::LockFileUsageArgs;
 use pixi::task::TaskName;
 use pixi::FeatureName;
-use pixi::TaskExecutionError;
 use pixi::UpdateLockFileOptions;
 use rattler_lock::{LockFile, Package};
 use std::{
@@ -157,7 +155,7 @@ impl PixiControl {
     /// Creates a new PixiControl instance from an existing manifest
     pub fn from_manifest(manifest: &str) -> miette::Result<PixiControl> {
         let pixi = Self::new()?;
-        std::fs::write(&pixi.manifest_path(), manifest)
+        std::fs::write(pixi.manifest_path(), manifest)
             .into_diagnostic()
             .context("failed to write pixi.toml")?;
         Ok(pixi)
@@ -216,6 +214,7 @@ impl PixiControl {
                 no_lockfile_update: false,
                 platform: Default::default(),
                 pypi: false,
+                index: None,
                 feature: None,
             },
         }
@@ -229,6 +228,7 @@ impl PixiControl {
                 channel: vec![],
                 no_install: true,
                 feature: None,
+                priority: None,
             },
         }
     }
@@ -236,64 +236,11 @@ impl PixiControl {
     /// Run a command
     pub async fn run(&self, mut args: run::Args) -> miette::Result<RunOutput> {
         args.manifest_path = args.manifest_path.or_else(|| Some(self.manifest_path()));
-
-        // Load the project
-        let project = self.project()?;
-
-        // Extract the passed in environment name.
-        let explicit_environment = args
-            .environment
-            .map(|n| EnvironmentName::from_str(n.as_str()))
-            .transpose()?
-            .map(|n| {
-                project
-                    .environment(&n)
-                    .ok_or_else(|| miette::miette!("unknown environment '{n}'"))
-            })
-            .transpose()?;
-
-        // Ensure the lock-file is up-to-date
-        let mut lock_file = project
-            .up_to_date_lock_file(UpdateLockFileOptions {
-                lock_file_usage: args.lock_file_usage.into(),
-                ..UpdateLockFileOptions::default()
-            })
-            .await?;
-
-        // Create a task graph from the command line arguments.
-        let search_env = SearchEnvironments::from_opt_env(
-            &project,
-            explicit_environment,
-            Some(Platform::current()),
-        );
-        let task_graph = TaskGraph::from_cmd_args(&project, &search_env, args.task)
-            .map_err(RunError::TaskGraphError)?;
-
-        // Iterate over all tasks in the graph and execute them.
-        let mut task_env = None;
-        let mut result = RunOutput::default();
-        for task_id in task_graph.topological_order() {
-            let task = ExecutableTask::from_task_graph(&task_graph, task_id);
-
-            // Construct the task environment if not already created.
-            let task_env = match task_env.as_ref() {
-                None => {
-                    let env = get_task_env(&mut lock_file, &task.run_environment).await?;
-                    task_env.insert(env) as &_
-                }
-                Some(task_env) => task_env,
-            };
-
-            let output = task.execute_with_pipes(&task_env, None).await?;
-            result.stdout.push_str(&output.stdout);
-            result.stderr.push_str(&output.stderr);
-            result.exit_code = output.exit_code;
-            if output.exit_code != 0 {
-                return Err(RunError::NonZeroExitCode(output.exit_code).into());
-            }
+        let output = run::execute_captured(args).await?;
+        if output.exit_code != 0 {
+            return Err(RunError::NonZeroExitCode(output.exit_code).into());
         }
-
-        return Ok(result);
+        Ok(output)
     }
 
     /// Returns a [`InstallBuilder`]. To execute the command and await the result call `.await` on the return value.
@@ -310,6 +257,27 @@ impl PixiControl {
         }
     }
 
+    /// Returns a [`UpdateBuilder`]. To execute the command and await the result call `.await` on the return value.
+    pub fn update(&self) -> UpdateBuilder {
+        UpdateBuilder {
+            args: update::Args {
+                manifest_path: Some(self.manifest_path()),
+                ..update::Args::default()
+            },
+        }
+    }
+
+    /// Returns a [`UpgradeBuilder`]. To execute the command and await the result call `.await` on the return value.
+    pub fn upgrade(&self) -> UpgradeBuilder {
+        UpgradeBuilder {
+            args: upgrade::Args {
+                manifest_path: Some(self.manifest_path()),
+                no_install: true,
+                ..upgrade::Args::default()
+            },
+        }
+    }
+
     /// Load the current lock-file.
     ///
     /// If you want to lock-file to be up-to-date with the project call [`Self::up_to_date_lock_file`].
@@ -355,6 +323,17 @@ impl TasksControl<'_> {
                 platform,
                 feature,
                 cwd: None,
+                env: Default::default(),
+                env_file: vec![],
+                inputs: None,
+                outputs: None,
+                description: None,
+                args: vec![],
+                timeout: None,
+                retries: None,
+                allow_failure: false,
+                environment: None,
+                shell: None,
             },
         }
     }
@@ -376,6 +355,42 @@ impl TasksControl<'_> {
         })
     }
 
+    /// Update a task
+    pub fn update(
+        &self,
+        name: TaskName,
+        platform: Option<Platform>,
+        feature_name: FeatureName,
+    ) -> TaskUpdateBuilder {
+        let feature = feature_name.name().map(|s| s.to_string());
+        TaskUpdateBuilder {
+            manifest_path: Some(self.pixi.manifest_path()),
+            args: UpdateArgs {
+                name,
+                platform,
+                feature,
+                cmd: None,
+                add_depends_on: vec![],
+                remove_depends_on: vec![],
+                cwd: None,
+                env: vec![],
+                remove_env: vec![],
+                env_file: None,
+                inputs: None,
+                outputs: None,
+                description: None,
+                args: vec![],
+                remove_arg: vec![],
+                timeout: None,
+                retries: None,
+                allow_failure: None,
+                environment: None,
+                shell: None,
+                unset: vec![],
+            },
+        }
+    }
+
     /// Alias one or multiple tasks
     pub fn alias(&self, name: TaskName, platform: Option<Platform>) -> TaskAliasBuilder {
         TaskAliasBuilder {
@@ -384,6 +399,8 @@ impl TasksControl<'_> {
                 platform,
                 alias: name,
                 depends_on: vec![],
+                description: None,
+                environment: None,
             },
         }
     }
@@ -415,10 +432,6 @@ impl IntoMatchSpec for MatchSpec {
 
 #[derive(Error, Debug, Diagnostic)]
 enum RunError {
-    #[error(transparent)]
-    TaskGraphError(#[from] TaskGraphError),
-    #[error(transparent)]
-    ExecutionError(#[from] TaskExecutionError),
     #[error("the task executed with a non-zero exit code {0}")]
     NonZeroExitCode(i32),
 }
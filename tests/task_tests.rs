@@ -1,11 +1,14 @@
 use crate::common::PixiControl;
 use pixi::cli::run::Args;
-use pixi::task::TaskName;
+use pixi::cli::task::TaskField;
+use pixi::task::{Dependency, TaskName, TaskShell};
 use pixi::FeatureName;
 use pixi::{CmdArgs, Task};
 use rattler_conda_types::Platform;
 use std::fs;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 mod common;
 
@@ -82,7 +85,7 @@ pub async fn add_command_types() {
     let project = pixi.project().unwrap();
     let tasks = project.default_environment().tasks(None, true).unwrap();
     let task = tasks.get(&<TaskName>::from("testing")).unwrap();
-    assert!(matches!(task, Task::Alias(a) if a.depends_on.get(0).unwrap().as_str() == "test"));
+    assert!(matches!(task, Task::Alias(a) if a.depends_on.first().unwrap().as_str() == "test"));
 }
 
 #[tokio::test]
@@ -211,3 +214,582 @@ async fn test_cwd() {
         .await
         .is_err());
 }
+
+#[tokio::test]
+async fn test_task_with_env() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("hello".into(), None, FeatureName::Default)
+        .with_commands(["echo $GREETING $NAME"])
+        .with_env("GREETING", "hello")
+        .with_env("NAME", "${GREETING}-world")
+        .execute()
+        .unwrap();
+
+    let project = pixi.project().unwrap();
+    let tasks = project.default_environment().tasks(None, true).unwrap();
+    let task = tasks.get(&<TaskName>::from("hello")).unwrap();
+    let env = task.env().unwrap();
+    assert_eq!(env.get("GREETING").map(String::as_str), Some("hello"));
+    assert_eq!(
+        env.get("NAME").map(String::as_str),
+        Some("${GREETING}-world")
+    );
+
+    let result = pixi
+        .run(Args {
+            task: vec!["hello".to_string()],
+            manifest_path: None,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout, "hello hello-world\n");
+}
+
+#[tokio::test]
+async fn test_task_caching() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("copy".into(), None, FeatureName::Default)
+        .with_commands(["cp input.txt output.txt && echo copied"])
+        .with_inputs(["*.txt"])
+        .with_outputs(["output.txt"])
+        .execute()
+        .unwrap();
+
+    let input = pixi.project_path().join("input.txt");
+    let output = pixi.project_path().join("output.txt");
+    fs::write(&input, "first").unwrap();
+
+    let run = || {
+        pixi.run(Args {
+            task: vec!["copy".to_string()],
+            manifest_path: None,
+            ..Default::default()
+        })
+    };
+
+    // The first time the task is executed
+    assert_eq!(run().await.unwrap().stdout, "copied\n");
+    assert_eq!(fs::read_to_string(&output).unwrap(), "first");
+
+    // Nothing changed so the task is skipped
+    assert_eq!(run().await.unwrap().stdout, "");
+
+    // Changing an input reruns the task
+    fs::write(&input, "second").unwrap();
+    assert_eq!(run().await.unwrap().stdout, "copied\n");
+    assert_eq!(fs::read_to_string(&output).unwrap(), "second");
+
+    // Removing an output reruns the task
+    fs::remove_file(&output).unwrap();
+    assert_eq!(run().await.unwrap().stdout, "copied\n");
+    assert!(output.is_file());
+
+    // A task without inputs always runs, even if its outputs did not change
+    pixi.tasks()
+        .add("generate".into(), None, FeatureName::Default)
+        .with_commands(["echo generated"])
+        .with_outputs(["output.txt"])
+        .execute()
+        .unwrap();
+    let run_generate = || {
+        pixi.run(Args {
+            task: vec!["generate".to_string()],
+            manifest_path: None,
+            ..Default::default()
+        })
+    };
+    assert_eq!(run_generate().await.unwrap().stdout, "generated\n");
+    assert_eq!(run_generate().await.unwrap().stdout, "generated\n");
+}
+
+#[tokio::test]
+async fn test_task_description() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("build".into(), None, FeatureName::Default)
+        .with_commands(["make"])
+        .with_description("Build the project")
+        .execute()
+        .unwrap();
+
+    pixi.tasks()
+        .alias("all".into(), None)
+        .with_depends_on(vec!["build".into()])
+        .with_description("Run everything")
+        .execute()
+        .unwrap();
+
+    let project = pixi.project().unwrap();
+    let tasks = project.default_environment().tasks(None, true).unwrap();
+    let build = tasks.get(&<TaskName>::from("build")).unwrap();
+    assert_eq!(build.description(), Some("Build the project"));
+    assert_eq!(build.as_single_command().as_deref(), Some("make"));
+    let all = tasks.get(&<TaskName>::from("all")).unwrap();
+    assert_eq!(all.description(), Some("Run everything"));
+    assert_eq!(
+        all.depends_on(),
+        [Dependency::from(<TaskName>::from("build"))]
+    );
+}
+
+#[tokio::test]
+async fn test_run_tasks_concurrently() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("first".into(), None, FeatureName::Default)
+        .with_commands(["echo first > first.txt"])
+        .execute()
+        .unwrap();
+    pixi.tasks()
+        .add("second".into(), None, FeatureName::Default)
+        .with_commands(["echo second > second.txt"])
+        .execute()
+        .unwrap();
+    pixi.tasks()
+        .add("both".into(), None, FeatureName::Default)
+        .with_commands(["cat first.txt second.txt > both.txt"])
+        .with_depends_on(vec!["first".into(), "second".into()])
+        .execute()
+        .unwrap();
+
+    pixi.run(Args {
+        task: vec!["both".to_string()],
+        jobs: NonZeroUsize::new(2),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // The dependencies finished before the task that depends on them started.
+    assert_eq!(
+        fs::read_to_string(pixi.project_path().join("both.txt")).unwrap(),
+        "first\nsecond\n"
+    );
+}
+
+#[tokio::test]
+async fn test_run_task_in_all_environments() {
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+    [project]
+    name = "matrix"
+    channels = []
+    platforms = ["{platform}"]
+
+    [feature.py39.dependencies]
+    [feature.py310.dependencies]
+    [feature.docs.tasks]
+    build-docs = "echo docs"
+
+    [tasks]
+    test = "echo $PIXI_ENVIRONMENT_NAME > $PIXI_ENVIRONMENT_NAME.txt"
+
+    [environments]
+    py39 = ["py39"]
+    py310 = ["py310"]
+    docs = ["docs"]
+    "#,
+        platform = Platform::current()
+    ))
+    .unwrap();
+
+    pixi.run(Args {
+        task: vec!["test".to_string()],
+        all_environments: true,
+        jobs: NonZeroUsize::new(2),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // The task ran once in every environment
+    for environment in ["default", "py39", "py310", "docs"] {
+        assert_eq!(
+            fs::read_to_string(pixi.project_path().join(format!("{environment}.txt")))
+                .unwrap()
+                .trim(),
+            environment
+        );
+    }
+
+    // Only the environments that define the task are selected
+    let result = pixi
+        .run(Args {
+            task: vec!["build-docs".to_string()],
+            all_environments: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.stdout, "docs\n");
+
+    // Or the environments that are selected explicitly
+    let result = pixi
+        .run(Args {
+            task: vec!["echo".to_string(), "$PIXI_ENVIRONMENT_NAME".to_string()],
+            environment: vec!["py310".to_string(), "py39".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.stdout, "py310\npy39\n");
+}
+
+#[tokio::test]
+async fn test_task_args() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("greet".into(), None, FeatureName::Default)
+        .with_commands(["echo {{ greeting }} {{ name }}"])
+        .with_arg("name", None)
+        .with_arg("greeting", Some("hello"))
+        .execute()
+        .unwrap();
+
+    let run = |args: &[&str]| {
+        pixi.run(Args {
+            task: args.iter().map(|arg| arg.to_string()).collect(),
+            manifest_path: None,
+            ..Default::default()
+        })
+    };
+
+    assert_eq!(
+        run(&["greet", "world"]).await.unwrap().stdout,
+        "hello world\n"
+    );
+    assert_eq!(
+        run(&["greet", "pixi", "hi"]).await.unwrap().stdout,
+        "hi pixi\n"
+    );
+
+    // Values with spaces or shell syntax are passed as a single argument.
+    assert_eq!(
+        run(&["greet", "my world; echo injected", "hi"])
+            .await
+            .unwrap()
+            .stdout,
+        "hi my world; echo injected\n"
+    );
+
+    // The argument without a default is required
+    let err = run(&["greet"]).await.unwrap_err();
+    assert!(
+        err.to_string().contains("'name'"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn test_task_retries_timeout_and_allow_failure() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    // Fails the first time it is executed and succeeds the second time.
+    pixi.tasks()
+        .add("flaky".into(), None, FeatureName::Default)
+        .with_commands(["cat attempt.txt || (echo 1 > attempt.txt && exit 1)"])
+        .with_retries(1)
+        .execute()
+        .unwrap();
+    pixi.tasks()
+        .add("slow".into(), None, FeatureName::Default)
+        .with_commands(["sleep 30"])
+        .with_timeout("200ms")
+        .with_allow_failure()
+        .execute()
+        .unwrap();
+    pixi.tasks()
+        .add("report".into(), None, FeatureName::Default)
+        .with_commands(["echo finished > report.txt"])
+        .with_depends_on(vec!["flaky".into(), "slow".into()])
+        .execute()
+        .unwrap();
+
+    let project = pixi.project().unwrap();
+    let tasks = project.default_environment().tasks(None, true).unwrap();
+    let slow = tasks.get(&<TaskName>::from("slow")).unwrap();
+    assert_eq!(slow.timeout(), Some(Duration::from_millis(200)));
+    assert!(slow.allow_failure());
+
+    let start = Instant::now();
+    let output = pixi
+        .run(Args {
+            task: vec!["report".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // The flaky task succeeded the second time, the slow task was killed and the task that
+    // depends on them still ran.
+    assert_eq!(output.stdout, "1\n");
+    assert!(start.elapsed() < Duration::from_secs(20));
+    assert_eq!(
+        fs::read_to_string(pixi.project_path().join("report.txt")).unwrap(),
+        "finished\n"
+    );
+}
+
+#[tokio::test]
+async fn test_update_task() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("lint".into(), None, FeatureName::Default)
+        .with_commands(["ruff check ."])
+        .execute()
+        .unwrap();
+    pixi.tasks()
+        .add("build".into(), None, FeatureName::Default)
+        .with_commands(["make"])
+        .with_cwd(PathBuf::from("src"))
+        .execute()
+        .unwrap();
+
+    // Change the command and add a dependency and an environment variable
+    pixi.tasks()
+        .update("build".into(), None, FeatureName::Default)
+        .with_cmd("make all")
+        .with_add_depends_on(vec!["lint".into()])
+        .with_env("CC", "clang")
+        .execute()
+        .unwrap();
+    let project = pixi.project().unwrap();
+    let tasks = project.default_environment().tasks(None, true).unwrap();
+    let Task::Execute(execute) = tasks.get(&<TaskName>::from("build")).unwrap() else {
+        panic!("expected an execute task");
+    };
+    assert_eq!(execute.cmd.as_single().as_ref(), "make all");
+    assert_eq!(execute.cwd, Some(PathBuf::from("src")));
+    assert_eq!(
+        execute.depends_on,
+        vec![Dependency::from(TaskName::from("lint"))]
+    );
+    assert_eq!(
+        execute.env.as_ref().unwrap().get("CC").map(String::as_str),
+        Some("clang")
+    );
+
+    // Removing everything except the command turns it back into a plain task
+    pixi.tasks()
+        .update("build".into(), None, FeatureName::Default)
+        .with_remove_depends_on(vec!["lint".into()])
+        .with_unset(vec![TaskField::Cwd, TaskField::Env])
+        .execute()
+        .unwrap();
+    let project = pixi.project().unwrap();
+    let tasks = project.default_environment().tasks(None, true).unwrap();
+    let task = tasks.get(&<TaskName>::from("build")).unwrap();
+    assert!(matches!(task, Task::Plain(cmd) if cmd == "make all"));
+
+    // Removing a dependency that doesn't exist fails
+    assert!(pixi
+        .tasks()
+        .update("build".into(), None, FeatureName::Default)
+        .with_remove_depends_on(vec!["lint".into()])
+        .execute()
+        .is_err());
+
+    // Updating a task that doesn't exist fails
+    assert!(pixi
+        .tasks()
+        .update("test".into(), None, FeatureName::Default)
+        .with_cmd("pytest")
+        .execute()
+        .is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_task_shell() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    // Functions, arrays and heredocs are not supported by the built-in shell.
+    pixi.tasks()
+        .add("script".into(), None, FeatureName::Default)
+        .with_commands([r#"greet() { echo "hello $1"; }
+names=(pixi "$1")
+for name in "${names[@]}"; do greet "$name"; done
+cat <<END
+from $0
+END"#])
+        .with_shell(TaskShell::Bash)
+        .execute()
+        .unwrap();
+    pixi.tasks()
+        .add("strict".into(), None, FeatureName::Default)
+        .with_commands(["set -e\nfalse\necho unreachable > strict.txt"])
+        .with_shell(TaskShell::Bash)
+        .execute()
+        .unwrap();
+    pixi.tasks()
+        .add("env".into(), None, FeatureName::Default)
+        .with_commands([r#"env | grep -c '^PIXI_TASK_SCRIPT=' || true"#])
+        .with_shell(TaskShell::Bash)
+        .execute()
+        .unwrap();
+
+    let project = pixi.project().unwrap();
+    let tasks = project.default_environment().tasks(None, true).unwrap();
+    let script = tasks.get(&<TaskName>::from("script")).unwrap();
+    assert_eq!(script.shell(), Some(TaskShell::Bash));
+
+    let run = |args: &[&str]| {
+        pixi.run(Args {
+            task: args.iter().map(|arg| arg.to_string()).collect(),
+            manifest_path: None,
+            ..Default::default()
+        })
+    };
+
+    // The interpreter is never taken from outside of the environment.
+    let err = run(&["script", "world"]).await.unwrap_err();
+    assert!(
+        err.to_string().contains("bash is not installed"),
+        "unexpected error: {err}"
+    );
+
+    // Provide the interpreter from the system inside the prefix.
+    let bin_dir = project.default_environment().dir().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let system_bash = std::env::split_paths(&std::env::var_os("PATH").unwrap())
+        .map(|dir| dir.join("bash"))
+        .find(|path| path.is_file())
+        .unwrap();
+    std::os::unix::fs::symlink(system_bash, bin_dir.join("bash")).unwrap();
+
+    assert_eq!(
+        run(&["script", "world"]).await.unwrap().stdout,
+        "hello pixi\nhello world\nfrom script\n"
+    );
+
+    // The script stops at the first failing command.
+    assert!(run(&["strict"]).await.is_err());
+    assert!(!pixi.project_path().join("strict.txt").exists());
+
+    // The script is not passed on to the processes that the script starts.
+    assert_eq!(run(&["env"]).await.unwrap().stdout, "0\n");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_task_shell_pwsh_arguments() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+    pixi.tasks()
+        .add("greet".into(), None, FeatureName::Default)
+        .with_commands(["Write-Output \"hello $($args[0])\""])
+        .with_shell(TaskShell::Pwsh)
+        .execute()
+        .unwrap();
+
+    // A stand-in for PowerShell that prints the arguments it was started with.
+    let project = pixi.project().unwrap();
+    let bin_dir = project.default_environment().dir().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let pwsh = bin_dir.join("pwsh");
+    fs::write(&pwsh, "#!/bin/sh\nprintf '%s\\n' \"$@\"\n").unwrap();
+    fs::set_permissions(&pwsh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = pixi
+        .run(Args {
+            task: vec!["greet".to_string(), "it's; rm -rf ~".to_string()],
+            manifest_path: None,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .stdout;
+
+    // The script is run from a file, so the argument is bound to `$args` instead of being
+    // appended to the script.
+    let args = output.lines().collect::<Vec<_>>();
+    assert_eq!(args[..2], ["-NoProfile", "-File"]);
+    assert_eq!(args[3], "it's; rm -rf ~");
+    assert_eq!(
+        fs::read_to_string(args[2]).unwrap(),
+        "Write-Output \"hello $($args[0])\""
+    );
+}
+
+#[tokio::test]
+async fn test_task_env_file() {
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+    [project]
+    name = "env-files"
+    channels = []
+    platforms = ["{platform}"]
+    env-file = [".env", ".env.local"]
+
+    [feature.staging]
+    env-file = [".env.staging"]
+
+    [tasks]
+    show = "echo $DB $TOKEN $MODE"
+    override = {{ cmd = "echo $DB $TOKEN $MODE", env_file = [".env.task"], env = {{ MODE = "task" }} }}
+
+    [environments]
+    staging = ["staging"]
+    "#,
+        platform = Platform::current()
+    ))
+    .unwrap();
+    let root = pixi.project_path();
+    fs::write(root.join(".env"), "DB=postgres\nTOKEN=public\nMODE=dev\n").unwrap();
+    fs::write(root.join(".env.local"), "TOKEN=secret\n").unwrap();
+    fs::write(root.join(".env.staging"), "MODE=staging\n").unwrap();
+    fs::write(root.join(".env.task"), "DB=sqlite\nMODE=file\n").unwrap();
+
+    let run = |task: &str, environment: &str| {
+        pixi.run(Args {
+            task: vec![task.to_string()],
+            environment: vec![environment.to_string()],
+            ..Default::default()
+        })
+    };
+
+    // Later files override earlier files, the files of features override those of the project.
+    assert_eq!(
+        run("show", "default").await.unwrap().stdout,
+        "postgres secret dev\n"
+    );
+    assert_eq!(
+        run("show", "staging").await.unwrap().stdout,
+        "postgres secret staging\n"
+    );
+
+    // The files of a task override those of the environment, the `env` of the task overrides all.
+    assert_eq!(
+        run("override", "default").await.unwrap().stdout,
+        "sqlite secret task\n"
+    );
+
+    // Parse errors are reported
+    fs::write(root.join(".env.local"), "TOKEN=secret\nNOT A VARIABLE\n").unwrap();
+    let err = run("show", "default").await.unwrap_err();
+    assert!(
+        err.to_string().contains("failed to parse env file"),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,76 @@
+use crate::common::{
+    package_database::{Package, PackageDatabase},
+    LockFileExt, PixiControl,
+};
+use rattler_conda_types::Platform;
+use tempfile::TempDir;
+use url::Url;
+
+mod common;
+
+#[tokio::test]
+async fn update_selected_packages() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    package_database.add_package(Package::build("bar", "1").finish());
+
+    // Write the repodata to disk
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let channel = Url::from_file_path(channel_dir.path()).unwrap();
+    let platform = Platform::current();
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+    [project]
+    name = "test-update"
+    channels = ["{channel}"]
+    platforms = ["{platform}"]
+
+    [dependencies]
+    foo = "*"
+    bar = "*"
+    "#
+    ))
+    .unwrap();
+
+    let lock_file = pixi.up_to_date_lock_file().await.unwrap();
+    assert!(lock_file.contains_match_spec("default", platform, "foo ==1"));
+    assert!(lock_file.contains_match_spec("default", platform, "bar ==1"));
+
+    // Publish newer versions of both packages
+    package_database.add_package(Package::build("foo", "2").finish());
+    package_database.add_package(Package::build("bar", "2").finish());
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    // Regular locking keeps the locked versions
+    let lock_file = pixi.up_to_date_lock_file().await.unwrap();
+    assert!(lock_file.contains_match_spec("default", platform, "foo ==1"));
+
+    // Only update foo
+    pixi.update().with_package("foo").await.unwrap();
+    let lock_file = pixi.lock_file().await.unwrap();
+    assert!(
+        lock_file.contains_match_spec("default", platform, "foo ==2"),
+        "foo should have been updated"
+    );
+    assert!(
+        lock_file.contains_match_spec("default", platform, "bar ==1"),
+        "bar should not have been updated"
+    );
+
+    // Unknown packages are rejected
+    assert!(pixi.update().with_package("baz").await.is_err());
+
+    // Update everything
+    pixi.update().await.unwrap();
+    let lock_file = pixi.lock_file().await.unwrap();
+    assert!(lock_file.contains_match_spec("default", platform, "foo ==2"));
+    assert!(lock_file.contains_match_spec("default", platform, "bar ==2"));
+}
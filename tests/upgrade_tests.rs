@@ -0,0 +1,99 @@
+use crate::common::{
+    package_database::{Package, PackageDatabase},
+    LockFileExt, PixiControl,
+};
+use pixi::SpecType;
+use rattler_conda_types::{PackageName, Platform};
+use std::str::FromStr;
+use tempfile::TempDir;
+use url::Url;
+
+mod common;
+
+/// Returns the version spec of a run dependency of the default feature.
+fn run_dependency_version(pixi: &PixiControl, name: &str) -> String {
+    let project = pixi.project().unwrap();
+    let name = PackageName::from_str(name).unwrap();
+    project
+        .dependencies(Some(SpecType::Run), None)
+        .into_specs()
+        .find(|(spec_name, _)| spec_name == &name)
+        .unwrap()
+        .1
+        .to_string()
+}
+
+#[tokio::test]
+async fn upgrade_dependencies() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1.0.0").finish());
+    package_database.add_package(Package::build("bar", "1.0.0").finish());
+    package_database.add_package(
+        Package::build("baz", "1")
+            .with_dependency("foo <3")
+            .finish(),
+    );
+
+    // Write the repodata to disk
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let channel = Url::from_file_path(channel_dir.path()).unwrap();
+    let platform = Platform::current();
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+    [project]
+    name = "test-upgrade"
+    channels = ["{channel}"]
+    platforms = ["{platform}"]
+
+    [dependencies]
+    foo = ">=1.0.0,<2"
+    bar = {{ version = "==1.0.0" }}
+    baz = "*"
+    "#
+    ))
+    .unwrap();
+    pixi.up_to_date_lock_file().await.unwrap();
+
+    // Publish newer versions
+    package_database.add_package(Package::build("foo", "2.1.0").finish());
+    package_database.add_package(Package::build("foo", "3.0.0").finish());
+    package_database.add_package(Package::build("bar", "1.1.0").finish());
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    // A dry-run does not modify the project
+    let manifest = std::fs::read_to_string(pixi.manifest_path()).unwrap();
+    pixi.upgrade().with_dry_run().await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(pixi.manifest_path()).unwrap(),
+        manifest
+    );
+
+    // Only upgrade bar
+    pixi.upgrade().with_package("bar").await.unwrap();
+    assert_eq!(run_dependency_version(&pixi, "bar"), "==1.1.0");
+    assert_eq!(run_dependency_version(&pixi, "foo"), ">=1.0.0,<2");
+
+    // Upgrade everything, `baz` restricts `foo` to versions before 3 so `foo` is upgraded to
+    // 2.1.0 and keeps its pinning style.
+    pixi.upgrade().await.unwrap();
+    assert_eq!(run_dependency_version(&pixi, "foo"), ">=2.1.0,<3");
+    assert_eq!(run_dependency_version(&pixi, "baz"), "*");
+    let lock_file = pixi.lock_file().await.unwrap();
+    assert!(lock_file.contains_match_spec("default", platform, "foo ==2.1.0"));
+    assert!(lock_file.contains_match_spec("default", platform, "bar ==1.1.0"));
+
+    // Table entries keep their format
+    let manifest = std::fs::read_to_string(pixi.manifest_path()).unwrap();
+    assert!(manifest.contains(r#"bar = { version = "==1.1.0" }"#));
+
+    // Unknown dependencies are rejected
+    assert!(pixi.upgrade().with_package("qux").await.is_err());
+}